@@ -1,3 +1,4 @@
+use crate::{NEError, NEResult};
 use std::any::TypeId;
 
 #[derive(Clone, Debug)]
@@ -12,6 +13,26 @@ pub enum ATTRType {
    F64,
 }
 
+//how the shader sees the data, floats, normalized ints mapped to 0..1 or -1..1, pure ints or doubles
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ATTRKind {
+   Float,
+   Normalized,
+   Integer,
+   Double,
+}
+//attributes start as Float whatever their type, the other kinds are opt-in through set_kind
+impl ATTRKind {
+   //any type converts to float, the rest only make sense for their own types
+   pub(crate) fn fits(&self, typ: &ATTRType) -> bool {
+      match self {
+         ATTRKind::Float => true,
+         ATTRKind::Double => matches!(typ, ATTRType::F64),
+         ATTRKind::Normalized | ATTRKind::Integer => !matches!(typ, ATTRType::F32 | ATTRType::F64),
+      }
+   }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct ATTRInfo {
    pub(crate) typ: ATTRType,
@@ -19,6 +40,7 @@ pub(crate) struct ATTRInfo {
    pub(crate) exists: bool,
    pub(crate) byte_count: usize,
    pub(crate) elem_count: usize,
+   pub(crate) kind: ATTRKind,
 }
 impl ATTRInfo {
    pub(crate) fn empty() -> ATTRInfo {
@@ -28,6 +50,7 @@ impl ATTRInfo {
          exists: false,
          byte_count: 0,
          elem_count: 0,
+         kind: ATTRKind::Float,
       }
    }
 }
@@ -140,6 +163,7 @@ macro_rules! attribute {
                exists: self.info.exists,
                byte_count: self.info.byte_count,
                elem_count: self.info.elem_count,
+               kind: self.info.kind,
             }
          }
       }
@@ -149,11 +173,17 @@ attribute!(PosATTR, [f32; 3]);
 attribute!(ColATTR, [f32; 3]);
 attribute!(UVMATTR, [f32; 2]);
 attribute!(NrmATTR, [f32; 3]);
+attribute!(TanATTR, [f32; 4]);
 attribute!(Indices, u32);
 
+//integer data reaches the shader converted to float (kind Float) unless set_kind opts into
+//Integer or Normalized, f64 data likewise needs Double to stay double
 pub struct CustomATTR {
    pub(crate) data: Vec<u8>,
    pub(crate) info: ATTRInfo,
+   //shader input name and location, unset ones are placed after the built-ins in attach order
+   pub(crate) name: Option<String>,
+   pub(crate) location: Option<u32>,
 }
 impl CustomATTR {
    pub fn from<T: DataType + 'static>(vec: Vec<T>) -> CustomATTR {
@@ -170,16 +200,45 @@ impl CustomATTR {
          info.exists = true;
          (info.typ, info.typ_str, info.byte_count, info.elem_count) = get_format(&vec[0]);
       }
-      CustomATTR { data, info }
+      CustomATTR {
+         data,
+         info,
+         name: None,
+         location: None,
+      }
    }
    pub fn from_array<T: DataType + Clone + 'static>(array: &[T]) -> CustomATTR {
       let mut vec = Vec::from(array);
       CustomATTR::from(vec)
    }
+   pub fn set_kind(&mut self, kind: ATTRKind) -> NEResult<()> {
+      if !kind.fits(&self.info.typ) {
+         return NEResult::ER(NEError::attr_kind_invalid(&self.info.typ_str, kind));
+      }
+      self.info.kind = kind;
+      NEResult::OK(())
+   }
+   pub fn kind(&self) -> ATTRKind {
+      self.info.kind
+   }
+   pub fn set_name(&mut self, name: &str) {
+      self.name = Some(name.to_string())
+   }
+   pub fn name(&self) -> Option<&str> {
+      self.name.as_deref()
+   }
+   pub fn set_location(&mut self, location: u32) {
+      self.location = Some(location)
+   }
+   pub fn location(&self) -> Option<u32> {
+      self.location
+   }
    pub fn empty() -> CustomATTR {
       CustomATTR {
          data: Vec::new(),
          info: ATTRInfo::empty(),
+         name: None,
+         location: None,
       }
    }
    pub fn is_empty(&self) -> bool {
@@ -198,6 +257,7 @@ impl CustomATTR {
          exists: self.info.exists,
          byte_count: self.info.byte_count,
          elem_count: self.info.elem_count,
+         kind: self.info.kind,
       }
    }
 }
@@ -0,0 +1,316 @@
+use crate::*;
+use cgmath::{InnerSpace, Vector3};
+use std::collections::HashMap;
+
+fn close<const N: usize>(a: &[f32; N], b: &[f32; N], tolerance: f32) -> bool {
+   a.iter()
+      .zip(b.iter())
+      .all(|(x, y)| (x - y).abs() <= tolerance)
+}
+
+impl NEMeshAsset {
+   //merges vertices whose attributes all lie within `tolerance`, customs must match exactly,
+   //non indexed meshes become indexed, returns how many vertices were removed
+   pub fn weld_vertices(&mut self, tolerance: f32) -> NEResult<usize> {
+      if let NEResult::ER(e) = self.check_vertices("welding") {
+         return NEResult::ER(e);
+      }
+      let vert_count = self.pos_attr.data.len();
+      let cell = |p: [f32; 3]| -> [i64; 3] {
+         match tolerance > 0.0 {
+            true => p.map(|c| (c / tolerance).floor() as i64),
+            false => p.map(|c| (c + 0.0).to_bits() as i64),
+         }
+      };
+      let same = |a: usize, b: usize| -> bool {
+         close(&self.pos_attr.data[a], &self.pos_attr.data[b], tolerance)
+            && (self.col_attr.is_empty()
+               || close(&self.col_attr.data[a], &self.col_attr.data[b], tolerance))
+            && (self.uvm_attr.is_empty()
+               || close(&self.uvm_attr.data[a], &self.uvm_attr.data[b], tolerance))
+            && (self.nrm_attr.is_empty()
+               || close(&self.nrm_attr.data[a], &self.nrm_attr.data[b], tolerance))
+            && (self.tan_attr.is_empty()
+               || close(&self.tan_attr.data[a], &self.tan_attr.data[b], tolerance))
+            && self.cus_attrs.iter().all(|c| {
+               let size = c.info.byte_count * c.info.elem_count;
+               c.data[a * size..(a + 1) * size] == c.data[b * size..(b + 1) * size]
+            })
+      };
+
+      //kept vertices bucketed by position cell, neighbours are checked too when tolerant
+      let mut grid: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+      let mut remap: Vec<usize> = vec![0; vert_count];
+      let mut order: Vec<usize> = Vec::new();
+      let reach: i64 = if tolerance > 0.0 { 1 } else { 0 };
+      for v in 0..vert_count {
+         let c = cell(self.pos_attr.data[v]);
+         let mut found = None;
+         'search: for x in -reach..=reach {
+            for y in -reach..=reach {
+               for z in -reach..=reach {
+                  let near = [c[0] + x, c[1] + y, c[2] + z];
+                  if let Some(kept) = grid.get(&near) {
+                     if let Some(k) = kept.iter().find(|k| same(**k, v)) {
+                        found = Some(remap[*k]);
+                        break 'search;
+                     }
+                  }
+               }
+            }
+         }
+         remap[v] = match found {
+            Some(k) => k,
+            None => {
+               grid.entry(c).or_default().push(v);
+               order.push(v);
+               order.len() - 1
+            }
+         };
+      }
+
+      self.ensure_indices();
+      for i in self.indices.data.iter_mut() {
+         *i = remap[*i as usize] as u32;
+      }
+      self.reorder_vertices(&order);
+      NEResult::OK(vert_count - order.len())
+   }
+
+   //drops triangles that repeat a vertex or have no area, and the vertices only they used,
+   //returns how many triangles were removed
+   pub fn remove_degenerate_triangles(&mut self) -> NEResult<usize> {
+      if let NEResult::ER(e) = self.check_vertices("degenerate removal") {
+         return NEResult::ER(e);
+      }
+      let triangles = self.triangles();
+      let pos = |i: usize| Vector3::from(self.pos_attr.data[i]);
+      let mut kept: Vec<u32> = Vec::new();
+      let mut smoothing: Vec<u32> = Vec::new();
+      for (f, tri) in triangles.iter().enumerate() {
+         let (e1, e2) = (pos(tri[1]) - pos(tri[0]), pos(tri[2]) - pos(tri[0]));
+         let area = e1.cross(e2).magnitude();
+         let repeated = tri[0] == tri[1] || tri[1] == tri[2] || tri[0] == tri[2];
+         if repeated || area <= f32::EPSILON * e1.magnitude() * e2.magnitude() {
+            continue;
+         }
+         kept.extend(tri.map(|i| i as u32));
+         if let Some(group) = self.smoothing.get(f) {
+            smoothing.push(*group);
+         }
+      }
+      let removed = triangles.len() - kept.len() / 3;
+      if removed == 0 {
+         return NEResult::OK(0);
+      }
+      if !self.smoothing.is_empty() {
+         self.smoothing = smoothing;
+      }
+
+      //keep the surviving vertices in their old order
+      let mut used = vec![false; self.pos_attr.data.len()];
+      for i in kept.iter() {
+         used[*i as usize] = true;
+      }
+      let order: Vec<usize> = (0..used.len()).filter(|v| used[*v]).collect();
+      let mut remap = vec![0u32; used.len()];
+      for (new, old) in order.iter().enumerate() {
+         remap[*old] = new as u32;
+      }
+      self.indices = Indices::from(kept.iter().map(|i| remap[*i as usize]).collect());
+      self.reorder_vertices(&order);
+      NEResult::OK(removed)
+   }
+
+   //reverses every triangle so front and back faces swap, normals are left alone
+   pub fn flip_winding(&mut self) {
+      match self.indices.has_data() {
+         true => {
+            for tri in self.indices.data.chunks_exact_mut(3) {
+               tri.swap(1, 2);
+            }
+         }
+         false => {
+            let order: Vec<usize> = self
+               .triangles()
+               .iter()
+               .flat_map(|t| [t[0], t[2], t[1]])
+               .collect();
+            self.reorder_vertices(&order);
+         }
+      }
+   }
+
+   //every attribute covers every position and every index points at one
+   pub(crate) fn check_vertices(&self, what: &str) -> NEResult<()> {
+      if self.pos_attr.is_empty() {
+         return NEResult::ER(NEError::attr_missing(&format!("{what} needs positions")));
+      }
+      let vert_count = self.pos_attr.data.len();
+      let short = (self.col_attr.has_data() && self.col_attr.data.len() < vert_count)
+         || (self.uvm_attr.has_data() && self.uvm_attr.data.len() < vert_count)
+         || (self.nrm_attr.has_data() && self.nrm_attr.data.len() < vert_count)
+         || (self.tan_attr.has_data() && self.tan_attr.data.len() < vert_count)
+         || self
+            .cus_attrs
+            .iter()
+            .any(|c| c.data.len() < vert_count * c.info.byte_count * c.info.elem_count);
+      if short {
+         return NEResult::ER(NEError::attr_missing(&format!(
+            "{what} needs every attribute for every position"
+         )));
+      }
+      if self.indices.data.iter().any(|i| *i as usize >= vert_count) {
+         return NEResult::ER(NEError::attr_missing(&format!(
+            "{what} needs a position for every index"
+         )));
+      }
+      NEResult::OK(())
+   }
+
+   fn ensure_indices(&mut self) {
+      if self.indices.is_empty() {
+         self.indices = Indices::from((0..self.pos_attr.data.len() as u32).collect());
+      }
+   }
+
+   //rebuilds every attribute from the vertices listed in `order`, repeats are allowed
+   pub(crate) fn reorder_vertices(&mut self, order: &[usize]) {
+      if self.pos_attr.has_data() {
+         self.pos_attr.data = order.iter().map(|v| self.pos_attr.data[*v]).collect();
+      }
+      if self.col_attr.has_data() {
+         self.col_attr.data = order.iter().map(|v| self.col_attr.data[*v]).collect();
+      }
+      if self.uvm_attr.has_data() {
+         self.uvm_attr.data = order.iter().map(|v| self.uvm_attr.data[*v]).collect();
+      }
+      if self.nrm_attr.has_data() {
+         self.nrm_attr.data = order.iter().map(|v| self.nrm_attr.data[*v]).collect();
+      }
+      if self.tan_attr.has_data() {
+         self.tan_attr.data = order.iter().map(|v| self.tan_attr.data[*v]).collect();
+      }
+      for cus_attr in self.cus_attrs.iter_mut() {
+         let size = cus_attr.info.byte_count * cus_attr.info.elem_count;
+         if size > 0 {
+            let data = order
+               .iter()
+               .flat_map(|v| cus_attr.data[v * size..(v + 1) * size].to_vec())
+               .collect();
+            cus_attr.data = data;
+         }
+      }
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use crate::*;
+
+   fn mesh(pos: Vec<[f32; 3]>, col: Vec<[f32; 3]>, indices: Vec<u32>) -> NEMeshAsset {
+      NEMeshAsset::from_attrs(
+         PosATTR::from(pos),
+         ColATTR::from(col),
+         UVMATTR::empty(),
+         NrmATTR::empty(),
+         Indices::from(indices),
+      )
+   }
+   //two triangles with their own corners, the shared edge is off by `eps`
+   fn split_quad(eps: f32) -> NEMeshAsset {
+      mesh(
+         vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [eps, 0.0, 0.0],
+            [1.0, 1.0 + eps, 0.0],
+            [0.0, 1.0, 0.0],
+         ],
+         vec![[1.0, 1.0, 1.0]; 6],
+         vec![],
+      )
+   }
+
+   #[test]
+   fn weld_merges_within_tolerance() {
+      let mut asset = split_quad(1e-4);
+      assert_eq!(asset.weld_vertices(1e-3).unpack(), 2);
+      assert_eq!(asset.pos_attr.data().len(), 4);
+      assert_eq!(asset.indices.data(), &vec![0, 1, 2, 0, 2, 3]);
+      assert_eq!(asset.col_attr.data().len(), 4);
+
+      let mut asset = split_quad(1e-2);
+      assert_eq!(asset.weld_vertices(1e-3).unpack(), 0);
+      assert_eq!(asset.pos_attr.data().len(), 6);
+      assert_eq!(asset.indices.data().len(), 6);
+   }
+
+   #[test]
+   fn weld_keeps_differing_attributes_apart() {
+      let mut asset = split_quad(0.0);
+      asset.col_attr = ColATTR::from(vec![
+         [1.0, 1.0, 1.0],
+         [1.0, 1.0, 1.0],
+         [1.0, 1.0, 1.0],
+         [0.0, 0.0, 0.0],
+         [1.0, 1.0, 1.0],
+         [1.0, 1.0, 1.0],
+      ]);
+      assert_eq!(asset.weld_vertices(1e-3).unpack(), 1);
+      assert_eq!(asset.pos_attr.data().len(), 5);
+   }
+
+   #[test]
+   fn degenerate_triangles_and_their_vertices_go() {
+      let mut asset = mesh(
+         vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [2.0, 0.0, 0.0],
+            [3.0, 0.0, 0.0],
+         ],
+         vec![],
+         //a good one, a zero-area one along x and one repeating a corner
+         vec![0, 1, 2, 1, 3, 4, 0, 0, 2],
+      );
+      asset.set_smoothing_groups(vec![3, 4, 5]);
+      assert_eq!(asset.remove_degenerate_triangles().unpack(), 2);
+      assert_eq!(asset.indices.data(), &vec![0, 1, 2]);
+      assert_eq!(asset.pos_attr.data().len(), 3);
+      assert_eq!(asset.smoothing_groups(), &vec![3]);
+      assert_eq!(asset.remove_degenerate_triangles().unpack(), 0);
+   }
+
+   #[test]
+   fn flip_winding_swaps_corners() {
+      let pos = vec![
+         [0.0, 0.0, 0.0],
+         [1.0, 0.0, 0.0],
+         [1.0, 1.0, 0.0],
+         [0.0, 1.0, 0.0],
+      ];
+      let mut asset = mesh(pos.clone(), vec![], vec![0, 1, 2, 0, 2, 3]);
+      asset.flip_winding();
+      assert_eq!(asset.indices.data(), &vec![0, 2, 1, 0, 3, 2]);
+
+      let mut asset = mesh(pos[..3].to_vec(), vec![], vec![]);
+      asset.flip_winding();
+      assert_eq!(asset.pos_attr.data(), &vec![pos[0], pos[2], pos[1]]);
+   }
+
+   #[test]
+   fn broken_meshes_are_rejected() {
+      let mut asset = split_quad(0.0);
+      asset.indices = Indices::from(vec![0, 1, 6]);
+      assert!(asset.weld_vertices(1e-3).is_err());
+      assert!(asset.remove_degenerate_triangles().is_err());
+
+      let mut asset = split_quad(0.0);
+      asset.col_attr = ColATTR::from(vec![[1.0, 1.0, 1.0]; 5]);
+      assert!(asset.weld_vertices(1e-3).is_err());
+      assert!(asset.remove_degenerate_triangles().is_err());
+   }
+}
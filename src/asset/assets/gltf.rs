@@ -0,0 +1,937 @@
+use super::json::Json;
+use crate::*;
+use cgmath::{Matrix4, Quaternion, SquareMatrix, Vector3};
+use std::path::Path;
+
+const GLB_MAGIC: u32 = 0x46546C67;
+const GLB_JSON: u32 = 0x4E4F534A;
+const GLB_BIN: u32 = 0x004E4942;
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+//accessors without a buffer view start zeroed, there is no data to check their count against
+const MAX_ZEROED_VALUES: usize = 1 << 24;
+
+//vertex input names joints and weights are attached under, see attach_named_attr
+pub const JOINTS: &str = "vJnt";
+pub const WEIGHTS: &str = "vWgt";
+
+//an accessor's components as read, before normalization
+struct Accessor {
+   values: Vec<f64>,
+   comps: usize,
+   component_type: u64,
+   normalized: bool,
+}
+
+impl Accessor {
+   fn floats(&self) -> Vec<f32> {
+      let scale = match (self.normalized, self.component_type) {
+         (true, 5120) => 127.0,
+         (true, 5121) => 255.0,
+         (true, 5122) => 32767.0,
+         (true, 5123) => 65535.0,
+         (true, 5125) => 4294967295.0,
+         _ => 1.0,
+      };
+      self
+         .values
+         .iter()
+         .map(|v| (v / scale).max(-1.0) as f32)
+         .collect()
+   }
+   fn vec<const N: usize>(&self) -> Vec<[f32; N]> {
+      let floats = self.floats();
+      floats
+         .chunks_exact(self.comps)
+         .map(|c| {
+            let mut elem = [0.0; N];
+            for (e, v) in elem.iter_mut().zip(c.iter()) {
+               *e = *v;
+            }
+            elem
+         })
+         .collect()
+   }
+}
+
+pub(crate) struct GLTF {
+   json: Json,
+   buffers: Vec<Vec<u8>>,
+   dir: String,
+   path: String,
+}
+
+impl GLTF {
+   //every mesh primitive reachable from the default scene, with its node's world transform
+   pub(crate) fn load_parts(raw_path: &str, binary: bool) -> NEResult<Vec<NEMeshPart>> {
+      match GLTF::load_parts_and_files(raw_path, binary) {
+         NEResult::ER(e) => NEResult::ER(e),
+         NEResult::OK((parts, _)) => NEResult::OK(parts),
+      }
+   }
+   //the parts and the external files they were read from
+   pub(crate) fn load_parts_and_files(
+      raw_path: &str,
+      binary: bool,
+   ) -> NEResult<(Vec<NEMeshPart>, Vec<String>)> {
+      let gltf = match GLTF::load(raw_path, binary) {
+         Ok(g) => g,
+         Err(msg) => return NEResult::ER(NEError::mesh_invalid(raw_path, &msg)),
+      };
+      let mut parts = Vec::new();
+      match gltf.collect_parts(&mut parts) {
+         Err(msg) => NEResult::ER(NEError::mesh_invalid(raw_path, &msg)),
+         Ok(_) if parts.is_empty() => NEResult::ER(NEError::mesh_invalid(raw_path, "no meshes")),
+         Ok(_) => NEResult::OK((parts, gltf.external_files())),
+      }
+   }
+
+   fn load(raw_path: &str, binary: bool) -> Result<GLTF, String> {
+      let bytes = match file::read_as_bytes(raw_path) {
+         NEResult::ER(_) => return Err("could not be read".to_string()),
+         NEResult::OK(b) => b,
+      };
+      GLTF::from_bytes(bytes, raw_path, binary)
+   }
+   //buffers with a uri are read relative to `raw_path`
+   fn from_bytes(bytes: Vec<u8>, raw_path: &str, binary: bool) -> Result<GLTF, String> {
+      let (json_src, bin) = match binary {
+         true => GLTF::split_glb(&bytes)?,
+         false => (bytes, None),
+      };
+      let json_src = String::from_utf8(json_src).map_err(|_| "json is not utf8".to_string())?;
+      let json = Json::parse(&json_src).map_err(|e| format!("json {e}"))?;
+      match json.get("asset").get("version").as_str() {
+         Some(v) if v.starts_with("2.") => {}
+         v => return Err(format!("unsupported gltf version {v:?}")),
+      }
+
+      let dir = Path::new(raw_path)
+         .parent()
+         .map(|d| d.to_string_lossy().to_string())
+         .unwrap_or_default();
+      let mut gltf = GLTF {
+         json,
+         buffers: Vec::new(),
+         dir,
+         path: raw_path.to_string(),
+      };
+      let mut bin = bin;
+      for (i, buffer) in gltf.json.get("buffers").as_arr().iter().enumerate() {
+         let data = match buffer.get("uri").as_str() {
+            //only the first buffer of a glb may leave out its uri
+            None if i == 0 => bin.take().ok_or("buffer 0 has no data")?,
+            None => return Err(format!("buffer {i} has no uri")),
+            Some(uri) => gltf.read_uri(uri)?,
+         };
+         let length = buffer.get("byteLength").as_usize().unwrap_or(0);
+         if data.len() < length {
+            return Err(format!("buffer {i} is shorter than its byteLength"));
+         }
+         gltf.buffers.push(data);
+      }
+      Ok(gltf)
+   }
+
+   //(json chunk, bin chunk)
+   fn split_glb(bytes: &[u8]) -> Result<(Vec<u8>, Option<Vec<u8>>), String> {
+      let word = |at: usize| -> Option<u32> {
+         bytes
+            .get(at..at + 4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+      };
+      if word(0) != Some(GLB_MAGIC) {
+         return Err("not a glb file".to_string());
+      }
+      if word(4) != Some(2) {
+         return Err(format!("unsupported glb version {:?}", word(4)));
+      }
+      let length = (word(8).unwrap_or(0) as usize).min(bytes.len());
+      let (mut json, mut bin) = (None, None);
+      let mut at = 12;
+      while at + 8 <= length {
+         let chunk_len = word(at).unwrap_or(0) as usize;
+         let chunk_type = word(at + 4).unwrap_or(0);
+         let chunk = bytes
+            .get(at + 8..at + 8 + chunk_len)
+            .ok_or("glb chunk runs past the end of the file")?;
+         match chunk_type {
+            GLB_JSON if json.is_none() => json = Some(chunk.to_vec()),
+            GLB_BIN if bin.is_none() => bin = Some(chunk.to_vec()),
+            _ => {}
+         }
+         at += 8 + chunk_len;
+      }
+      match json {
+         None => Err("glb has no json chunk".to_string()),
+         Some(json) => Ok((json, bin)),
+      }
+   }
+
+   //data uris are decoded, anything else is a file next to the gltf
+   fn read_uri(&self, uri: &str) -> Result<Vec<u8>, String> {
+      if let Some(data) = uri.strip_prefix("data:") {
+         return match data.split_once(";base64,") {
+            Some((_, encoded)) => base64(encoded),
+            None => Err("only base64 data uris are supported".to_string()),
+         };
+      }
+      let path = self.file_path(uri);
+      match file::read_as_bytes(&path) {
+         NEResult::ER(_) => Err(format!("[{path}] could not be read")),
+         NEResult::OK(b) => Ok(b),
+      }
+   }
+   //buffers and images outside the gltf, data uris are part of it
+   fn external_files(&self) -> Vec<String> {
+      let mut files = Vec::new();
+      for list in ["buffers", "images"] {
+         for item in self.json.get(list).as_arr().iter() {
+            match item.get("uri").as_str() {
+               Some(uri) if !uri.starts_with("data:") => files.push(self.file_path(uri)),
+               _ => {}
+            }
+         }
+      }
+      files
+   }
+   fn file_path(&self, uri: &str) -> String {
+      let uri = percent_decode(uri);
+      Path::new(&self.dir).join(uri).to_string_lossy().to_string()
+   }
+
+   fn accessor(&self, index: usize) -> Result<Accessor, String> {
+      let acc = self.json.get("accessors").at(index);
+      if acc.is_null() {
+         return Err(format!("accessor {index} does not exist"));
+      }
+      let component_type = acc.get("componentType").as_usize().unwrap_or(0) as u64;
+      let size = match component_type {
+         5120 | 5121 => 1,
+         5122 | 5123 => 2,
+         5125 | 5126 => 4,
+         t => return Err(format!("accessor {index} has unknown component type {t}")),
+      };
+      let comps = match acc.get("type").as_str() {
+         Some("SCALAR") => 1,
+         Some("VEC2") => 2,
+         Some("VEC3") => 3,
+         Some("VEC4") | Some("MAT2") => 4,
+         Some("MAT3") => 9,
+         Some("MAT4") => 16,
+         t => return Err(format!("accessor {index} has unknown type {t:?}")),
+      };
+      let count = acc.get("count").as_usize().unwrap_or(0);
+      let mut values = match acc.get("bufferView").as_usize() {
+         Some(view) => {
+            let offset = acc.get("byteOffset").as_usize().unwrap_or(0);
+            self.view_elems(view, offset, count, comps, size, component_type)?
+         }
+         None => match count.checked_mul(comps) {
+            Some(n) if n <= MAX_ZEROED_VALUES => vec![0.0; n],
+            _ => {
+               return Err(format!(
+                  "accessor {index} is too large without a buffer view"
+               ))
+            }
+         },
+      };
+
+      //sparse accessors replace some elements of the base data
+      let sparse = acc.get("sparse");
+      if !sparse.is_null() {
+         let sparse_count = sparse.get("count").as_usize().unwrap_or(0);
+         let ind = sparse.get("indices");
+         let ind_type = ind.get("componentType").as_usize().unwrap_or(5125) as u64;
+         let ind_size = match ind_type {
+            5121 => 1,
+            5123 => 2,
+            _ => 4,
+         };
+         let indices = self.view_elems(
+            ind.get("bufferView").as_usize().unwrap_or(usize::MAX),
+            ind.get("byteOffset").as_usize().unwrap_or(0),
+            sparse_count,
+            1,
+            ind_size,
+            ind_type,
+         )?;
+         let val = sparse.get("values");
+         let replaced = self.view_elems(
+            val.get("bufferView").as_usize().unwrap_or(usize::MAX),
+            val.get("byteOffset").as_usize().unwrap_or(0),
+            sparse_count,
+            comps,
+            size,
+            component_type,
+         )?;
+         for (i, target) in indices.iter().enumerate() {
+            let target = *target as usize;
+            if target >= count {
+               return Err(format!("accessor {index} has a sparse index out of range"));
+            }
+            values[target * comps..(target + 1) * comps]
+               .copy_from_slice(&replaced[i * comps..(i + 1) * comps]);
+         }
+      }
+
+      Ok(Accessor {
+         values,
+         comps,
+         component_type,
+         normalized: acc.get("normalized").as_bool().unwrap_or(false),
+      })
+   }
+
+   //`count` elements of `comps` little endian components from a buffer view
+   fn view_elems(
+      &self,
+      view: usize,
+      offset: usize,
+      count: usize,
+      comps: usize,
+      size: usize,
+      component_type: u64,
+   ) -> Result<Vec<f64>, String> {
+      let bytes = self.view(view)?;
+      let elem_size = comps * size;
+      let stride = match self
+         .json
+         .get("bufferViews")
+         .at(view)
+         .get("byteStride")
+         .as_usize()
+      {
+         Some(s) if s > 0 => s,
+         _ => elem_size,
+      };
+      //checked so absurd counts are rejected before anything is allocated
+      let end = match count {
+         0 => Some(0),
+         _ => (count - 1)
+            .checked_mul(stride)
+            .and_then(|n| n.checked_add(offset))
+            .and_then(|n| n.checked_add(elem_size)),
+      };
+      match end {
+         Some(end) if end <= bytes.len() => {}
+         _ => return Err(format!("buffer view {view} is too short for its accessor")),
+      }
+      let mut values = Vec::with_capacity(count * comps);
+      for e in 0..count {
+         for c in 0..comps {
+            let at = offset + e * stride + c * size;
+            let b = &bytes[at..at + size];
+            values.push(match component_type {
+               5120 => b[0] as i8 as f64,
+               5121 => b[0] as f64,
+               5122 => i16::from_le_bytes([b[0], b[1]]) as f64,
+               5123 => u16::from_le_bytes([b[0], b[1]]) as f64,
+               5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+               _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            });
+         }
+      }
+      Ok(values)
+   }
+
+   fn view(&self, index: usize) -> Result<&[u8], String> {
+      let view = self.json.get("bufferViews").at(index);
+      let buffer = view.get("buffer").as_usize().unwrap_or(usize::MAX);
+      let offset = view.get("byteOffset").as_usize().unwrap_or(0);
+      let length = view.get("byteLength").as_usize().unwrap_or(0);
+      self
+         .buffers
+         .get(buffer)
+         .and_then(|b| b.get(offset..offset.checked_add(length)?))
+         .ok_or(format!("buffer view {index} is out of range"))
+   }
+
+   fn collect_parts(&self, parts: &mut Vec<NEMeshPart>) -> Result<(), String> {
+      let nodes = self.json.get("nodes").as_arr();
+      let scene = self.json.get("scene").as_usize().unwrap_or(0);
+      let scene = self.json.get("scenes").at(scene);
+      let roots: Vec<usize> = match scene.is_null() {
+         false => scene
+            .get("nodes")
+            .as_arr()
+            .iter()
+            .filter_map(|n| n.as_usize())
+            .collect(),
+         //no scene, every node nobody lists as a child is a root
+         true => (0..nodes.len())
+            .filter(|i| {
+               !nodes.iter().any(|n| {
+                  n.get("children")
+                     .as_arr()
+                     .iter()
+                     .any(|c| c.as_usize() == Some(*i))
+               })
+            })
+            .collect(),
+      };
+      if nodes.is_empty() {
+         for m in 0..self.json.get("meshes").as_arr().len() {
+            self.mesh_parts(m, "", Matrix4::identity(), parts)?;
+         }
+         return Ok(());
+      }
+      for root in roots {
+         self.visit(root, Matrix4::identity(), 0, parts)?;
+      }
+      Ok(())
+   }
+
+   fn visit(
+      &self,
+      index: usize,
+      parent: Matrix4<f32>,
+      depth: usize,
+      parts: &mut Vec<NEMeshPart>,
+   ) -> Result<(), String> {
+      let nodes = self.json.get("nodes").as_arr();
+      let node = match nodes.get(index) {
+         None => return Err(format!("node {index} does not exist")),
+         Some(n) => n,
+      };
+      if depth > nodes.len() {
+         return Err("node hierarchy has a cycle".to_string());
+      }
+      let world = parent * node_matrix(node);
+      if let Some(mesh) = node.get("mesh").as_usize() {
+         let name = node.get("name").as_str().unwrap_or("");
+         self.mesh_parts(mesh, name, world, parts)?;
+      }
+      for child in node.get("children").as_arr().iter() {
+         if let Some(child) = child.as_usize() {
+            self.visit(child, world, depth + 1, parts)?;
+         }
+      }
+      Ok(())
+   }
+
+   //one part per primitive, named after the node, the mesh or its index
+   fn mesh_parts(
+      &self,
+      index: usize,
+      node_name: &str,
+      world: Matrix4<f32>,
+      parts: &mut Vec<NEMeshPart>,
+   ) -> Result<(), String> {
+      let mesh = self.json.get("meshes").at(index);
+      if mesh.is_null() {
+         return Err(format!("mesh {index} does not exist"));
+      }
+      let name = match (node_name, mesh.get("name").as_str()) {
+         ("", Some(n)) => n.to_string(),
+         ("", None) => format!("mesh{index}"),
+         (n, _) => n.to_string(),
+      };
+      let primitives = mesh.get("primitives").as_arr();
+      for (p, prim) in primitives.iter().enumerate() {
+         let material = prim.get("material").as_usize().map(|m| self.material(m));
+         let mut asset = match self.primitive(prim, material.as_ref())? {
+            None => {
+               log_warn!(
+                  "[{}] {name} primitive {p} is not triangles, skipped",
+                  self.path
+               );
+               continue;
+            }
+            Some(a) => a,
+         };
+         asset.transform = Transform::from_matrix(world);
+         let textures = match prim.get("material").as_usize() {
+            None => Vec::new(),
+            Some(m) => self.textures(m),
+         };
+         parts.push(NEMeshPart {
+            name: match primitives.len() {
+               1 => name.clone(),
+               _ => format!("{name}.{p}"),
+            },
+            asset,
+            material,
+            textures,
+         });
+      }
+      Ok(())
+   }
+
+   //None for point and line primitives
+   fn primitive(
+      &self,
+      prim: &Json,
+      material: Option<&MTLMaterial>,
+   ) -> Result<Option<NEMeshAsset>, String> {
+      let attrs = prim.get("attributes");
+      let read = |name: &str| -> Result<Option<Accessor>, String> {
+         match attrs.get(name).as_usize() {
+            None => Ok(None),
+            Some(a) => self.accessor(a).map(Some),
+         }
+      };
+      let pos = match read("POSITION")? {
+         None => return Err("primitive has no POSITION".to_string()),
+         Some(a) => a.vec::<3>(),
+      };
+      let vert_count = pos.len();
+      let same_count = |v: usize, attr: &str| match v == vert_count {
+         true => Ok(()),
+         false => Err(format!("{attr} count does not match POSITION")),
+      };
+
+      let tint = material.map(|m| m.diffuse).unwrap_or([1.0; 3]);
+      let col = match read("COLOR_0")? {
+         None => vec![tint; vert_count],
+         Some(a) => a.vec::<3>(),
+      };
+      same_count(col.len(), "COLOR_0")?;
+      let uvm = read("TEXCOORD_0")?.map(|a| a.vec::<2>());
+      let nrm = read("NORMAL")?.map(|a| a.vec::<3>());
+      let tan = read("TANGENT")?.map(|a| a.vec::<4>());
+      for (len, attr) in [
+         (uvm.as_ref().map(|v| v.len()), "TEXCOORD_0"),
+         (nrm.as_ref().map(|v| v.len()), "NORMAL"),
+         (tan.as_ref().map(|v| v.len()), "TANGENT"),
+      ] {
+         if let Some(len) = len {
+            same_count(len, attr)?;
+         }
+      }
+
+      let mut indices: Vec<u32> = match prim.get("indices").as_usize() {
+         None => (0..vert_count as u32).collect(),
+         Some(a) => self.accessor(a)?.values.iter().map(|i| *i as u32).collect(),
+      };
+      if indices.iter().any(|i| *i as usize >= vert_count) {
+         return Err("primitive index out of range".to_string());
+      }
+      //strips and fans are unrolled into a triangle list
+      match prim.get("mode").as_usize().unwrap_or(4) {
+         4 => {}
+         5 => {
+            indices = (2..indices.len())
+               .flat_map(|i| match i % 2 {
+                  0 => [indices[i - 2], indices[i - 1], indices[i]],
+                  _ => [indices[i - 1], indices[i - 2], indices[i]],
+               })
+               .collect()
+         }
+         6 => {
+            indices = (2..indices.len())
+               .flat_map(|i| [indices[0], indices[i - 1], indices[i]])
+               .collect()
+         }
+         _ => return Ok(None),
+      }
+
+      let mut asset = NEMeshAsset::from_attrs(
+         PosATTR::from(pos),
+         ColATTR::from(col),
+         uvm.map(UVMATTR::from).unwrap_or(UVMATTR::empty()),
+         nrm.map(NrmATTR::from).unwrap_or(NrmATTR::empty()),
+         Indices::from(indices),
+      );
+      if let Some(tan) = tan {
+         asset.tan_attr = TanATTR::from(tan);
+      }
+      if let Some(joints) = read("JOINTS_0")? {
+         let joints: Vec<[u16; 4]> = joints
+            .values
+            .chunks_exact(joints.comps)
+            .map(|j| [0, 1, 2, 3].map(|i| *j.get(i).unwrap_or(&0.0) as u16))
+            .collect();
+         same_count(joints.len(), "JOINTS_0")?;
+         asset.attach_named_attr(JOINTS, CustomATTR::from(joints));
+      }
+      if let Some(weights) = read("WEIGHTS_0")? {
+         let weights = weights.vec::<4>();
+         same_count(weights.len(), "WEIGHTS_0")?;
+         asset.attach_named_attr(WEIGHTS, CustomATTR::from(weights));
+      }
+      //the spec asks for flat normals when a primitive has none
+      if asset.nrm_attr.is_empty() {
+         if let NEResult::ER(_) = asset.generate_normals(false) {
+            return Err("normals could not be generated".to_string());
+         }
+      }
+      Ok(Some(asset))
+   }
+
+   //pbr factors mapped onto the obj style description, maps only for external images
+   fn material(&self, index: usize) -> MTLMaterial {
+      let mat = self.json.get("materials").at(index);
+      let pbr = mat.get("pbrMetallicRoughness");
+      let base = pbr.get("baseColorFactor").as_f32s([1.0; 4]);
+      let map = |tex: &Json| -> Option<String> {
+         let source = self
+            .json
+            .get("textures")
+            .at(tex.get("index").as_usize()?)
+            .get("source")
+            .as_usize()?;
+         let uri = self.json.get("images").at(source).get("uri").as_str()?;
+         match uri.starts_with("data:") {
+            true => None,
+            false => Some(self.file_path(uri)),
+         }
+      };
+      MTLMaterial {
+         name: mat
+            .get("name")
+            .as_str()
+            .map(|n| n.to_string())
+            .unwrap_or(format!("material{index}")),
+         ambient: [0.0; 3],
+         diffuse: [base[0], base[1], base[2]],
+         specular: [0.0; 3],
+         emissive: mat.get("emissiveFactor").as_f32s([0.0; 3]),
+         shininess: 0.0,
+         opacity: base[3],
+         diffuse_map: map(pbr.get("baseColorTexture")),
+         normal_map: map(mat.get("normalTexture")),
+         specular_map: None,
+         emissive_map: map(mat.get("emissiveTexture")),
+      }
+   }
+
+   //png images of a material keyed by the NEMaterial sampler they belong on
+   fn textures(&self, index: usize) -> Vec<(String, NETexture)> {
+      let mat = self.json.get("materials").at(index);
+      let pbr = mat.get("pbrMetallicRoughness");
+      let slots = [
+         (ALBEDO, pbr.get("baseColorTexture")),
+         (ROUGHNESS, pbr.get("metallicRoughnessTexture")),
+         (NORMAL, mat.get("normalTexture")),
+         (EMISSIVE, mat.get("emissiveTexture")),
+      ];
+      let mut textures = Vec::new();
+      for (sampler, info) in slots {
+         let tex = match info.get("index").as_usize() {
+            None => continue,
+            Some(t) => self.json.get("textures").at(t),
+         };
+         match self.texture(tex) {
+            Err(msg) => {
+               log_warn!("[{}] {sampler} texture skipped, {msg}", self.path);
+            }
+            Ok(t) => textures.push((sampler.to_string(), t)),
+         }
+      }
+      textures
+   }
+
+   fn texture(&self, tex: &Json) -> Result<NETexture, String> {
+      let image = match tex.get("source").as_usize() {
+         None => return Err("texture has no source".to_string()),
+         Some(i) => self.json.get("images").at(i),
+      };
+      let bytes = match (
+         image.get("uri").as_str(),
+         image.get("bufferView").as_usize(),
+      ) {
+         (Some(uri), _) => self.read_uri(uri)?,
+         (None, Some(view)) => self.view(view)?.to_vec(),
+         (None, None) => return Err("image has no data".to_string()),
+      };
+      if !bytes.starts_with(&PNG_SIGNATURE) {
+         return Err("only png images are supported".to_string());
+      }
+      let mut texture = match NETexture::decode_png(&bytes, &self.path) {
+         NEResult::ER(_) => return Err("png could not be decoded".to_string()),
+         NEResult::OK(t) => t,
+      };
+      let sampler = self
+         .json
+         .get("samplers")
+         .at(tex.get("sampler").as_usize().unwrap_or(usize::MAX));
+      texture.filter = match sampler.get("magFilter").as_usize() {
+         Some(9728) => TexFilter::Closest,
+         _ => TexFilter::Linear,
+      };
+      texture.wrap = match sampler.get("wrapS").as_usize() {
+         Some(33071) => TexWrap::Extend,
+         _ => TexWrap::Repeat,
+      };
+      Ok(texture)
+   }
+}
+
+//matrix, or translation * rotation * scale
+fn node_matrix(node: &Json) -> Matrix4<f32> {
+   if node.get("matrix").as_arr().len() == 16 {
+      let m = node.get("matrix").as_f32s([0.0; 16]);
+      return Matrix4::new(
+         m[0], m[1], m[2], m[3], m[4], m[5], m[6], m[7], m[8], m[9], m[10], m[11], m[12], m[13],
+         m[14], m[15],
+      );
+   }
+   let t = node.get("translation").as_f32s([0.0; 3]);
+   let r = node.get("rotation").as_f32s([0.0, 0.0, 0.0, 1.0]);
+   let s = node.get("scale").as_f32s([1.0; 3]);
+   Matrix4::from_translation(Vector3::from(t))
+      * Matrix4::from(Quaternion::new(r[3], r[0], r[1], r[2]))
+      * Matrix4::from_nonuniform_scale(s[0], s[1], s[2])
+}
+
+fn base64(encoded: &str) -> Result<Vec<u8>, String> {
+   let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+   let (mut acc, mut bits) = (0u32, 0);
+   for c in encoded.bytes() {
+      let v = match c {
+         b'A'..=b'Z' => c - b'A',
+         b'a'..=b'z' => c - b'a' + 26,
+         b'0'..=b'9' => c - b'0' + 52,
+         b'+' | b'-' => 62,
+         b'/' | b'_' => 63,
+         b'=' | b'\n' | b'\r' | b' ' => continue,
+         _ => return Err("invalid base64 data uri".to_string()),
+      };
+      acc = ((acc << 6) | v as u32) & 0xFFFF;
+      bits += 6;
+      if bits >= 8 {
+         bits -= 8;
+         bytes.push((acc >> bits) as u8);
+      }
+   }
+   Ok(bytes)
+}
+
+fn percent_decode(uri: &str) -> String {
+   let bytes = uri.as_bytes();
+   let mut out = Vec::with_capacity(bytes.len());
+   let mut i = 0;
+   while i < bytes.len() {
+      let hex = bytes
+         .get(i + 1..i + 3)
+         .and_then(|h| std::str::from_utf8(h).ok())
+         .and_then(|h| u8::from_str_radix(h, 16).ok());
+      match (bytes[i], hex) {
+         (b'%', Some(b)) => {
+            out.push(b);
+            i += 3;
+         }
+         (b, _) => {
+            out.push(b);
+            i += 1;
+         }
+      }
+   }
+   String::from_utf8_lossy(&out).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+   use super::{Json, GLB_BIN, GLB_JSON, GLB_MAGIC, GLTF, JOINTS, WEIGHTS};
+   use crate::*;
+
+   fn gltf(accessor: &str) -> GLTF {
+      let src = format!(
+         r#"{{"bufferViews": [{{"buffer": 0, "byteLength": 12}}], "accessors": [{accessor}]}}"#
+      );
+      GLTF {
+         json: Json::parse(&src).unwrap(),
+         buffers: vec![vec![0; 12]],
+         dir: String::new(),
+         path: String::new(),
+      }
+   }
+
+   #[test]
+   fn accessor_reads_its_view() {
+      let g = gltf(r#"{"bufferView": 0, "componentType": 5126, "type": "VEC3", "count": 1}"#);
+      assert_eq!(g.accessor(0).unwrap().values, [0.0; 3]);
+   }
+
+   #[test]
+   fn oversized_accessors_are_rejected() {
+      let accessors = [
+         r#"{"bufferView": 0, "componentType": 5126, "type": "VEC3", "count": 2}"#,
+         r#"{"bufferView": 0, "componentType": 5126, "type": "MAT4", "count": 1e19}"#,
+         r#"{"bufferView": 0, "byteOffset": 1e19, "componentType": 5126, "type": "SCALAR", "count": 1}"#,
+         r#"{"componentType": 5126, "type": "MAT4", "count": 1e19}"#,
+         r#"{"componentType": 5126, "type": "SCALAR", "count": 1e9}"#,
+      ];
+      for acc in accessors {
+         assert!(gltf(acc).accessor(0).is_err(), "{acc}");
+      }
+   }
+
+   //corners of a unit square, joints as ubyte4 and weights as vec4
+   fn buffer() -> Vec<u8> {
+      let mut bytes = Vec::new();
+      for p in [
+         [0.0f32, 0.0, 0.0],
+         [1.0, 0.0, 0.0],
+         [0.0, 1.0, 0.0],
+         [1.0, 1.0, 0.0],
+      ] {
+         bytes.extend(p.iter().flat_map(|c| c.to_le_bytes()));
+      }
+      for v in 0..4u8 {
+         bytes.extend([v, v + 1, 0, 0]);
+      }
+      for _ in 0..4 {
+         bytes.extend(
+            [0.75f32, 0.25, 0.0, 0.0]
+               .iter()
+               .flat_map(|c| c.to_le_bytes()),
+         );
+      }
+      bytes
+   }
+   fn document(buffer: &str, nodes: &str, prim: &str) -> String {
+      format!(
+         r#"{{"asset": {{"version": "2.0"}}, "buffers": [{buffer}],
+         "bufferViews": [{{"buffer": 0, "byteLength": 48}},
+            {{"buffer": 0, "byteOffset": 48, "byteLength": 16}},
+            {{"buffer": 0, "byteOffset": 64, "byteLength": 64}}],
+         "accessors": [{{"bufferView": 0, "componentType": 5126, "type": "VEC3", "count": 4}},
+            {{"bufferView": 1, "componentType": 5121, "type": "VEC4", "count": 4}},
+            {{"bufferView": 2, "componentType": 5126, "type": "VEC4", "count": 4}}],
+         "meshes": [{{"name": "square", "primitives": [{prim}]}}], "nodes": {nodes}}}"#
+      )
+   }
+   fn encode(bytes: &[u8]) -> String {
+      let table = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+      let mut out = String::new();
+      for chunk in bytes.chunks(3) {
+         let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+         ];
+         let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+         for i in 0..4 {
+            match i <= chunk.len() {
+               true => out.push(table[(n >> (18 - i * 6)) as usize & 63] as char),
+               false => out.push('='),
+            }
+         }
+      }
+      out
+   }
+   fn embedded(nodes: &str, prim: &str) -> Vec<NEMeshPart> {
+      let uri = format!(
+         r#"{{"uri": "data:application/octet-stream;base64,{}", "byteLength": 128}}"#,
+         encode(&buffer())
+      );
+      let src = document(&uri, nodes, prim);
+      let g = GLTF::from_bytes(src.into_bytes(), "test.gltf", false).unwrap();
+      let mut parts = Vec::new();
+      g.collect_parts(&mut parts).unwrap();
+      parts
+   }
+   const UNINDEXED: &str = r#"{"attributes": {"POSITION": 0}}"#;
+
+   #[test]
+   fn embedded_buffers_are_decoded() {
+      let parts = embedded("[]", UNINDEXED);
+      assert_eq!(parts.len(), 1);
+      assert_eq!(parts[0].name, "square");
+      let asset = &parts[0].asset;
+      assert_eq!(asset.pos_attr.data()[3], [1.0, 1.0, 0.0]);
+      //flat normals are generated when the primitive has none
+      assert!(asset.nrm_attr.has_data());
+   }
+
+   #[test]
+   fn glb_chunks_are_split() {
+      let json = document(r#"{"byteLength": 128}"#, "[]", UNINDEXED);
+      let mut json = json.into_bytes();
+      json.resize(json.len().div_ceil(4) * 4, b' ');
+      let bin = buffer();
+      let mut glb = Vec::new();
+      for word in [GLB_MAGIC, 2, (12 + 8 + json.len() + 8 + bin.len()) as u32] {
+         glb.extend(word.to_le_bytes());
+      }
+      glb.extend((json.len() as u32).to_le_bytes());
+      glb.extend(GLB_JSON.to_le_bytes());
+      glb.extend(&json);
+      glb.extend((bin.len() as u32).to_le_bytes());
+      glb.extend(GLB_BIN.to_le_bytes());
+      glb.extend(&bin);
+
+      let g = GLTF::from_bytes(glb.clone(), "test.glb", true).unwrap();
+      let mut parts = Vec::new();
+      g.collect_parts(&mut parts).unwrap();
+      assert_eq!(parts[0].asset.pos_attr.data()[1], [1.0, 0.0, 0.0]);
+
+      let (json_chunk, bin_chunk) = GLTF::split_glb(&glb).unwrap();
+      assert_eq!((json_chunk, bin_chunk), (json, Some(bin)));
+      assert!(GLTF::split_glb(&glb[..glb.len() - 4]).is_err());
+      glb[0] ^= 0xff;
+      assert!(GLTF::split_glb(&glb).is_err());
+   }
+
+   #[test]
+   fn node_trs_becomes_the_transform() {
+      let nodes = r#"[{"translation": [1, 0, 0], "children": [1]},
+         {"name": "child", "mesh": 0, "translation": [0, 2, 0],
+          "rotation": [0, 0, 0.70710678, 0.70710678], "scale": [2, 2, 2]}]"#;
+      let parts = embedded(nodes, UNINDEXED);
+      assert_eq!(parts[0].name, "child");
+      let t = &parts[0].asset.transform;
+      let near =
+         |a: [f32; 3], b: [f32; 3]| a.iter().zip(b.iter()).all(|(x, y)| (x - y).abs() < 1e-4);
+      assert!(near(t.pos.into(), [1.0, 2.0, 0.0]));
+      assert!(near(t.rot.into(), [0.0, 0.0, 90.0]));
+      assert!(near(t.scale.into(), [2.0, 2.0, 2.0]));
+      let corner = t.matrix * cgmath::Vector4::new(1.0, 0.0, 0.0, 1.0);
+      assert!(near([corner.x, corner.y, corner.z], [1.0, 4.0, 0.0]));
+   }
+
+   #[test]
+   fn strips_and_fans_are_unrolled() {
+      let modes = [(5, vec![0, 1, 2, 2, 1, 3]), (6, vec![0, 1, 2, 0, 2, 3])];
+      for (mode, expected) in modes {
+         let prim = format!(r#"{{"attributes": {{"POSITION": 0}}, "mode": {mode}}}"#);
+         let parts = embedded("[]", &prim);
+         let asset = &parts[0].asset;
+         let corners: Vec<[f32; 3]> = asset
+            .indices
+            .data()
+            .iter()
+            .map(|i| asset.pos_attr.data()[*i as usize])
+            .collect();
+         let square = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+         ];
+         let wanted: Vec<[f32; 3]> = expected.iter().map(|i| square[*i]).collect();
+         assert_eq!(corners, wanted, "mode {mode}");
+      }
+      assert!(embedded("[]", r#"{"attributes": {"POSITION": 0}, "mode": 0}"#).is_empty());
+   }
+
+   #[test]
+   fn skins_get_named_attributes() {
+      let prim = r#"{"attributes": {"POSITION": 0, "JOINTS_0": 1, "WEIGHTS_0": 2}}"#;
+      let parts = embedded("[]", prim);
+      let asset = &parts[0].asset;
+      let joints = asset
+         .cus_attrs
+         .iter()
+         .find(|c| c.name() == Some(JOINTS))
+         .unwrap();
+      assert_eq!(joints.info.typ_str, "u16x4");
+      let first: Vec<u8> = [0u16, 1, 0, 0]
+         .iter()
+         .flat_map(|j| j.to_ne_bytes())
+         .collect();
+      assert_eq!(&joints.data()[..8], &first[..]);
+      let weights = asset
+         .cus_attrs
+         .iter()
+         .find(|c| c.name() == Some(WEIGHTS))
+         .unwrap();
+      let first: Vec<u8> = [0.75f32, 0.25, 0.0, 0.0]
+         .iter()
+         .flat_map(|w| w.to_ne_bytes())
+         .collect();
+      assert_eq!(&weights.data()[..16], &first[..]);
+      assert_eq!((JOINTS, WEIGHTS), ("vJnt", "vWgt"));
+   }
+}
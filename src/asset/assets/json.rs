@@ -0,0 +1,285 @@
+//just enough json for model files, objects keep their key order
+#[derive(Debug, Clone)]
+pub(crate) enum Json {
+   Null,
+   Bool(bool),
+   Num(f64),
+   Str(String),
+   Arr(Vec<Json>),
+   Obj(Vec<(String, Json)>),
+}
+
+const NULL: Json = Json::Null;
+//deeper files are rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 128;
+
+impl Json {
+   pub(crate) fn parse(src: &str) -> Result<Json, String> {
+      let mut parser = JsonParser {
+         src: src.as_bytes(),
+         at: 0,
+         depth: 0,
+      };
+      let value = parser.value()?;
+      parser.skip_ws();
+      match parser.at == parser.src.len() {
+         true => Ok(value),
+         false => Err(format!("trailing data at byte {}", parser.at)),
+      }
+   }
+
+   //missing keys and indices give Null so lookups can be chained
+   pub(crate) fn get(&self, key: &str) -> &Json {
+      match self {
+         Json::Obj(pairs) => pairs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+            .unwrap_or(&NULL),
+         _ => &NULL,
+      }
+   }
+   pub(crate) fn at(&self, i: usize) -> &Json {
+      match self {
+         Json::Arr(items) => items.get(i).unwrap_or(&NULL),
+         _ => &NULL,
+      }
+   }
+   pub(crate) fn is_null(&self) -> bool {
+      matches!(self, Json::Null)
+   }
+   pub(crate) fn as_f64(&self) -> Option<f64> {
+      match self {
+         Json::Num(n) => Some(*n),
+         _ => None,
+      }
+   }
+   pub(crate) fn as_usize(&self) -> Option<usize> {
+      match self {
+         Json::Num(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+         _ => None,
+      }
+   }
+   pub(crate) fn as_bool(&self) -> Option<bool> {
+      match self {
+         Json::Bool(b) => Some(*b),
+         _ => None,
+      }
+   }
+   pub(crate) fn as_str(&self) -> Option<&str> {
+      match self {
+         Json::Str(s) => Some(s),
+         _ => None,
+      }
+   }
+   pub(crate) fn as_arr(&self) -> &[Json] {
+      match self {
+         Json::Arr(items) => items,
+         _ => &[],
+      }
+   }
+   pub(crate) fn as_obj(&self) -> &[(String, Json)] {
+      match self {
+         Json::Obj(pairs) => pairs,
+         _ => &[],
+      }
+   }
+   //numbers of an array, `def` when it isn't one
+   pub(crate) fn as_f32s<const N: usize>(&self, def: [f32; N]) -> [f32; N] {
+      let items = self.as_arr();
+      if items.len() != N {
+         return def;
+      }
+      let mut out = def;
+      for (o, item) in out.iter_mut().zip(items.iter()) {
+         *o = item.as_f64().map(|n| n as f32).unwrap_or(*o);
+      }
+      out
+   }
+}
+
+struct JsonParser<'a> {
+   src: &'a [u8],
+   at: usize,
+   depth: usize,
+}
+
+impl JsonParser<'_> {
+   fn skip_ws(&mut self) {
+      while self.at < self.src.len() && self.src[self.at].is_ascii_whitespace() {
+         self.at += 1;
+      }
+   }
+   fn peek(&mut self) -> Option<u8> {
+      self.skip_ws();
+      self.src.get(self.at).copied()
+   }
+   fn expect(&mut self, byte: u8) -> Result<(), String> {
+      match self.peek() {
+         Some(b) if b == byte => {
+            self.at += 1;
+            Ok(())
+         }
+         _ => Err(format!("expected '{}' at byte {}", byte as char, self.at)),
+      }
+   }
+   fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+      match self.src[self.at..].starts_with(word.as_bytes()) {
+         true => {
+            self.at += word.len();
+            Ok(value)
+         }
+         false => Err(format!("unexpected token at byte {}", self.at)),
+      }
+   }
+
+   fn value(&mut self) -> Result<Json, String> {
+      if self.depth == MAX_DEPTH {
+         return Err(format!(
+            "nested deeper than {MAX_DEPTH} at byte {}",
+            self.at
+         ));
+      }
+      self.depth += 1;
+      let value = self.any();
+      self.depth -= 1;
+      value
+   }
+   fn any(&mut self) -> Result<Json, String> {
+      match self.peek() {
+         None => Err("unexpected end".to_string()),
+         Some(b'{') => {
+            self.at += 1;
+            let mut pairs = Vec::new();
+            if self.peek() == Some(b'}') {
+               self.at += 1;
+               return Ok(Json::Obj(pairs));
+            }
+            loop {
+               self.skip_ws();
+               let key = self.string()?;
+               self.expect(b':')?;
+               pairs.push((key, self.value()?));
+               match self.peek() {
+                  Some(b',') => self.at += 1,
+                  Some(b'}') => {
+                     self.at += 1;
+                     return Ok(Json::Obj(pairs));
+                  }
+                  _ => return Err(format!("expected ',' or '}}' at byte {}", self.at)),
+               }
+            }
+         }
+         Some(b'[') => {
+            self.at += 1;
+            let mut items = Vec::new();
+            if self.peek() == Some(b']') {
+               self.at += 1;
+               return Ok(Json::Arr(items));
+            }
+            loop {
+               items.push(self.value()?);
+               match self.peek() {
+                  Some(b',') => self.at += 1,
+                  Some(b']') => {
+                     self.at += 1;
+                     return Ok(Json::Arr(items));
+                  }
+                  _ => return Err(format!("expected ',' or ']' at byte {}", self.at)),
+               }
+            }
+         }
+         Some(b'"') => Ok(Json::Str(self.string()?)),
+         Some(b't') => self.literal("true", Json::Bool(true)),
+         Some(b'f') => self.literal("false", Json::Bool(false)),
+         Some(b'n') => self.literal("null", Json::Null),
+         Some(_) => self.number(),
+      }
+   }
+
+   fn number(&mut self) -> Result<Json, String> {
+      let start = self.at;
+      while self.at < self.src.len()
+         && matches!(
+            self.src[self.at],
+            b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'
+         )
+      {
+         self.at += 1;
+      }
+      let text = std::str::from_utf8(&self.src[start..self.at]).unwrap_or("");
+      match text.parse::<f64>() {
+         Ok(n) => Ok(Json::Num(n)),
+         Err(_) => Err(format!("invalid number at byte {start}")),
+      }
+   }
+
+   fn string(&mut self) -> Result<String, String> {
+      if self.src.get(self.at) != Some(&b'"') {
+         return Err(format!("expected string at byte {}", self.at));
+      }
+      self.at += 1;
+      let mut bytes = Vec::new();
+      loop {
+         let b = match self.src.get(self.at) {
+            None => return Err("unterminated string".to_string()),
+            Some(b) => *b,
+         };
+         self.at += 1;
+         match b {
+            b'"' => break,
+            b'\\' => {
+               let e = self.src.get(self.at).copied().unwrap_or(b'"');
+               self.at += 1;
+               match e {
+                  b'n' => bytes.push(b'\n'),
+                  b't' => bytes.push(b'\t'),
+                  b'r' => bytes.push(b'\r'),
+                  b'b' => bytes.push(0x08),
+                  b'f' => bytes.push(0x0C),
+                  b'u' => {
+                     let c = self.hex4()?;
+                     //surrogate pairs come as two escapes
+                     let c = match (0xD800..0xDC00).contains(&c) {
+                        true if self.src[self.at..].starts_with(b"\\u") => {
+                           self.at += 2;
+                           let low = self.hex4()?;
+                           0x10000 + ((c - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+                        }
+                        _ => c,
+                     };
+                     let ch = char::from_u32(c).unwrap_or('\u{FFFD}');
+                     bytes.extend(ch.to_string().as_bytes());
+                  }
+                  other => bytes.push(other),
+               }
+            }
+            other => bytes.push(other),
+         }
+      }
+      String::from_utf8(bytes).map_err(|_| "string is not utf8".to_string())
+   }
+
+   fn hex4(&mut self) -> Result<u32, String> {
+      let digits = self
+         .src
+         .get(self.at..self.at + 4)
+         .and_then(|d| std::str::from_utf8(d).ok())
+         .and_then(|d| u32::from_str_radix(d, 16).ok());
+      self.at += 4;
+      digits.ok_or_else(|| format!("invalid unicode escape at byte {}", self.at - 4))
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::{Json, MAX_DEPTH};
+
+   #[test]
+   fn nesting_is_capped() {
+      let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+      assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+      assert!(Json::parse(&nested(MAX_DEPTH + 1)).is_err());
+      assert!(Json::parse(&"{\"a\":".repeat(100_000)).is_err());
+   }
+}
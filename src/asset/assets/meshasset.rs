@@ -1,5 +1,9 @@
+use super::gltf::GLTF;
+use super::nmesh::NMeshReject;
+use super::obj::OBJ;
+use super::ply::PLY;
+use super::stl::STL;
 use crate::*;
-use std::collections::HashMap;
 
 macro_rules! stringify {
    ($t:expr) => {
@@ -7,115 +11,54 @@ macro_rules! stringify {
    };
 }
 
-enum OBJ {
-   Parsed {
-      pos_attr: PosATTR,
-      col_attr: ColATTR,
-      uvm_attr: UVMATTR,
-      nrm_attr: NrmATTR,
-      indices: Indices,
-   },
-   NonTriangle(String),
+//which attribute a vertex layout refers to, customs by attach order
+#[derive(Clone, Debug, PartialEq)]
+pub enum VertexAttr {
+   Pos,
+   Col,
+   UVM,
+   Nrm,
+   Tan,
+   Custom(usize),
 }
 
-impl OBJ {
-   fn parse(src: &str) -> OBJ {
-      let mut pos_attr = PosATTR::empty();
-      let mut col_attr = ColATTR::empty();
-      let mut uvm_attr = UVMATTR::empty();
-      let mut nrm_attr = NrmATTR::empty();
-      let mut indices = Indices::empty();
-
-      let mut pos_data = Vec::new();
-      let mut uvm_data = Vec::new();
-      let mut nrm_data = Vec::new();
-      type Vert = Vec<usize>;
-      let mut verts: Vec<Vert> = Vec::new();
-      let mut unique_verts = HashMap::new();
+//how attributes are split into vertex buffers
+#[derive(Clone, Debug)]
+pub enum VertexLayout {
+   Interleaved,
+   Separate,
+   //one stream per group, attributes left out share a last stream
+   Grouped(Vec<Vec<VertexAttr>>),
+}
 
-      for line in src.lines() {
-         let line = line.trim();
-         let words = line.split(' ').collect::<Vec<&str>>();
-         if words.is_empty() {
-            continue;
-         }
-         match words[0] {
-            "v" => pos_data.push(words.parse_3_to_f32()),
-            "vt" => uvm_data.push(words.parse_2_to_f32()),
-            "vn" => nrm_data.push(words.parse_3_to_f32()),
-            "f" => {
-               if words.len() != 4 {
-                  return OBJ::NonTriangle(line.to_string());
-               }
-               for word in &words[1..] {
-                  let tokens = word.split('/').collect::<Vec<&str>>();
-                  let vert = tokens.parse_to_usize();
-                  verts.push(vert);
+impl VertexLayout {
+   //indices into `present` for each stream, attributes the asset doesn't have are ignored
+   pub(crate) fn group(&self, present: &[VertexAttr]) -> Vec<Vec<usize>> {
+      let mut groups: Vec<Vec<usize>> = match self {
+         VertexLayout::Interleaved => vec![(0..present.len()).collect()],
+         VertexLayout::Separate => (0..present.len()).map(|i| vec![i]).collect(),
+         VertexLayout::Grouped(wanted) => {
+            let mut groups: Vec<Vec<usize>> = Vec::new();
+            let mut used = Vec::new();
+            for group in wanted.iter() {
+               let mut indices = Vec::new();
+               for attr in group.iter() {
+                  match present.iter().position(|p| p == attr) {
+                     Some(i) if !used.contains(&i) => {
+                        used.push(i);
+                        indices.push(i);
+                     }
+                     _ => {}
+                  }
                }
+               groups.push(indices);
             }
-            _ => {}
+            groups.push((0..present.len()).filter(|i| !used.contains(i)).collect());
+            groups
          }
-      }
-      let attr_count = verts[0].len();
-      let pos_exists = attr_count > 0;
-      let uvm_exists = attr_count > 1;
-      let nrm_exists = attr_count > 2;
-
-      let def_uvm = [[0.0, 0.0], [0.0, 1.0], [1.0, 0.0]];
-      let def_col = [1.0, 1.0, 1.0];
-      let def_nrm = [1.0, 1.0, 1.0];
-      for (i, vert) in verts.iter().enumerate() {
-         let pos_index = match pos_exists {
-            true => Some(vert[0]),
-            _ => None,
-         };
-         let uvm_index = match uvm_exists {
-            true => Some(vert[1]),
-            _ => None,
-         };
-         let nrm_index = match nrm_exists {
-            true => Some(vert[2]),
-            _ => None,
-         };
-
-         let key = (pos_index, uvm_index, nrm_index);
-         if unique_verts.contains_key(&key) {
-            let idx = unique_verts[&key] as u32;
-            indices.shove(idx);
-         } else {
-            let v_local = i % 3;
-            let new = pos_attr.data.len();
-            unique_verts.insert(key, new);
-            pos_attr.shove(match pos_index {
-               Some(id) => pos_data[id],
-               None => [0.0; 3],
-            });
-            uvm_attr.shove(match uvm_index {
-               Some(id) => uvm_data[id],
-               None => def_uvm[v_local],
-            });
-            nrm_attr.shove(match nrm_index {
-               Some(id) => nrm_data[id],
-               None => def_nrm,
-            });
-            col_attr.shove(def_col);
-            indices.shove(new as u32);
-         }
-      }
-
-      pos_attr.calc_info();
-      col_attr.calc_info();
-      uvm_attr.calc_info();
-      nrm_attr.calc_info();
-      indices.calc_info();
-
-      OBJ::Parsed {
-         pos_attr,
-         col_attr,
-         uvm_attr,
-         nrm_attr,
-         indices,
-      }
+      };
+      groups.retain(|g| !g.is_empty());
+      groups
    }
 }
 
@@ -125,100 +68,303 @@ pub struct NEMeshAsset {
    pub(crate) col_attr: ColATTR,
    pub(crate) uvm_attr: UVMATTR,
    pub(crate) nrm_attr: NrmATTR,
+   //xyz tangent, w the bitangent sign, see generate_tangents
+   pub(crate) tan_attr: TanATTR,
    pub(crate) cus_attrs: Vec<CustomATTR>,
    pub(crate) indices: Indices,
+   //built-ins keep locations 0..4 even when some are missing
+   pub(crate) fixed_locations: bool,
+   pub(crate) vertex_layout: VertexLayout,
+   //point clouds come in as Points, the mesh built from the asset starts with this
+   pub(crate) draw_mode: DrawMode,
+   //one group per triangle, 0 is flat, filled from obj "s" lines
+   pub(crate) smoothing: Vec<u32>,
 }
 
 impl NEMeshAsset {
    pub fn from_path(path: &str) -> NEResult<NEMeshAsset> {
       NEMeshAsset::from_path_raw(&env::concat_with_asset(path))
    }
+   //sources are compiled to a binary nmesh next to the other mesh assets, it is reused while
+   //the source is unchanged and loaded on its own when only the nmesh is shipped
    fn from_path_raw(raw_path: &str) -> NEResult<NEMeshAsset> {
       let file_name = match file::name(raw_path) {
          NEOption::Empty => return NEResult::ER(NEError::file_invalid(raw_path)),
          NEOption::Exists(n) => n,
       };
-      let _ = match file::ex(raw_path) {
+      let ex = match file::ex(raw_path) {
          NEOption::Empty => return NEResult::ER(NEError::file_invalid(raw_path)),
-         NEOption::Exists(ex) => match ex.eq_ignore_ascii_case(ex::OBJ) {
-            false => return NEResult::ER(NEError::file_unsupported(raw_path, &ex)),
-            true => ex,
-         },
+         NEOption::Exists(ex) => ex.to_ascii_lowercase(),
       };
-      let nmesh_path = format!("{}{}.{}", path::MESH_ASSET, file_name, ex::NMESH);
+      if ![ex::OBJ, ex::PLY, ex::STL, ex::GLTF, ex::GLB].contains(&ex.as_str()) {
+         return NEResult::ER(NEError::file_unsupported(raw_path, &ex));
+      }
+      //the source extension stays in the name so cube.obj and cube.stl don't share a cache
+      let nmesh_name = format!("{file_name}.{ex}.{}", ex::NMESH);
+      let nmesh_path = format!("{}{}", path::MESH_ASSET, nmesh_name);
 
-      let file_exists = file::exists_on_disk(raw_path);
+      let stamp = file::stamp(raw_path);
       let nmesh_exists = file::exists_on_disk(&nmesh_path);
 
-      if !file_exists && !nmesh_exists {
-         let both_paths = format!("{} or {}", raw_path, nmesh_path);
-         return NEResult::ER(NEError::file_missing(&both_paths));
-      }
-      if file_exists {
-         //write/overwrite nmesh
-         let obj_src = match file::read_as_string(raw_path) {
+      if !nmesh_exists {
+         if let NEOption::Empty = stamp {
+            let both_paths = format!("{} or {}", raw_path, nmesh_path);
+            return NEResult::ER(NEError::file_missing(&both_paths));
+         }
+      } else {
+         let bytes = match file::read_as_bytes(&nmesh_path) {
             NEResult::ER(e) => return NEResult::ER(e),
-            NEResult::OK(of) => of,
+            NEResult::OK(b) => b,
          };
-         let nmesh = match OBJ::parse(&obj_src) {
-            OBJ::NonTriangle(line) => {
-               return NEResult::ER(NEError::non_triangulated(raw_path, line))
-            }
-            OBJ::Parsed {
-               pos_attr,
-               col_attr,
-               uvm_attr,
-               nrm_attr,
-               indices,
-            } => NEMeshAsset {
-               transform: Transform::default(),
-               cus_attrs: Vec::new(),
-               pos_attr,
-               col_attr,
-               uvm_attr,
-               nrm_attr,
-               indices,
-            },
+         let source_stamp = match stamp {
+            NEOption::Exists(s) => Some(s),
+            NEOption::Empty => None,
          };
+         match NEMeshAsset::from_nmesh(&bytes, source_stamp) {
+            Ok(nmesh) => return NEResult::OK(nmesh),
+            //nothing to rebuild from
+            Err(NMeshReject::Stale(msg) | NMeshReject::Corrupt(msg)) if source_stamp.is_none() => {
+               return NEResult::ER(NEError::mesh_invalid(&nmesh_path, &msg));
+            }
+            Err(NMeshReject::Stale(_)) => {}
+            Err(NMeshReject::Corrupt(msg)) => {
+               log_warn!("[{nmesh_path}] {msg}, rebuilding it from [{raw_path}]");
+            }
+         }
+      }
 
-         let nmesh_name = format!("{file_name}.{}", ex::NMESH);
-         match file::write_str_to_disk(path::MESH_ASSET, &nmesh_name, &obj_src) {
+      let stamp = match stamp {
+         NEOption::Exists(s) => s,
+         NEOption::Empty => (0, 0),
+      };
+      let (nmesh, files) = match NEMeshAsset::from_source(raw_path, &ex) {
+         NEResult::ER(e) => return NEResult::ER(e),
+         NEResult::OK(m) => m,
+      };
+      //files that can't be stamped now are recorded as missing, so the cache stays stale
+      let deps: Vec<(String, (u64, u64))> = files
+         .into_iter()
+         .map(|f| match file::stamp(&f) {
+            NEOption::Exists(s) => (f, s),
+            NEOption::Empty => (f, (0, 0)),
+         })
+         .collect();
+      let bytes = nmesh.to_nmesh(stamp, &deps);
+      match file::write_bytes_to_disk(path::MESH_ASSET, &nmesh_name, &bytes) {
+         NEResult::ER(e) => NEResult::ER(e),
+         _ => NEResult::OK(nmesh),
+      }
+   }
+   //the asset and the other files it was built from
+   fn from_source(raw_path: &str, ex: &str) -> NEResult<(NEMeshAsset, Vec<String>)> {
+      let single = |loaded: NEResult<NEMeshAsset>| match loaded {
+         NEResult::ER(e) => NEResult::ER(e),
+         NEResult::OK(asset) => NEResult::OK((asset, Vec::new())),
+      };
+      match ex {
+         ex::OBJ => match file::read_as_string(raw_path) {
             NEResult::ER(e) => NEResult::ER(e),
-            _ => NEResult::OK(nmesh),
-         }
-      } else {
-         //load new/pre-existing nmesh
-         let nmesh_src = match file::read_as_string(&nmesh_path) {
-            NEResult::ER(e) => return NEResult::ER(e),
-            NEResult::OK(f) => f,
-         };
-         let nmesh = match OBJ::parse(&nmesh_src) {
-            OBJ::NonTriangle(line) => {
-               return NEResult::ER(NEError::non_triangulated(raw_path, line))
+            NEResult::OK(src) => single(NEMeshAsset::from_obj(&src, raw_path)),
+         },
+         ex::PLY => single(PLY::load(raw_path)),
+         ex::STL => single(STL::load(raw_path)),
+         //model formats with several meshes give their first one
+         _ => match GLTF::load_parts_and_files(raw_path, ex == ex::GLB) {
+            NEResult::ER(e) => NEResult::ER(e),
+            NEResult::OK((mut parts, files)) => {
+               if parts.len() > 1 {
+                  log_warn!(
+                     "[{raw_path}] has {} meshes, only the first is used, see parts_from_path",
+                     parts.len()
+                  );
+               }
+               NEResult::OK((parts.remove(0).asset, files))
             }
-            OBJ::Parsed {
-               pos_attr,
-               col_attr,
-               uvm_attr,
-               nrm_attr,
-               indices,
-            } => NEMeshAsset {
-               transform: Transform::default(),
-               cus_attrs: Vec::new(),
-               pos_attr,
-               col_attr,
-               uvm_attr,
-               nrm_attr,
-               indices,
-            },
-         };
-         NEResult::OK(nmesh)
+         },
+      }
+   }
+   //every object, group or primitive of a model file as its own asset with its material
+   pub fn parts_from_path(path: &str) -> NEResult<Vec<NEMeshPart>> {
+      NEMeshAsset::parts_from_raw(&env::concat_with_asset(path))
+   }
+   fn parts_from_raw(raw_path: &str) -> NEResult<Vec<NEMeshPart>> {
+      let ex = match file::ex(raw_path) {
+         NEOption::Empty => return NEResult::ER(NEError::file_invalid(raw_path)),
+         NEOption::Exists(ex) => ex.to_ascii_lowercase(),
+      };
+      match ex.as_str() {
+         ex::OBJ => OBJ::load_parts(raw_path),
+         ex::GLTF => GLTF::load_parts(raw_path, false),
+         ex::GLB => GLTF::load_parts(raw_path, true),
+         //single mesh formats give one part named after the file
+         ex::PLY | ex::STL => {
+            let loaded = match ex.as_str() {
+               ex::PLY => PLY::load(raw_path),
+               _ => STL::load(raw_path),
+            };
+            match loaded {
+               NEResult::ER(e) => NEResult::ER(e),
+               NEResult::OK(asset) => NEResult::OK(vec![NEMeshPart {
+                  name: match file::name(raw_path) {
+                     NEOption::Exists(n) => n,
+                     NEOption::Empty => String::new(),
+                  },
+                  asset,
+                  material: None,
+                  textures: Vec::new(),
+               }]),
+            }
+         }
+         _ => NEResult::ER(NEError::file_unsupported(raw_path, &ex)),
+      }
+   }
+   //the whole file as one mesh, see parts_from_path for objects and materials
+   fn from_obj(src: &str, raw_path: &str) -> NEResult<NEMeshAsset> {
+      let obj = match OBJ::parse(src, raw_path, false) {
+         NEResult::ER(e) => return NEResult::ER(e),
+         NEResult::OK(o) => o,
+      };
+      match obj.build(&[]) {
+         NEResult::ER(e) => NEResult::ER(e),
+         NEResult::OK(mut parts) => NEResult::OK(parts.remove(0).asset),
       }
    }
 
+   //for procedural meshes, pass empty attrs for the ones the mesh doesn't have
+   pub fn from_attrs(
+      pos_attr: PosATTR,
+      col_attr: ColATTR,
+      uvm_attr: UVMATTR,
+      nrm_attr: NrmATTR,
+      indices: Indices,
+   ) -> NEMeshAsset {
+      NEMeshAsset {
+         transform: Transform::default(),
+         pos_attr,
+         col_attr,
+         uvm_attr,
+         nrm_attr,
+         tan_attr: TanATTR::empty(),
+         cus_attrs: Vec::new(),
+         indices,
+         fixed_locations: false,
+         vertex_layout: VertexLayout::Interleaved,
+         draw_mode: DrawMode::Triangles,
+         smoothing: Vec::new(),
+      }
+   }
+
+   //clip space quad, drawn with identity matrices by post passes
+   pub(crate) fn fullscreen_quad() -> NEMeshAsset {
+      NEMeshAsset::from_attrs(
+         PosATTR::from(vec![
+            [-1.0, -1.0, 0.0],
+            [1.0, -1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [-1.0, 1.0, 0.0],
+         ]),
+         ColATTR::from(vec![[1.0, 1.0, 1.0]; 4]),
+         UVMATTR::from(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]),
+         NrmATTR::empty(),
+         Indices::from(vec![0, 1, 2, 0, 2, 3]),
+      )
+   }
+
    pub fn attach_custom_attr(&mut self, cus_attr: CustomATTR) {
       self.cus_attrs.push(cus_attr);
    }
+   pub fn attach_custom_attr_at(
+      &mut self,
+      location: u32,
+      mut cus_attr: CustomATTR,
+   ) -> NEResult<()> {
+      let (builtins, customs) = self.attr_locations();
+      let taken = builtins.iter().flatten().chain(customs.iter());
+      if taken.into_iter().any(|l| *l == location) {
+         return NEResult::ER(NEError::layout_mismatch(
+            "",
+            vec![format!("location {location} is already used by the mesh")],
+         ));
+      }
+      cus_attr.set_location(location);
+      self.cus_attrs.push(cus_attr);
+      NEResult::OK(())
+   }
+   //the location is looked up by name in the shader, see resolve_locations
+   pub fn attach_named_attr(&mut self, name: &str, mut cus_attr: CustomATTR) {
+      cus_attr.set_name(name);
+      self.cus_attrs.push(cus_attr);
+   }
+   //takes the locations of named custom attrs from the shader's reflected vertex inputs
+   pub fn resolve_locations(&mut self, shader: &NEShader) -> NEResult<()> {
+      let mut problems = Vec::new();
+      for cus_attr in self.cus_attrs.iter_mut() {
+         let name = match &cus_attr.name {
+            None => continue,
+            Some(n) => n,
+         };
+         match shader.info.attribute_named(name).and_then(|a| a.location) {
+            None => problems.push(format!("'{name}' is not a vertex input of the shader")),
+            Some(l) => cus_attr.location = Some(l),
+         }
+      }
+      match problems.is_empty() {
+         true => NEResult::OK(()),
+         false => NEResult::ER(NEError::layout_mismatch(
+            &format!("program {}", shader.id),
+            problems,
+         )),
+      }
+   }
+   pub fn set_fixed_locations(&mut self, fixed: bool) {
+      self.fixed_locations = fixed
+   }
+   pub fn set_vertex_layout(&mut self, layout: VertexLayout) {
+      self.vertex_layout = layout
+   }
+   pub fn set_draw_mode(&mut self, draw_mode: DrawMode) {
+      self.draw_mode = draw_mode
+   }
+   pub fn draw_mode(&self) -> DrawMode {
+      self.draw_mode
+   }
+
+   //(pos, col, uvm, nrm, tan) locations if present, then one per custom attr
+   pub(crate) fn attr_locations(&self) -> ([Option<u32>; 5], Vec<u32>) {
+      let present = [
+         self.pos_attr.has_data(),
+         self.col_attr.has_data(),
+         self.uvm_attr.has_data(),
+         self.nrm_attr.has_data(),
+         self.tan_attr.has_data(),
+      ];
+      let explicit: Vec<u32> = self.cus_attrs.iter().filter_map(|c| c.location).collect();
+      let mut builtins = [None; 5];
+      let mut next = 0;
+      for (i, exists) in present.iter().enumerate() {
+         if self.fixed_locations {
+            next = present.len() as u32;
+            builtins[i] = exists.then_some(i as u32);
+         } else if *exists {
+            builtins[i] = Some(next);
+            next += 1;
+         }
+      }
+      let mut customs = Vec::new();
+      for cus_attr in self.cus_attrs.iter() {
+         match cus_attr.location {
+            Some(l) => customs.push(l),
+            None => {
+               while explicit.contains(&next) || builtins.contains(&Some(next)) {
+                  next += 1;
+               }
+               customs.push(next);
+               next += 1;
+            }
+         }
+      }
+      (builtins, customs)
+   }
 
    pub fn has_no_attr(&self) -> bool {
       let no_attr = self.starts_with_custom();
@@ -231,43 +377,10 @@ impl NEMeshAsset {
          && self.col_attr.is_empty()
          && self.uvm_attr.is_empty()
          && self.nrm_attr.is_empty()
+         && self.tan_attr.is_empty()
    }
 
    pub(crate) fn has_custom_attrs(&self) -> bool {
       !self.cus_attrs.is_empty()
    }
 }
-
-trait ParseWords {
-   fn parse_2_to_f32(&self) -> [f32; 2];
-   fn parse_3_to_f32(&self) -> [f32; 3];
-   fn parse_to_usize(&self) -> Vec<usize>;
-}
-impl ParseWords for Vec<&str> {
-   fn parse_2_to_f32(&self) -> [f32; 2] {
-      const N: usize = 2;
-      let mut elem = [0.0; N];
-      for i in 1..=N {
-         elem[i - 1] = self[i].parse::<f32>().unwrap_or(0.0)
-      }
-      elem[1] *= -1.0;
-      elem
-   }
-
-   fn parse_3_to_f32(&self) -> [f32; 3] {
-      const N: usize = 3;
-      let mut elem = [0.0; N];
-      for i in 1..=N {
-         elem[i - 1] = self[i].parse::<f32>().unwrap_or(0.0)
-      }
-      elem
-   }
-
-   fn parse_to_usize(&self) -> Vec<usize> {
-      let mut elem: Vec<usize> = Vec::new();
-      for str in self {
-         elem.push(str.parse::<usize>().unwrap_or(1) - 1);
-      }
-      elem
-   }
-}
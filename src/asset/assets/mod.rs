@@ -1,9 +1,22 @@
 mod attrib;
+mod cleanup;
+mod gltf;
+mod json;
 mod meshasset;
+mod nmesh;
+mod normals;
+mod obj;
+mod ply;
 mod shaderasset;
+mod spirv;
+mod stl;
+mod tangents;
 mod textureasset;
 
 pub use attrib::*;
+pub use gltf::{JOINTS, WEIGHTS};
 pub use meshasset::*;
+pub use obj::{MTLMaterial, NEMeshPart};
 pub use shaderasset::*;
+pub use spirv::*;
 pub use textureasset::*;
@@ -0,0 +1,635 @@
+use crate::*;
+
+//compiled mesh cache, everything native endian:
+//  magic, version, endian, interleaved, draw mode, attr count, source length and mtime,
+//  dependency count, per dependency: path length + path, length and mtime,
+//  vertex count, index count, transform (pos, rot, scale), bounds (min, max),
+//  fixed locations, vertex layout: kind, groups of (slot, custom index),
+//  smoothing group count + groups,
+//  attr table: slot, type, kind, elem count, location, name length + name,
+//  vertex data, interleaved or one block per attr, u32 indices, fnv-1a checksum of the rest
+const MAGIC: &[u8; 4] = b"NMSH";
+const VERSION: u32 = 2;
+const NO_LOCATION: u32 = u32::MAX;
+
+//custom attrs use slot 5
+const SLOT_CUSTOM: u8 = 5;
+
+pub(crate) enum NMeshReject {
+   //older version, another machine or changed source, recompiling fixes it
+   Stale(String),
+   Corrupt(String),
+}
+
+fn checksum(bytes: &[u8]) -> u64 {
+   let mut hash: u64 = 0xcbf29ce484222325;
+   for b in bytes.iter() {
+      hash ^= *b as u64;
+      hash = hash.wrapping_mul(0x100000001b3);
+   }
+   hash
+}
+
+fn typ_code(typ: &ATTRType) -> (u8, usize, &'static str) {
+   match typ {
+      ATTRType::U8 => (0, 1, "u8"),
+      ATTRType::I8 => (1, 1, "i8"),
+      ATTRType::U16 => (2, 2, "u16"),
+      ATTRType::I16 => (3, 2, "i16"),
+      ATTRType::U32 => (4, 4, "u32"),
+      ATTRType::I32 => (5, 4, "i32"),
+      ATTRType::F32 => (6, 4, "f32"),
+      ATTRType::F64 => (7, 8, "f64"),
+   }
+}
+fn typ_from(code: u8) -> Option<ATTRType> {
+   [
+      ATTRType::U8,
+      ATTRType::I8,
+      ATTRType::U16,
+      ATTRType::I16,
+      ATTRType::U32,
+      ATTRType::I32,
+      ATTRType::F32,
+      ATTRType::F64,
+   ]
+   .get(code as usize)
+   .cloned()
+}
+const KINDS: [ATTRKind; 4] = [
+   ATTRKind::Float,
+   ATTRKind::Normalized,
+   ATTRKind::Integer,
+   ATTRKind::Double,
+];
+const DRAW_MODES: [DrawMode; 4] = [
+   DrawMode::Points,
+   DrawMode::Lines,
+   DrawMode::Triangles,
+   DrawMode::Strip,
+];
+
+//one attribute as written, builtins are f32 vectors
+struct NMeshAttr {
+   slot: u8,
+   info: ATTRInfo,
+   location: Option<u32>,
+   name: Option<String>,
+   bytes: Vec<u8>,
+}
+
+impl NMeshAttr {
+   fn size(&self) -> usize {
+      self.info.byte_count * self.info.elem_count
+   }
+}
+
+struct NMeshReader<'a> {
+   bytes: &'a [u8],
+   at: usize,
+}
+
+impl<'a> NMeshReader<'a> {
+   fn take(&mut self, n: usize) -> Result<&'a [u8], NMeshReject> {
+      let end = self.at.checked_add(n).filter(|e| *e <= self.bytes.len());
+      match end {
+         None => Err(NMeshReject::Corrupt("file ends early".to_string())),
+         Some(end) => {
+            let slice = &self.bytes[self.at..end];
+            self.at = end;
+            Ok(slice)
+         }
+      }
+   }
+   fn u8(&mut self) -> Result<u8, NMeshReject> {
+      Ok(self.take(1)?[0])
+   }
+   fn u32(&mut self) -> Result<u32, NMeshReject> {
+      let b = self.take(4)?;
+      Ok(u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+   }
+   fn u64(&mut self) -> Result<u64, NMeshReject> {
+      let b = self.take(8)?;
+      Ok(u64::from_ne_bytes([
+         b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7],
+      ]))
+   }
+   fn string(&mut self) -> Result<String, NMeshReject> {
+      let len = self.u32()? as usize;
+      match std::str::from_utf8(self.take(len)?) {
+         Err(_) => Err(NMeshReject::Corrupt("string is not utf8".to_string())),
+         Ok(s) => Ok(s.to_string()),
+      }
+   }
+   fn f32s<const N: usize>(&mut self) -> Result<[f32; N], NMeshReject> {
+      let mut out = [0.0; N];
+      for o in out.iter_mut() {
+         *o = f32::from_bits(self.u32()?);
+      }
+      Ok(out)
+   }
+}
+
+fn push_str(out: &mut Vec<u8>, s: &str) {
+   out.extend((s.len() as u32).to_ne_bytes());
+   out.extend(s.as_bytes());
+}
+
+//vertex layout attrs use the attr table slots, customs add their index
+fn attr_code(attr: &VertexAttr) -> (u8, u32) {
+   match attr {
+      VertexAttr::Pos => (0, 0),
+      VertexAttr::Col => (1, 0),
+      VertexAttr::UVM => (2, 0),
+      VertexAttr::Nrm => (3, 0),
+      VertexAttr::Tan => (4, 0),
+      VertexAttr::Custom(i) => (SLOT_CUSTOM, *i as u32),
+   }
+}
+fn attr_from(slot: u8, index: u32) -> Option<VertexAttr> {
+   match slot {
+      0 => Some(VertexAttr::Pos),
+      1 => Some(VertexAttr::Col),
+      2 => Some(VertexAttr::UVM),
+      3 => Some(VertexAttr::Nrm),
+      4 => Some(VertexAttr::Tan),
+      SLOT_CUSTOM => Some(VertexAttr::Custom(index as usize)),
+      _ => None,
+   }
+}
+
+fn bytes_of<T: DataType>(data: &[T]) -> Vec<u8> {
+   data.iter().flat_map(|e| e.u8ify()).collect()
+}
+fn floats<const N: usize>(bytes: &[u8]) -> Vec<[f32; N]> {
+   bytes
+      .chunks_exact(4 * N)
+      .map(|e| {
+         let mut elem = [0.0; N];
+         for (i, c) in elem.iter_mut().enumerate() {
+            let b = &e[i * 4..i * 4 + 4];
+            *c = f32::from_ne_bytes([b[0], b[1], b[2], b[3]]);
+         }
+         elem
+      })
+      .collect()
+}
+
+impl NEMeshAsset {
+   //(min, max) of the positions
+   pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+      let first = *self.pos_attr.data.first()?;
+      let (mut min, mut max) = (first, first);
+      for p in self.pos_attr.data.iter() {
+         for i in 0..3 {
+            min[i] = min[i].min(p[i]);
+            max[i] = max[i].max(p[i]);
+         }
+      }
+      Some((min, max))
+   }
+
+   fn nmesh_attrs(&self) -> Vec<NMeshAttr> {
+      let mut attrs = Vec::new();
+      let mut builtin = |slot: u8, info: &ATTRInfo, bytes: Vec<u8>| {
+         if info.exists {
+            attrs.push(NMeshAttr {
+               slot,
+               info: info.clone(),
+               location: None,
+               name: None,
+               bytes,
+            });
+         }
+      };
+      builtin(0, &self.pos_attr.info, bytes_of(&self.pos_attr.data));
+      builtin(1, &self.col_attr.info, bytes_of(&self.col_attr.data));
+      builtin(2, &self.uvm_attr.info, bytes_of(&self.uvm_attr.data));
+      builtin(3, &self.nrm_attr.info, bytes_of(&self.nrm_attr.data));
+      builtin(4, &self.tan_attr.info, bytes_of(&self.tan_attr.data));
+      for cus_attr in self.cus_attrs.iter().filter(|c| c.has_data()) {
+         attrs.push(NMeshAttr {
+            slot: SLOT_CUSTOM,
+            info: cus_attr.info.clone(),
+            location: cus_attr.location,
+            name: cus_attr.name.clone(),
+            bytes: cus_attr.data.clone(),
+         });
+      }
+      attrs
+   }
+
+   //`stamp` is the source's (length, mtime) and `deps` the same for every other file it was
+   //built from, so edits to any of them make the cache stale
+   pub(crate) fn to_nmesh(&self, stamp: (u64, u64), deps: &[(String, (u64, u64))]) -> Vec<u8> {
+      let attrs = self.nmesh_attrs();
+      let vert_count = attrs.first().map(|a| a.bytes.len() / a.size()).unwrap_or(0);
+      let interleaved = matches!(self.vertex_layout, VertexLayout::Interleaved)
+         && attrs.iter().all(|a| a.bytes.len() == vert_count * a.size());
+      let draw_mode = DRAW_MODES
+         .iter()
+         .position(|d| std::mem::discriminant(d) == std::mem::discriminant(&self.draw_mode))
+         .unwrap_or(2);
+      let (min, max) = self.bounds().unwrap_or(([0.0; 3], [0.0; 3]));
+      let t = &self.transform;
+
+      let mut out: Vec<u8> = Vec::new();
+      out.extend(MAGIC);
+      out.extend(VERSION.to_ne_bytes());
+      out.push(cfg!(target_endian = "little") as u8);
+      out.push(interleaved as u8);
+      out.push(draw_mode as u8);
+      out.push(attrs.len() as u8);
+      out.extend(stamp.0.to_ne_bytes());
+      out.extend(stamp.1.to_ne_bytes());
+      out.extend((deps.len() as u32).to_ne_bytes());
+      for (path, (len, mtime)) in deps.iter() {
+         push_str(&mut out, path);
+         out.extend(len.to_ne_bytes());
+         out.extend(mtime.to_ne_bytes());
+      }
+      out.extend((vert_count as u32).to_ne_bytes());
+      out.extend((self.indices.data.len() as u32).to_ne_bytes());
+      for v in [t.pos, t.rot, t.scale] {
+         out.extend([v.x, v.y, v.z].u8ify());
+      }
+      out.extend(min.u8ify());
+      out.extend(max.u8ify());
+      out.push(self.fixed_locations as u8);
+      match &self.vertex_layout {
+         VertexLayout::Interleaved => out.push(0),
+         VertexLayout::Separate => out.push(1),
+         VertexLayout::Grouped(groups) => {
+            out.push(2);
+            out.extend((groups.len() as u32).to_ne_bytes());
+            for group in groups.iter() {
+               out.extend((group.len() as u32).to_ne_bytes());
+               for attr in group.iter() {
+                  let (slot, index) = attr_code(attr);
+                  out.push(slot);
+                  out.extend(index.to_ne_bytes());
+               }
+            }
+         }
+      }
+      out.extend((self.smoothing.len() as u32).to_ne_bytes());
+      for group in self.smoothing.iter() {
+         out.extend(group.to_ne_bytes());
+      }
+
+      for attr in attrs.iter() {
+         let (code, _, _) = typ_code(&attr.info.typ);
+         let kind = KINDS.iter().position(|k| *k == attr.info.kind).unwrap_or(0);
+         out.push(attr.slot);
+         out.push(code);
+         out.push(kind as u8);
+         out.push(attr.info.elem_count as u8);
+         out.extend(attr.location.unwrap_or(NO_LOCATION).to_ne_bytes());
+         push_str(&mut out, attr.name.as_deref().unwrap_or(""));
+         if !interleaved {
+            out.extend(((attr.bytes.len() / attr.size()) as u32).to_ne_bytes());
+         }
+      }
+      match interleaved {
+         true => {
+            for v in 0..vert_count {
+               for attr in attrs.iter() {
+                  out.extend(&attr.bytes[v * attr.size()..(v + 1) * attr.size()]);
+               }
+            }
+         }
+         false => {
+            for attr in attrs.iter() {
+               out.extend(&attr.bytes);
+            }
+         }
+      }
+      for index in self.indices.data.iter() {
+         out.extend(index.to_ne_bytes());
+      }
+      let sum = checksum(&out);
+      out.extend(sum.to_ne_bytes());
+      out
+   }
+
+   //without a `stamp` the source is gone and any intact nmesh is accepted,
+   //with one the recorded dependencies are stamped again and compared too
+   pub(crate) fn from_nmesh(
+      bytes: &[u8],
+      stamp: Option<(u64, u64)>,
+   ) -> Result<NEMeshAsset, NMeshReject> {
+      let corrupt = |msg: &str| Err(NMeshReject::Corrupt(msg.to_string()));
+      if bytes.len() < 12 || &bytes[..4] != MAGIC {
+         return corrupt("not an nmesh file");
+      }
+      let (body, sum) = bytes.split_at(bytes.len() - 8);
+      let mut r = NMeshReader { bytes: body, at: 4 };
+      let version = r.u32()?;
+      if version != VERSION {
+         return Err(NMeshReject::Stale(format!(
+            "nmesh version {version}, expected {VERSION}"
+         )));
+      }
+      if checksum(body).to_ne_bytes() != sum {
+         return corrupt("checksum mismatch");
+      }
+      if r.u8()? != cfg!(target_endian = "little") as u8 {
+         return Err(NMeshReject::Stale(
+            "written on another endianness".to_string(),
+         ));
+      }
+      let interleaved = r.u8()? == 1;
+      let draw_mode = match DRAW_MODES.get(r.u8()? as usize) {
+         None => return corrupt("unknown draw mode"),
+         Some(d) => *d,
+      };
+      let attr_count = r.u8()? as usize;
+      let written = (r.u64()?, r.u64()?);
+      if stamp.is_some_and(|s| s != written) {
+         return Err(NMeshReject::Stale("source changed".to_string()));
+      }
+      let dep_count = r.u32()?;
+      for _ in 0..dep_count {
+         let path = r.string()?;
+         let written = (r.u64()?, r.u64()?);
+         let current = match file::stamp(&path) {
+            NEOption::Exists(s) => Some(s),
+            NEOption::Empty => None,
+         };
+         if stamp.is_some() && current != Some(written) {
+            return Err(NMeshReject::Stale(format!("[{path}] changed")));
+         }
+      }
+      let vert_count = r.u32()? as usize;
+      let ind_count = r.u32()? as usize;
+      let (pos, rot, scale) = (r.f32s::<3>()?, r.f32s::<3>()?, r.f32s::<3>()?);
+      let _bounds = (r.f32s::<3>()?, r.f32s::<3>()?);
+      let fixed_locations = r.u8()? == 1;
+      let vertex_layout = match r.u8()? {
+         0 => VertexLayout::Interleaved,
+         1 => VertexLayout::Separate,
+         2 => {
+            let mut groups = Vec::new();
+            for _ in 0..r.u32()? {
+               let mut group = Vec::new();
+               for _ in 0..r.u32()? {
+                  match attr_from(r.u8()?, r.u32()?) {
+                     None => return corrupt("unknown vertex layout attribute"),
+                     Some(a) => group.push(a),
+                  }
+               }
+               groups.push(group);
+            }
+            VertexLayout::Grouped(groups)
+         }
+         _ => return corrupt("unknown vertex layout"),
+      };
+      let smoothing_count = r.u32()? as usize;
+      let smoothing: Vec<u32> = r
+         .take(smoothing_count * 4)?
+         .chunks_exact(4)
+         .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+         .collect();
+
+      let mut attrs: Vec<(NMeshAttr, usize)> = Vec::new();
+      for _ in 0..attr_count {
+         let slot = r.u8()?;
+         let typ = match typ_from(r.u8()?) {
+            None => return corrupt("unknown attribute type"),
+            Some(t) => t,
+         };
+         let kind = match KINDS.get(r.u8()? as usize) {
+            None => return corrupt("unknown attribute kind"),
+            Some(k) => *k,
+         };
+         let elem_count = r.u8()? as usize;
+         let location = match r.u32()? {
+            NO_LOCATION => None,
+            l => Some(l),
+         };
+         let name = match r.string()? {
+            n if n.is_empty() => None,
+            n => Some(n),
+         };
+         let count = match interleaved {
+            true => vert_count,
+            false => r.u32()? as usize,
+         };
+         let (_, byte_count, prefix) = typ_code(&typ);
+         let builtin_ok = match slot {
+            0 | 1 | 3 => elem_count == 3,
+            2 => elem_count == 2,
+            4 => elem_count == 4,
+            SLOT_CUSTOM => (1..=4).contains(&elem_count),
+            _ => false,
+         };
+         if !builtin_ok || (slot != SLOT_CUSTOM && !matches!(typ, ATTRType::F32)) {
+            return corrupt("attribute table does not fit its slot");
+         }
+         let info = ATTRInfo {
+            typ,
+            typ_str: format!("{prefix}x{elem_count}"),
+            exists: true,
+            byte_count,
+            elem_count,
+            kind,
+         };
+         let attr = NMeshAttr {
+            slot,
+            info,
+            location,
+            name,
+            bytes: Vec::new(),
+         };
+         attrs.push((attr, count));
+      }
+
+      match interleaved {
+         true => {
+            let stride: usize = attrs.iter().map(|(a, _)| a.size()).sum();
+            let data = r.take(stride * vert_count)?;
+            for (a, _) in attrs.iter_mut() {
+               a.bytes.reserve(vert_count * a.size());
+            }
+            for vert in data.chunks_exact(stride.max(1)) {
+               let mut offset = 0;
+               for (a, _) in attrs.iter_mut() {
+                  let size = a.size();
+                  a.bytes.extend(&vert[offset..offset + size]);
+                  offset += size;
+               }
+            }
+         }
+         false => {
+            for (a, count) in attrs.iter_mut() {
+               let size = a.size() * *count;
+               a.bytes = r.take(size)?.to_vec();
+            }
+         }
+      }
+      let indices: Vec<u32> = r
+         .take(ind_count * 4)?
+         .chunks_exact(4)
+         .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+         .collect();
+      if r.at != body.len() {
+         return corrupt("trailing data");
+      }
+
+      let mut nmesh = NEMeshAsset::from_attrs(
+         PosATTR::empty(),
+         ColATTR::empty(),
+         UVMATTR::empty(),
+         NrmATTR::empty(),
+         Indices::from(indices),
+      );
+      nmesh.draw_mode = draw_mode;
+      nmesh.fixed_locations = fixed_locations;
+      nmesh.vertex_layout = vertex_layout;
+      nmesh.smoothing = smoothing;
+      nmesh.transform = Transform::with_pos(pos[0], pos[1], pos[2]);
+      nmesh.transform.set_rotation(rot[0], rot[1], rot[2]);
+      nmesh.transform.set_scale(scale[0], scale[1], scale[2]);
+      for (attr, _) in attrs {
+         match attr.slot {
+            0 => nmesh.pos_attr = PosATTR::from(floats(&attr.bytes)),
+            1 => nmesh.col_attr = ColATTR::from(floats(&attr.bytes)),
+            2 => nmesh.uvm_attr = UVMATTR::from(floats(&attr.bytes)),
+            3 => nmesh.nrm_attr = NrmATTR::from(floats(&attr.bytes)),
+            4 => nmesh.tan_attr = TanATTR::from(floats(&attr.bytes)),
+            _ => nmesh.cus_attrs.push(CustomATTR {
+               data: attr.bytes,
+               info: attr.info,
+               name: attr.name,
+               location: attr.location,
+            }),
+         }
+      }
+      Ok(nmesh)
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::NMeshReject;
+   use crate::asset::file;
+   use crate::*;
+
+   fn quad() -> NEMeshAsset {
+      NEMeshAsset::from_attrs(
+         PosATTR::from(vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+         ]),
+         ColATTR::empty(),
+         UVMATTR::from(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]),
+         NrmATTR::empty(),
+         Indices::from(vec![0, 1, 2, 0, 2, 3]),
+      )
+   }
+
+   fn with_customs() -> NEMeshAsset {
+      let mut asset = quad();
+      asset.col_attr = ColATTR::from(vec![
+         [1.0, 0.0, 0.0],
+         [0.0, 1.0, 0.0],
+         [0.0, 0.0, 1.0],
+         [1.0; 3],
+      ]);
+      let mut ids = CustomATTR::from(vec![[1u16, 2], [3, 4], [5, 6], [7, 8]]);
+      ids.set_kind(ATTRKind::Integer).unpack();
+      asset.attach_custom_attr_at(6, ids).unpack();
+      asset.attach_named_attr("vWgt", CustomATTR::from(vec![0.25f32, 0.5, 0.75, 1.0]));
+      asset
+   }
+   fn same(a: &NEMeshAsset, b: &NEMeshAsset) {
+      assert_eq!(a.pos_attr.data(), b.pos_attr.data());
+      assert_eq!(a.col_attr.data(), b.col_attr.data());
+      assert_eq!(a.uvm_attr.data(), b.uvm_attr.data());
+      assert_eq!(a.indices.data(), b.indices.data());
+      assert_eq!(a.cus_attrs.len(), b.cus_attrs.len());
+      for (x, y) in a.cus_attrs.iter().zip(b.cus_attrs.iter()) {
+         assert_eq!(x.data(), y.data());
+         assert_eq!(x.info.typ_str, y.info.typ_str);
+         assert_eq!(x.kind(), y.kind());
+         assert_eq!(x.location(), y.location());
+         assert_eq!(x.name(), y.name());
+      }
+   }
+
+   #[test]
+   fn both_layouts_round_trip() {
+      let mut asset = with_customs();
+      for layout in [VertexLayout::Interleaved, VertexLayout::Separate] {
+         asset.set_vertex_layout(layout);
+         let bytes = asset.to_nmesh((1, 2), &[]);
+         let read = NEMeshAsset::from_nmesh(&bytes, Some((1, 2))).ok().unwrap();
+         same(&asset, &read);
+         assert_eq!(read.cus_attrs[0].kind(), ATTRKind::Integer);
+         assert_eq!(read.cus_attrs[0].location(), Some(6));
+         assert_eq!(read.cus_attrs[1].name(), Some("vWgt"));
+      }
+   }
+
+   #[test]
+   fn damaged_files_are_corrupt() {
+      let good = with_customs().to_nmesh((1, 2), &[]);
+      let corrupt = |bytes: &[u8]| {
+         matches!(
+            NEMeshAsset::from_nmesh(bytes, Some((1, 2))),
+            Err(NMeshReject::Corrupt(_))
+         )
+      };
+      let mut bad_magic = good.clone();
+      bad_magic[0] = b'X';
+      assert!(corrupt(&bad_magic));
+      let mut flipped = good.clone();
+      *flipped.last_mut().unwrap() ^= 0x01;
+      assert!(corrupt(&flipped));
+      let mut flipped = good.clone();
+      flipped[good.len() / 2] ^= 0x01;
+      assert!(corrupt(&flipped));
+      assert!(corrupt(&good[..good.len() - 5]));
+      assert!(corrupt(&good[..10]));
+   }
+
+   #[test]
+   fn settings_survive_the_cache() {
+      let mut asset = quad();
+      asset.set_fixed_locations(true);
+      let layout = vec![
+         vec![VertexAttr::UVM],
+         vec![VertexAttr::Pos, VertexAttr::Custom(0)],
+      ];
+      asset.set_vertex_layout(VertexLayout::Grouped(layout.clone()));
+      asset.smoothing = vec![1, 0];
+      let bytes = asset.to_nmesh((1, 2), &[]);
+      let read = NEMeshAsset::from_nmesh(&bytes, Some((1, 2))).ok().unwrap();
+      assert!(read.fixed_locations);
+      assert!(matches!(read.vertex_layout, VertexLayout::Grouped(g) if g == layout));
+      assert_eq!(read.smoothing, [1, 0]);
+   }
+
+   #[test]
+   fn changed_dependencies_make_it_stale() {
+      let dir = std::env::temp_dir().join("nerve-nmesh");
+      std::fs::create_dir_all(&dir).unwrap();
+      let dep = dir.join("mesh.bin").to_string_lossy().to_string();
+      std::fs::write(&dep, [0u8; 4]).unwrap();
+      let stamp = match file::stamp(&dep) {
+         NEOption::Exists(s) => s,
+         NEOption::Empty => panic!("[{dep}] was just written"),
+      };
+      let bytes = quad().to_nmesh((1, 2), &[(dep.clone(), stamp)]);
+      assert!(NEMeshAsset::from_nmesh(&bytes, Some((1, 2))).is_ok());
+
+      std::fs::write(&dep, [0u8; 8]).unwrap();
+      assert!(NEMeshAsset::from_nmesh(&bytes, Some((1, 2))).is_err());
+      //only the nmesh was shipped, nothing to compare against
+      assert!(NEMeshAsset::from_nmesh(&bytes, None).is_ok());
+      std::fs::remove_file(&dep).unwrap();
+      assert!(NEMeshAsset::from_nmesh(&bytes, Some((1, 2))).is_err());
+   }
+}
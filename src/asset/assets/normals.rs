@@ -0,0 +1,190 @@
+use crate::*;
+use cgmath::{InnerSpace, Vector3};
+use std::collections::{HashMap, HashSet};
+
+//which normal a triangle corner takes, flat faces get their own, smooth ones share by position
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum NrmKey {
+   Flat(usize),
+   Smooth([u32; 3], u32),
+}
+
+impl NEMeshAsset {
+   //smooth normals are angle-weighted and shared by faces of the same smoothing group that
+   //touch the same position, group 0 (obj "s off") stays flat, vertices are split where needed
+   pub fn generate_normals(&mut self, smooth: bool) -> NEResult<()> {
+      if let NEResult::ER(e) = self.check_vertices("normal generation") {
+         return NEResult::ER(e);
+      }
+      let vert_count = self.pos_attr.data.len();
+      let triangles = self.triangles();
+      let pos = |i: usize| Vector3::from(self.pos_attr.data[i]);
+
+      let mut sums: HashMap<NrmKey, Vector3<f32>> = HashMap::new();
+      let mut corners: Vec<(usize, NrmKey)> = Vec::with_capacity(triangles.len() * 3);
+      for (f, tri) in triangles.iter().enumerate() {
+         let group = match smooth {
+            true => self.smoothing.get(f).copied().unwrap_or(1),
+            false => 0,
+         };
+         let face = (pos(tri[1]) - pos(tri[0])).cross(pos(tri[2]) - pos(tri[0]));
+         let face = match face.magnitude2() > 0.0 {
+            true => face.normalize(),
+            false => face,
+         };
+         for c in 0..3 {
+            let v = tri[c];
+            let key = match group {
+               0 => NrmKey::Flat(f),
+               _ => NrmKey::Smooth(self.pos_attr.data[v].map(|p| (p + 0.0).to_bits()), group),
+            };
+            let (a, b) = (
+               pos(tri[(c + 1) % 3]) - pos(v),
+               pos(tri[(c + 2) % 3]) - pos(v),
+            );
+            let angle = match a.magnitude2() * b.magnitude2() > 0.0 {
+               true => a.normalize().dot(b.normalize()).clamp(-1.0, 1.0).acos(),
+               false => 0.0,
+            };
+            *sums.entry(key).or_insert(Vector3::new(0.0, 0.0, 0.0)) += face * angle;
+            corners.push((v, key));
+         }
+      }
+
+      //a vertex keeps the first normal it gets, corners wanting another one get a copy
+      let mut targets: HashMap<(usize, NrmKey), usize> = HashMap::new();
+      let mut claimed: HashSet<usize> = HashSet::new();
+      let mut split: Vec<usize> = Vec::new();
+      let mut remapped: Vec<u32> = Vec::with_capacity(corners.len());
+      for corner in corners.iter() {
+         let target = *targets.entry(*corner).or_insert_with(|| {
+            if claimed.insert(corner.0) {
+               return corner.0;
+            }
+            split.push(corner.0);
+            vert_count + split.len() - 1
+         });
+         remapped.push(target as u32);
+      }
+
+      let mut normals = vec![[0.0, 0.0, 1.0]; vert_count + split.len()];
+      for ((_, key), target) in targets.iter() {
+         let sum = sums[key];
+         if sum.magnitude2() > 0.0 {
+            let n = sum.normalize();
+            normals[*target] = [n.x, n.y, n.z];
+         }
+      }
+      for src in split.iter() {
+         self.copy_vertex(*src);
+      }
+      if self.indices.has_data() {
+         self.indices = Indices::from(remapped);
+      }
+      self.nrm_attr = NrmATTR::from(normals);
+      NEResult::OK(())
+   }
+
+   //one smoothing group per triangle, 0 for flat, used by generate_normals
+   pub fn set_smoothing_groups(&mut self, groups: Vec<u32>) {
+      self.smoothing = groups
+   }
+   pub fn smoothing_groups(&self) -> &Vec<u32> {
+      &self.smoothing
+   }
+
+   pub fn flip_normals(&mut self) {
+      for n in self.nrm_attr.data.iter_mut() {
+         *n = n.map(|c| -c);
+      }
+      for t in self.tan_attr.data.iter_mut() {
+         *t = [-t[0], -t[1], -t[2], t[3]];
+      }
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::super::obj::OBJ;
+   use crate::*;
+
+   //unit cube, faces wound outwards, the top face gets the smoothing group `top`
+   fn cube(sides: &str, top: &str) -> NEMeshAsset {
+      let src = format!(
+         "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n\
+          s {sides}\nf 1 4 3 2\nf 1 2 6 5\nf 4 8 7 3\nf 1 5 8 4\nf 2 3 7 6\n\
+          s {top}\nf 5 6 7 8\n"
+      );
+      let obj = OBJ::parse(&src, "cube.obj", false).unpack();
+      obj.build(&[]).unpack().remove(0).asset
+   }
+   fn near(a: [f32; 3], b: [f32; 3]) -> bool {
+      a.iter().zip(b.iter()).all(|(x, y)| (x - y).abs() < 1e-5)
+   }
+
+   #[test]
+   fn smooth_cube_shares_corner_normals() {
+      let asset = cube("1", "1");
+      assert_eq!(asset.pos_attr.data().len(), 8);
+      let d = 1.0 / 3.0f32.sqrt();
+      for (p, n) in asset
+         .pos_attr
+         .data()
+         .iter()
+         .zip(asset.nrm_attr.data().iter())
+      {
+         let expected = p.map(|c| if c > 0.5 { d } else { -d });
+         assert!(near(*n, expected), "{p:?} got {n:?}");
+      }
+   }
+
+   #[test]
+   fn flat_cube_splits_every_face() {
+      for asset in [cube("off", "off"), {
+         let mut asset = cube("1", "1");
+         asset.generate_normals(false).unpack();
+         asset
+      }] {
+         //flat normals belong to triangles, so each of the 12 gets its own corners
+         assert_eq!(asset.pos_attr.data().len(), 36);
+         let normals = asset.nrm_attr.data();
+         for tri in asset.triangles() {
+            let n = normals[tri[0]];
+            assert!(tri.iter().all(|v| near(normals[*v], n)));
+            assert_eq!(n.iter().filter(|c| c.abs() > 0.5).count(), 1);
+            //the normal points away from the centre of the cube
+            let p = asset.pos_attr.data()[tri[0]];
+            let out: f32 = (0..3).map(|i| (p[i] - 0.5) * n[i]).sum();
+            assert!(out > 0.0);
+         }
+      }
+   }
+
+   #[test]
+   fn smoothing_groups_split_where_they_meet() {
+      let asset = cube("1", "2");
+      assert_eq!(asset.pos_attr.data().len(), 12);
+      let up = asset
+         .nrm_attr
+         .data()
+         .iter()
+         .filter(|n| near(**n, [0.0, 0.0, 1.0]))
+         .count();
+      assert_eq!(up, 4);
+   }
+
+   #[test]
+   fn broken_meshes_are_rejected() {
+      let mut asset = NEMeshAsset::from_attrs(
+         PosATTR::from(vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]),
+         ColATTR::empty(),
+         UVMATTR::empty(),
+         NrmATTR::empty(),
+         Indices::from(vec![0, 1, 3]),
+      );
+      assert!(asset.generate_normals(true).is_err());
+      asset.pos_attr = PosATTR::empty();
+      asset.indices = Indices::empty();
+      assert!(asset.generate_normals(true).is_err());
+   }
+}
@@ -0,0 +1,583 @@
+use crate::*;
+use cgmath::{InnerSpace, Vector3};
+use std::collections::HashMap;
+use std::path::Path;
+
+//material from an obj's mtllib, map paths are resolved next to the mtl file
+#[derive(Clone, Debug)]
+pub struct MTLMaterial {
+   pub name: String,
+   pub ambient: [f32; 3],
+   pub diffuse: [f32; 3],
+   pub specular: [f32; 3],
+   pub emissive: [f32; 3],
+   pub shininess: f32,
+   pub opacity: f32,
+   pub diffuse_map: Option<String>,
+   pub normal_map: Option<String>,
+   pub specular_map: Option<String>,
+   pub emissive_map: Option<String>,
+}
+
+impl MTLMaterial {
+   fn named(name: &str) -> MTLMaterial {
+      MTLMaterial {
+         name: name.to_string(),
+         ambient: [0.0; 3],
+         diffuse: [1.0; 3],
+         specular: [0.0; 3],
+         emissive: [0.0; 3],
+         shininess: 0.0,
+         opacity: 1.0,
+         diffuse_map: None,
+         normal_map: None,
+         specular_map: None,
+         emissive_map: None,
+      }
+   }
+
+   pub(crate) fn parse(src: &str, dir: &str) -> Vec<MTLMaterial> {
+      let mut materials: Vec<MTLMaterial> = Vec::new();
+      for line in src.lines() {
+         let words = line.split_whitespace().collect::<Vec<&str>>();
+         if words.is_empty() || words[0].starts_with('#') {
+            continue;
+         }
+         if words[0] == "newmtl" {
+            materials.push(MTLMaterial::named(&words[1..].join(" ")));
+            continue;
+         }
+         let mtl = match materials.last_mut() {
+            None => continue,
+            Some(m) => m,
+         };
+         //map options come before the file name, which is always last
+         let map = || words.last().map(|f| resolve(dir, f));
+         match words[0] {
+            "Ka" => mtl.ambient = words.parse_3_to_f32(),
+            "Kd" => mtl.diffuse = words.parse_3_to_f32(),
+            "Ks" => mtl.specular = words.parse_3_to_f32(),
+            "Ke" => mtl.emissive = words.parse_3_to_f32(),
+            "Ns" => mtl.shininess = words.parse_f32(1, 0.0),
+            "d" => mtl.opacity = words.parse_f32(1, 1.0),
+            "Tr" => mtl.opacity = 1.0 - words.parse_f32(1, 0.0),
+            "map_Kd" if words.len() > 1 => mtl.diffuse_map = map(),
+            "map_Bump" | "map_bump" | "bump" | "norm" if words.len() > 1 => mtl.normal_map = map(),
+            "map_Ks" if words.len() > 1 => mtl.specular_map = map(),
+            "map_Ke" if words.len() > 1 => mtl.emissive_map = map(),
+            _ => {}
+         }
+      }
+      materials
+   }
+}
+
+fn resolve(dir: &str, file: &str) -> String {
+   let file = file.replace('\\', "/");
+   Path::new(dir).join(file).to_string_lossy().to_string()
+}
+
+//one object/group/material run of a model file
+pub struct NEMeshPart {
+   pub name: String,
+   pub asset: NEMeshAsset,
+   pub material: Option<MTLMaterial>,
+   //images the file embeds or references, keyed by the NEMaterial sampler they go on
+   pub textures: Vec<(String, NETexture)>,
+}
+
+//a face corner as indices into the file wide v, vt and vn lists
+type Corner = (usize, Option<usize>, Option<usize>);
+
+struct OBJPart {
+   name: String,
+   material: Option<String>,
+   triangles: Vec<[Corner; 3]>,
+   smoothing: Vec<u32>,
+}
+
+pub(crate) struct OBJ {
+   pos_data: Vec<[f32; 3]>,
+   col_data: Vec<[f32; 3]>,
+   has_col: bool,
+   uvm_data: Vec<[f32; 2]>,
+   nrm_data: Vec<[f32; 3]>,
+   parts: Vec<OBJPart>,
+   pub(crate) mtllibs: Vec<String>,
+}
+
+impl OBJ {
+   //with `split` every object, group and material gets its own part, otherwise all faces
+   //end up in one
+   pub(crate) fn parse(src: &str, path: &str, split: bool) -> NEResult<OBJ> {
+      let mut obj = OBJ {
+         pos_data: Vec::new(),
+         col_data: Vec::new(),
+         has_col: false,
+         uvm_data: Vec::new(),
+         nrm_data: Vec::new(),
+         parts: Vec::new(),
+         mtllibs: Vec::new(),
+      };
+      let mut part_of: HashMap<(String, Option<String>), usize> = HashMap::new();
+      let mut name = String::new();
+      let mut material: Option<String> = None;
+      let mut group = 0;
+
+      for (n, line) in src.lines().enumerate() {
+         let words = line.split_whitespace().collect::<Vec<&str>>();
+         if words.is_empty() || words[0].starts_with('#') {
+            continue;
+         }
+         let bad = |msg: &str| {
+            NEResult::ER(NEError::mesh_invalid(
+               path,
+               &format!("line {}: {msg}", n + 1),
+            ))
+         };
+         match words[0] {
+            "v" => {
+               if words.len() < 4 {
+                  return bad("vertex needs 3 coordinates");
+               }
+               obj.pos_data.push(words.parse_3_to_f32());
+               //vertex colors are a common extension, "v x y z r g b"
+               match words.len() >= 7 {
+                  true => {
+                     obj.has_col = true;
+                     obj.col_data.push(words[3..].to_vec().parse_3_to_f32());
+                  }
+                  false => obj.col_data.push([1.0, 1.0, 1.0]),
+               }
+            }
+            "vt" => obj.uvm_data.push(words.parse_2_to_f32()),
+            "vn" => {
+               if words.len() < 4 {
+                  return bad("normal needs 3 coordinates");
+               }
+               obj.nrm_data.push(words.parse_3_to_f32())
+            }
+            //"s off" and "s 0" are flat, anything unparsable counts as one group
+            "s" => {
+               group = match words.get(1) {
+                  Some(&"off") | None => 0,
+                  Some(g) => g.parse::<u32>().unwrap_or(1),
+               }
+            }
+            "o" | "g" if split => name = words[1..].join(" "),
+            "usemtl" => material = words.get(1).map(|m| m.to_string()),
+            "mtllib" => obj.mtllibs.extend(words[1..].iter().map(|m| m.to_string())),
+            "f" => {
+               if words.len() < 4 {
+                  return bad("face needs at least 3 corners");
+               }
+               let mut corners: Vec<Corner> = Vec::new();
+               for word in words[1..].iter() {
+                  match obj.corner(word) {
+                     Err(msg) => return bad(&msg),
+                     Ok(c) => corners.push(c),
+                  }
+               }
+               let key = match split {
+                  true => (name.clone(), material.clone()),
+                  false => (String::new(), None),
+               };
+               let index = *part_of.entry(key.clone()).or_insert_with(|| {
+                  obj.parts.push(OBJPart {
+                     name: key.0,
+                     material: key.1,
+                     triangles: Vec::new(),
+                     smoothing: Vec::new(),
+                  });
+                  obj.parts.len() - 1
+               });
+               let points: Vec<Vector3<f32>> = corners
+                  .iter()
+                  .map(|c| Vector3::from(obj.pos_data[c.0]))
+                  .collect();
+               let part = &mut obj.parts[index];
+               for tri in triangulate(&points) {
+                  part.triangles.push(tri.map(|i| corners[i]));
+                  part.smoothing.push(group);
+               }
+            }
+            _ => {}
+         }
+      }
+      if obj.parts.is_empty() {
+         return NEResult::ER(NEError::mesh_invalid(path, "no faces"));
+      }
+      NEResult::OK(obj)
+   }
+
+   //"v", "v/vt", "v//vn" or "v/vt/vn", 1 based or negative from the end of each list
+   fn corner(&self, word: &str) -> Result<Corner, String> {
+      let tokens = word.split('/').collect::<Vec<&str>>();
+      if tokens.len() > 3 {
+         return Err(format!("corner '{word}' has too many indices"));
+      }
+      let index = |i: usize, len: usize| -> Result<Option<usize>, String> {
+         let token = match tokens.get(i) {
+            None | Some(&"") => return Ok(None),
+            Some(t) => t,
+         };
+         let value = match token.parse::<i64>() {
+            Err(_) => return Err(format!("corner '{word}' has an invalid index")),
+            Ok(v) => v,
+         };
+         let resolved = match value {
+            v if v > 0 => v - 1,
+            v if v < 0 => len as i64 + v,
+            _ => return Err(format!("corner '{word}' uses index 0")),
+         };
+         match resolved >= 0 && (resolved as usize) < len {
+            true => Ok(Some(resolved as usize)),
+            false => Err(format!("corner '{word}' is out of range")),
+         }
+      };
+      let pos = match index(0, self.pos_data.len())? {
+         None => return Err(format!("corner '{word}' has no vertex")),
+         Some(p) => p,
+      };
+      Ok((
+         pos,
+         index(1, self.uvm_data.len())?,
+         index(2, self.nrm_data.len())?,
+      ))
+   }
+
+   //parts in file order, materials are matched by name
+   pub(crate) fn build(&self, materials: &[MTLMaterial]) -> NEResult<Vec<NEMeshPart>> {
+      let mut parts = Vec::new();
+      for part in self.parts.iter() {
+         let material = part
+            .material
+            .as_ref()
+            .and_then(|m| materials.iter().find(|mtl| mtl.name == *m))
+            .cloned();
+         let asset = match self.build_asset(part, material.as_ref()) {
+            NEResult::ER(e) => return NEResult::ER(e),
+            NEResult::OK(a) => a,
+         };
+         parts.push(NEMeshPart {
+            name: part.name.clone(),
+            asset,
+            material,
+            textures: Vec::new(),
+         });
+      }
+      NEResult::OK(parts)
+   }
+
+   fn build_asset(&self, part: &OBJPart, material: Option<&MTLMaterial>) -> NEResult<NEMeshAsset> {
+      let corners = part.triangles.iter().flatten();
+      let uvm_exists = corners.clone().any(|c| c.1.is_some());
+      let nrm_exists = corners.clone().all(|c| c.2.is_some());
+      //without vertex colors the material's diffuse tints the mesh
+      let tint = match (self.has_col, material) {
+         (false, Some(m)) => Some(m.diffuse),
+         _ => None,
+      };
+
+      let mut pos_attr = PosATTR::empty();
+      let mut col_attr = ColATTR::empty();
+      let mut uvm_attr = UVMATTR::empty();
+      let mut nrm_attr = NrmATTR::empty();
+      let mut indices = Indices::empty();
+      let mut unique_verts = HashMap::new();
+
+      for corner in corners {
+         let key = match nrm_exists {
+            true => *corner,
+            false => (corner.0, corner.1, None),
+         };
+         if let Some(idx) = unique_verts.get(&key) {
+            indices.shove(*idx as u32);
+            continue;
+         }
+         let new = pos_attr.data.len();
+         unique_verts.insert(key, new);
+         pos_attr.shove(self.pos_data[corner.0]);
+         col_attr.shove(tint.unwrap_or(self.col_data[corner.0]));
+         //files without vt get no uvs rather than made up ones
+         if uvm_exists {
+            uvm_attr.shove(corner.1.map_or([0.0, 0.0], |id| self.uvm_data[id]));
+         }
+         if let (true, Some(id)) = (nrm_exists, corner.2) {
+            nrm_attr.shove(self.nrm_data[id]);
+         }
+         indices.shove(new as u32);
+      }
+
+      pos_attr.calc_info();
+      col_attr.calc_info();
+      uvm_attr.calc_info();
+      nrm_attr.calc_info();
+      indices.calc_info();
+
+      let mut nmesh = NEMeshAsset::from_attrs(pos_attr, col_attr, uvm_attr, nrm_attr, indices);
+      nmesh.smoothing = part.smoothing.clone();
+      if !nrm_exists {
+         if let NEResult::ER(e) = nmesh.generate_normals(true) {
+            return NEResult::ER(e);
+         }
+      }
+      NEResult::OK(nmesh)
+   }
+}
+
+impl OBJ {
+   //every object, group and material as its own part, materials come from the file's mtllibs
+   pub(crate) fn load_parts(raw_path: &str) -> NEResult<Vec<NEMeshPart>> {
+      let src = match file::read_as_string(raw_path) {
+         NEResult::ER(e) => return NEResult::ER(e),
+         NEResult::OK(s) => s,
+      };
+      let obj = match OBJ::parse(&src, raw_path, true) {
+         NEResult::ER(e) => return NEResult::ER(e),
+         NEResult::OK(o) => o,
+      };
+      let dir = Path::new(raw_path)
+         .parent()
+         .map(|d| d.to_string_lossy().to_string())
+         .unwrap_or_default();
+      let mut materials = Vec::new();
+      for lib in obj.mtllibs.iter() {
+         let lib_path = resolve(&dir, lib);
+         match file::read_as_string(&lib_path) {
+            NEResult::ER(_) => {
+               log_warn!("mtllib [{lib_path}] could not be read, its materials are skipped");
+            }
+            NEResult::OK(mtl_src) => materials.extend(MTLMaterial::parse(&mtl_src, &dir)),
+         }
+      }
+      obj.build(&materials)
+   }
+}
+
+//ear clipping on the polygon projected to its dominant plane, keeps the winding, falls back
+//to a fan when no ear is left (self intersecting or degenerate polygons)
+pub(crate) fn triangulate(points: &[Vector3<f32>]) -> Vec<[usize; 3]> {
+   let n = points.len();
+   if n == 3 {
+      return vec![[0, 1, 2]];
+   }
+   //newell normal
+   let mut normal = Vector3::new(0.0, 0.0, 0.0);
+   for i in 0..n {
+      let (a, b) = (points[i], points[(i + 1) % n]);
+      normal.x += (a.y - b.y) * (a.z + b.z);
+      normal.y += (a.z - b.z) * (a.x + b.x);
+      normal.z += (a.x - b.x) * (a.y + b.y);
+   }
+   let (ax, ay) = match (normal.x.abs(), normal.y.abs(), normal.z.abs()) {
+      (x, y, z) if x >= y && x >= z => (1, 2),
+      (_, y, z) if y >= z => (2, 0),
+      _ => (0, 1),
+   };
+   let flat: Vec<[f32; 2]> = points.iter().map(|p| [p[ax], p[ay]]).collect();
+   let cross = |o: usize, a: usize, b: usize| {
+      let (o, a, b) = (flat[o], flat[a], flat[b]);
+      (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+   };
+   let mut area = 0.0;
+   for i in 0..n {
+      area += cross(0, i, (i + 1) % n);
+   }
+   let sign = if area < 0.0 { -1.0 } else { 1.0 };
+
+   let mut triangles = Vec::new();
+   let mut remaining: Vec<usize> = (0..n).collect();
+   if normal.magnitude2() > 0.0 {
+      while remaining.len() > 3 {
+         let len = remaining.len();
+         let ear = (0..len).find(|i| {
+            let (p, c, x) = (
+               remaining[(i + len - 1) % len],
+               remaining[*i],
+               remaining[(i + 1) % len],
+            );
+            if cross(p, c, x) * sign <= 0.0 {
+               return false;
+            }
+            !remaining.iter().any(|o| {
+               ![p, c, x].contains(o)
+                  && cross(p, c, *o) * sign >= 0.0
+                  && cross(c, x, *o) * sign >= 0.0
+                  && cross(x, p, *o) * sign >= 0.0
+            })
+         });
+         match ear {
+            None => break,
+            Some(i) => {
+               triangles.push([
+                  remaining[(i + len - 1) % len],
+                  remaining[i],
+                  remaining[(i + 1) % len],
+               ]);
+               remaining.remove(i);
+            }
+         }
+      }
+   }
+   for i in 1..remaining.len() - 1 {
+      triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+   }
+   triangles
+}
+
+trait ParseWords {
+   fn parse_f32(&self, i: usize, def: f32) -> f32;
+   fn parse_2_to_f32(&self) -> [f32; 2];
+   fn parse_3_to_f32(&self) -> [f32; 3];
+}
+impl ParseWords for Vec<&str> {
+   fn parse_f32(&self, i: usize, def: f32) -> f32 {
+      self
+         .get(i)
+         .and_then(|w| w.parse::<f32>().ok())
+         .unwrap_or(def)
+   }
+
+   fn parse_2_to_f32(&self) -> [f32; 2] {
+      let mut elem = [self.parse_f32(1, 0.0), self.parse_f32(2, 0.0)];
+      elem[1] *= -1.0;
+      elem
+   }
+
+   fn parse_3_to_f32(&self) -> [f32; 3] {
+      [
+         self.parse_f32(1, 0.0),
+         self.parse_f32(2, 0.0),
+         self.parse_f32(3, 0.0),
+      ]
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::{triangulate, MTLMaterial, OBJ};
+   use cgmath::Vector3;
+
+   fn first_part(src: &str) -> crate::NEMeshAsset {
+      let obj = OBJ::parse(src, "test.obj", false).unpack();
+      obj.build(&[]).unpack().remove(0).asset
+   }
+
+   #[test]
+   fn no_vt_means_no_uvs() {
+      let asset = first_part("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n");
+      assert!(asset.uvm_attr.is_empty());
+      assert_eq!(asset.pos_attr.data().len(), 3);
+   }
+
+   #[test]
+   fn corners_without_vt_get_zero_uvs() {
+      let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0.5 0.5\nf 1/1 2 3\n";
+      let asset = first_part(src);
+      assert_eq!(asset.uvm_attr.data().len(), 3);
+      assert!(asset.uvm_attr.data().contains(&[0.0, 0.0]));
+   }
+
+   #[test]
+   fn concave_polygons_are_ear_clipped() {
+      //an arrow notched at (1, 1), a fan from the first corner would leave the polygon
+      let points: Vec<Vector3<f32>> = [[2.0, 2.0], [1.0, 1.0], [0.0, 2.0], [0.0, 0.0], [2.0, 0.0]]
+         .iter()
+         .map(|p| Vector3::new(p[0], p[1], 0.0))
+         .collect();
+      let triangles = triangulate(&points);
+      assert_eq!(triangles.len(), 3);
+      let mut total = 0.0;
+      for [a, b, c] in triangles {
+         let area = (points[b] - points[a]).cross(points[c] - points[a]).z / 2.0;
+         assert!(area > 0.0, "{a} {b} {c}");
+         total += area;
+      }
+      assert_eq!(total, 3.0);
+   }
+
+   #[test]
+   fn negative_indices_and_normal_only_corners() {
+      let src = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 1 0 0\nvn 0 1 0\nf -3//-1 -2//-1 -1//1\n";
+      let asset = first_part(src);
+      assert_eq!(asset.pos_attr.data().len(), 3);
+      assert!(asset.uvm_attr.is_empty());
+      assert_eq!(
+         asset.nrm_attr.data(),
+         &vec![[0.0, 1.0, 0.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]]
+      );
+      for face in ["f 1 2 -4", "f 0 1 2", "f 1/1/1/1 2 3", "f 1 2"] {
+         let src = format!("v 0 0 0\nv 1 0 0\nv 0 1 0\n{face}\n");
+         assert!(OBJ::parse(&src, "test.obj", false).is_err(), "{face}");
+      }
+   }
+
+   #[test]
+   fn objects_groups_and_materials_split_parts() {
+      let src = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+         o a\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 3 4\n\
+         g b\nf 1 2 4\no a\nusemtl red\nf 2 3 4\n";
+      let mtl = MTLMaterial::parse("newmtl red\nKd 1 0 0\nnewmtl blue\nKd 0 0 1\n", "");
+      let parts = OBJ::parse(src, "test.obj", true)
+         .unpack()
+         .build(&mtl)
+         .unpack();
+      let names: Vec<(&str, &str)> = parts
+         .iter()
+         .map(|p| (p.name.as_str(), p.material.as_ref().unwrap().name.as_str()))
+         .collect();
+      assert_eq!(names, [("a", "red"), ("a", "blue"), ("b", "blue")]);
+      assert_eq!(parts[0].asset.indices.data().len(), 6);
+      //without vertex colors the diffuse tints the part
+      assert_eq!(parts[1].asset.col_attr.data()[0], [0.0, 0.0, 1.0]);
+
+      let whole = OBJ::parse(src, "test.obj", false)
+         .unpack()
+         .build(&mtl)
+         .unpack();
+      assert_eq!(whole.len(), 1);
+      assert_eq!(whole[0].asset.indices.data().len(), 12);
+   }
+
+   #[test]
+   fn vertex_colors_follow_positions() {
+      let src = "v 0 0 0 1 0 0\nv 1 0 0 0 1 0\nv 0 1 0\nusemtl blue\nf 1 2 3\n";
+      let mtl = MTLMaterial::parse("newmtl blue\nKd 0 0 1\n", "");
+      let parts = OBJ::parse(src, "test.obj", true)
+         .unpack()
+         .build(&mtl)
+         .unpack();
+      assert_eq!(
+         parts[0].asset.col_attr.data(),
+         &vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 1.0]]
+      );
+   }
+
+   #[test]
+   fn mtl_materials() {
+      let src = "# exported\nKd 0 1 0\nnewmtl metal plate\nKa 0.1 0.1 0.1\nKd 0.5 0.5 0.5\n\
+         Ns 32\nTr 0.25\nmap_Kd -s 1 1 1 tex\\plate.png\nmap_Bump -bm 2 plate_n.png\n\
+         newmtl glass\nd 0.5\n";
+      let mtl = MTLMaterial::parse(src, "models");
+      assert_eq!(mtl.len(), 2);
+      assert_eq!(mtl[0].name, "metal plate");
+      assert_eq!(mtl[0].ambient, [0.1, 0.1, 0.1]);
+      assert_eq!(mtl[0].diffuse, [0.5, 0.5, 0.5]);
+      assert_eq!((mtl[0].shininess, mtl[0].opacity), (32.0, 0.75));
+      assert_eq!(mtl[0].diffuse_map.as_deref(), Some("models/tex/plate.png"));
+      assert_eq!(mtl[0].normal_map.as_deref(), Some("models/plate_n.png"));
+      assert_eq!((mtl[1].diffuse, mtl[1].opacity), ([1.0; 3], 0.5));
+      assert!(mtl[1].diffuse_map.is_none());
+   }
+
+   #[test]
+   fn files_without_faces_are_rejected() {
+      for src in ["", "# nothing\n", "v 0 0 0\nv 1 0 0\n"] {
+         match OBJ::parse(src, "test.obj", true) {
+            crate::NEResult::ER(e) => assert!(e.msg().1.contains("no faces")),
+            crate::NEResult::OK(_) => panic!("{src:?} parsed"),
+         }
+      }
+   }
+}
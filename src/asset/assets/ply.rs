@@ -0,0 +1,382 @@
+use super::obj::triangulate;
+use crate::*;
+use cgmath::Vector3;
+
+#[derive(Copy, Clone, PartialEq)]
+enum PLYFormat {
+   Ascii,
+   LittleEndian,
+   BigEndian,
+}
+
+#[derive(Copy, Clone)]
+enum PLYType {
+   I8,
+   U8,
+   I16,
+   U16,
+   I32,
+   U32,
+   F32,
+   F64,
+}
+
+impl PLYType {
+   fn from(name: &str) -> Option<PLYType> {
+      match name {
+         "char" | "int8" => Some(PLYType::I8),
+         "uchar" | "uint8" => Some(PLYType::U8),
+         "short" | "int16" => Some(PLYType::I16),
+         "ushort" | "uint16" => Some(PLYType::U16),
+         "int" | "int32" => Some(PLYType::I32),
+         "uint" | "uint32" => Some(PLYType::U32),
+         "float" | "float32" => Some(PLYType::F32),
+         "double" | "float64" => Some(PLYType::F64),
+         _ => None,
+      }
+   }
+   fn size(&self) -> usize {
+      match self {
+         PLYType::I8 | PLYType::U8 => 1,
+         PLYType::I16 | PLYType::U16 => 2,
+         PLYType::I32 | PLYType::U32 | PLYType::F32 => 4,
+         PLYType::F64 => 8,
+      }
+   }
+   //integer colors are scaled by this to 0..1
+   fn color_max(&self) -> f64 {
+      match self {
+         PLYType::I8 => i8::MAX as f64,
+         PLYType::U8 => u8::MAX as f64,
+         PLYType::I16 => i16::MAX as f64,
+         PLYType::U16 => u16::MAX as f64,
+         PLYType::I32 => i32::MAX as f64,
+         PLYType::U32 => u32::MAX as f64,
+         PLYType::F32 | PLYType::F64 => 1.0,
+      }
+   }
+}
+
+enum PLYProperty {
+   Scalar(String, PLYType),
+   //(name, count type, item type)
+   List(String, PLYType, PLYType),
+}
+
+struct PLYElement {
+   name: String,
+   count: usize,
+   props: Vec<PLYProperty>,
+}
+
+//reads values from the body, words for ascii and bytes otherwise
+struct PLYBody<'a> {
+   format: PLYFormat,
+   bytes: &'a [u8],
+   words: Vec<&'a str>,
+   at: usize,
+}
+
+impl PLYBody<'_> {
+   fn read(&mut self, typ: PLYType) -> Result<f64, String> {
+      if self.format == PLYFormat::Ascii {
+         let word = self.words.get(self.at).ok_or("body ends early")?;
+         self.at += 1;
+         return word
+            .parse::<f64>()
+            .map_err(|_| format!("'{word}' is not a number"));
+      }
+      let size = typ.size();
+      let raw = self
+         .bytes
+         .get(self.at..self.at + size)
+         .ok_or("body ends early")?;
+      self.at += size;
+      let mut b = [0u8; 8];
+      b[..size].copy_from_slice(raw);
+      if self.format == PLYFormat::BigEndian {
+         b[..size].reverse();
+      }
+      Ok(match typ {
+         PLYType::I8 => b[0] as i8 as f64,
+         PLYType::U8 => b[0] as f64,
+         PLYType::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+         PLYType::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+         PLYType::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+         PLYType::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+         PLYType::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+         PLYType::F64 => f64::from_le_bytes(b),
+      })
+   }
+}
+
+pub(crate) struct PLY;
+
+impl PLY {
+   //meshes without a face element are point clouds drawn with DrawMode::Points
+   pub(crate) fn load(raw_path: &str) -> NEResult<NEMeshAsset> {
+      let bytes = match file::read_as_bytes(raw_path) {
+         NEResult::ER(e) => return NEResult::ER(e),
+         NEResult::OK(b) => b,
+      };
+      match PLY::parse(&bytes) {
+         Err(msg) => NEResult::ER(NEError::mesh_invalid(raw_path, &msg)),
+         Ok(mut nmesh) => {
+            if nmesh.indices.has_data() && nmesh.nrm_attr.is_empty() {
+               if let NEResult::ER(e) = nmesh.generate_normals(true) {
+                  return NEResult::ER(e);
+               }
+            }
+            NEResult::OK(nmesh)
+         }
+      }
+   }
+
+   fn parse(bytes: &[u8]) -> Result<NEMeshAsset, String> {
+      let end = match bytes.windows(10).position(|w| w == b"end_header") {
+         None => return Err("no end_header".to_string()),
+         Some(e) => e,
+      };
+      let header = String::from_utf8_lossy(&bytes[..end]).to_string();
+      let mut body_at = end + 10;
+      while body_at < bytes.len() && bytes[body_at] != b'\n' {
+         body_at += 1;
+      }
+      body_at += 1;
+
+      let mut lines = header
+         .lines()
+         .map(|l| l.split_whitespace().collect::<Vec<&str>>());
+      if lines.next().map(|l| l.first() == Some(&"ply")) != Some(true) {
+         return Err("not a ply file".to_string());
+      }
+      let mut format = None;
+      let mut elements: Vec<PLYElement> = Vec::new();
+      for words in lines {
+         let typ = |name: &str| PLYType::from(name).ok_or(format!("unknown type {name}"));
+         match words.as_slice() {
+            ["format", f, ..] => {
+               format = Some(match *f {
+                  "ascii" => PLYFormat::Ascii,
+                  "binary_little_endian" => PLYFormat::LittleEndian,
+                  "binary_big_endian" => PLYFormat::BigEndian,
+                  f => return Err(format!("unknown format {f}")),
+               })
+            }
+            ["element", name, count] => elements.push(PLYElement {
+               name: name.to_string(),
+               count: count.parse().map_err(|_| format!("bad count for {name}"))?,
+               props: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+               let prop = PLYProperty::List(name.to_string(), typ(count)?, typ(item)?);
+               match elements.last_mut() {
+                  None => return Err("property before any element".to_string()),
+                  Some(e) => e.props.push(prop),
+               }
+            }
+            ["property", t, name] => {
+               let prop = PLYProperty::Scalar(name.to_string(), typ(t)?);
+               match elements.last_mut() {
+                  None => return Err("property before any element".to_string()),
+                  Some(e) => e.props.push(prop),
+               }
+            }
+            _ => {}
+         }
+      }
+      let format = format.ok_or("no format line")?;
+      let body_bytes = bytes.get(body_at..).unwrap_or(&[]);
+      let mut body = PLYBody {
+         format,
+         bytes: body_bytes,
+         words: match format {
+            PLYFormat::Ascii => std::str::from_utf8(body_bytes)
+               .map_err(|_| "ascii body is not text".to_string())?
+               .split_whitespace()
+               .collect(),
+            _ => Vec::new(),
+         },
+         at: 0,
+      };
+
+      let mut pos_data: Vec<[f32; 3]> = Vec::new();
+      let mut col_data: Vec<[f32; 3]> = Vec::new();
+      let mut uvm_data: Vec<[f32; 2]> = Vec::new();
+      let mut nrm_data: Vec<[f32; 3]> = Vec::new();
+      let mut faces: Vec<Vec<usize>> = Vec::new();
+      for element in elements.iter() {
+         //where each known vertex property sits among the element's properties
+         let slot = |names: &[&str]| {
+            element.props.iter().position(|p| match p {
+               PLYProperty::Scalar(n, _) => names.contains(&n.as_str()),
+               _ => false,
+            })
+         };
+         let pos = [slot(&["x"]), slot(&["y"]), slot(&["z"])];
+         let col = [
+            slot(&["red", "r", "diffuse_red"]),
+            slot(&["green", "g", "diffuse_green"]),
+            slot(&["blue", "b", "diffuse_blue"]),
+         ];
+         let uvm = [
+            slot(&["u", "s", "texture_u", "texture_s"]),
+            slot(&["v", "t", "texture_v", "texture_t"]),
+         ];
+         let nrm = [slot(&["nx"]), slot(&["ny"]), slot(&["nz"])];
+         let col_max = match col[0].map(|c| &element.props[c]) {
+            Some(PLYProperty::Scalar(_, t)) => t.color_max(),
+            _ => 1.0,
+         };
+         let is_vertex = element.name == "vertex";
+         let is_face = element.name == "face";
+
+         let mut row = vec![0.0; element.props.len()];
+         for _ in 0..element.count {
+            let mut polygon = Vec::new();
+            for (p, prop) in element.props.iter().enumerate() {
+               match prop {
+                  PLYProperty::Scalar(_, t) => row[p] = body.read(*t)?,
+                  PLYProperty::List(name, count_type, item_type) => {
+                     let count = body.read(*count_type)? as usize;
+                     let wanted = is_face && (name == "vertex_indices" || name == "vertex_index");
+                     for _ in 0..count {
+                        let item = body.read(*item_type)?;
+                        if wanted {
+                           polygon.push(item as usize);
+                        }
+                     }
+                  }
+               }
+            }
+            if is_vertex {
+               let get = |s: Option<usize>| s.map(|s| row[s] as f32).unwrap_or(0.0);
+               pos_data.push(pos.map(get));
+               if col[0].is_some() {
+                  col_data.push(col.map(|c| (c.map(|c| row[c]).unwrap_or(0.0) / col_max) as f32));
+               }
+               if uvm[0].is_some() {
+                  uvm_data.push([get(uvm[0]), -get(uvm[1])]);
+               }
+               if nrm[0].is_some() {
+                  nrm_data.push(nrm.map(get));
+               }
+            }
+            if is_face {
+               faces.push(polygon);
+            }
+         }
+      }
+      if pos_data.is_empty() {
+         return Err("no vertices".to_string());
+      }
+
+      let mut indices = Vec::new();
+      for face in faces.iter() {
+         if face.iter().any(|i| *i >= pos_data.len()) {
+            return Err("face index out of range".to_string());
+         }
+         if face.len() < 3 {
+            continue;
+         }
+         let points: Vec<Vector3<f32>> = face.iter().map(|i| Vector3::from(pos_data[*i])).collect();
+         for tri in triangulate(&points) {
+            indices.extend(tri.map(|i| face[i] as u32));
+         }
+      }
+
+      let vert_count = pos_data.len();
+      if col_data.is_empty() {
+         col_data = vec![[1.0, 1.0, 1.0]; vert_count];
+      }
+      let mut nmesh = NEMeshAsset::from_attrs(
+         PosATTR::from(pos_data),
+         ColATTR::from(col_data),
+         UVMATTR::from(uvm_data),
+         NrmATTR::from(nrm_data),
+         Indices::from(indices),
+      );
+      if faces.is_empty() {
+         nmesh.set_draw_mode(DrawMode::Points);
+      }
+      Ok(nmesh)
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::PLY;
+   use crate::*;
+
+   fn header(format: &str, vertex_props: &str, faces: usize) -> String {
+      let mut header = format!("ply\nformat {format} 1.0\nelement vertex 4\n{vertex_props}");
+      if faces > 0 {
+         header += &format!("element face {faces}\nproperty list uchar int vertex_indices\n");
+      }
+      header + "end_header\n"
+   }
+   const SQUARE: [[f32; 3]; 4] = [
+      [0.0, 0.0, 0.0],
+      [1.0, 0.0, 0.0],
+      [1.0, 1.0, 0.0],
+      [0.0, 1.0, 0.0],
+   ];
+
+   #[test]
+   fn ascii_quad_is_triangulated() {
+      let props = "property float x\nproperty float y\nproperty float z\n\
+         property uchar red\nproperty uchar green\nproperty uchar blue\n";
+      let src = header("ascii", props, 1)
+         + "0 0 0 255 0 51\n1 0 0 0 0 0\n1 1 0 0 0 0\n0 1 0 0 0 0\n4 0 1 2 3\n";
+      let asset = PLY::parse(src.as_bytes()).unwrap();
+      assert_eq!(asset.pos_attr.data(), &SQUARE.to_vec());
+      assert_eq!(asset.col_attr.data()[0], [1.0, 0.0, 0.2]);
+      let mut indices = asset.indices.data().clone();
+      assert_eq!(indices.len(), 6);
+      indices.sort();
+      indices.dedup();
+      assert_eq!(indices, [0, 1, 2, 3]);
+      assert!(matches!(asset.draw_mode(), DrawMode::Triangles));
+   }
+
+   #[test]
+   fn little_endian_point_cloud() {
+      let props = "property float x\nproperty float y\nproperty float z\n";
+      let mut bytes = header("binary_little_endian", props, 0).into_bytes();
+      for p in SQUARE.iter() {
+         bytes.extend(p.iter().flat_map(|c| c.to_le_bytes()));
+      }
+      let asset = PLY::parse(&bytes).unwrap();
+      assert_eq!(asset.pos_attr.data(), &SQUARE.to_vec());
+      assert!(asset.indices.is_empty());
+      assert!(matches!(asset.draw_mode(), DrawMode::Points));
+      assert!(PLY::parse(&bytes[..bytes.len() - 2]).is_err());
+   }
+
+   #[test]
+   fn big_endian_floats_and_colors() {
+      let props = "property float x\nproperty float y\nproperty float z\nproperty uchar red\n";
+      let mut bytes = header("binary_big_endian", props, 1).into_bytes();
+      for (i, p) in SQUARE.iter().enumerate() {
+         bytes.extend(p.map(|c| c * 1.5).iter().flat_map(|c| c.to_be_bytes()));
+         bytes.push(i as u8 * 85);
+      }
+      bytes.push(3);
+      for i in [0i32, 1, 2] {
+         bytes.extend(i.to_be_bytes());
+      }
+      let asset = PLY::parse(&bytes).unwrap();
+      assert_eq!(asset.pos_attr.data()[2], [1.5, 1.5, 0.0]);
+      assert_eq!(asset.col_attr.data()[3], [1.0, 0.0, 0.0]);
+      assert_eq!(asset.col_attr.data()[1][0], 85.0 / 255.0);
+      assert_eq!(asset.indices.data(), &vec![0, 1, 2]);
+   }
+
+   #[test]
+   fn broken_files_are_rejected() {
+      assert!(PLY::parse(b"ply\nformat ascii 1.0\nelement vertex 1\n").is_err());
+      let props = "property float x\nproperty float y\nproperty float z\n";
+      let src = header("ascii", props, 1) + "0 0 0\n1 0 0\n1 1 0\n0 1 0\n3 0 1 9\n";
+      assert!(PLY::parse(src.as_bytes()).is_err());
+   }
+}
@@ -4,8 +4,11 @@ pub(crate) enum NEAssetErrKind {
    //SHADER
    VertEmpty,
    FragEmpty,
-   //OBJ
-   NonTriangle(String),
+   //MESH
+   MeshInvalid(String),
+   //ATTR
+   AttrKind(String),
+   AttrMissing(String),
 }
 
 enum GLSL {
@@ -63,9 +66,21 @@ pub struct NEShaderAsset {
    pub(crate) path: String,
    pub(crate) v_spv: Vec<u8>,
    pub(crate) f_spv: Vec<u8>,
+   pub(crate) info: NEShaderInfo,
 }
 
 impl NEShaderAsset {
+   pub(crate) fn empty(path: &str) -> NEShaderAsset {
+      NEShaderAsset {
+         path: path.to_string(),
+         v_spv: Vec::new(),
+         f_spv: Vec::new(),
+         info: NEShaderInfo::empty(),
+      }
+   }
+   pub fn info(&self) -> &NEShaderInfo {
+      &self.info
+   }
    pub(crate) fn fallback() -> NEResult<NEShaderAsset> {
       NEShaderAsset::from_path_raw("nerve/assets/shaders/fallback.glsl")
    }
@@ -132,6 +147,10 @@ impl NEShaderAsset {
                nshdr.extend_from_slice(&v_spv);
                nshdr.extend_from_slice(&f_spv);
 
+               let info = match NEShaderInfo::from_spv(raw_path, &v_spv, &f_spv) {
+                  NEResult::ER(e) => return NEResult::ER(e),
+                  NEResult::OK(i) => i,
+               };
                let nshdr_name = format!("{file_name}.{}", ex::NSHDR);
                match file::write_bytes_to_disk(path::SHDR_ASSET, &nshdr_name, &nshdr) {
                   NEResult::ER(e) => NEResult::ER(e),
@@ -139,6 +158,7 @@ impl NEShaderAsset {
                      path: nshdr_path.clone(),
                      v_spv,
                      f_spv,
+                     info,
                   }),
                }
             }
@@ -160,10 +180,15 @@ impl NEShaderAsset {
          let v_spv = clone_slice(&nshdr[8..v_offset]);
          let f_spv = clone_slice(&nshdr[v_offset..f_offset]);
 
+         let info = match NEShaderInfo::from_spv(&nshdr_path, &v_spv, &f_spv) {
+            NEResult::ER(e) => return NEResult::ER(e),
+            NEResult::OK(i) => i,
+         };
          NEResult::OK(NEShaderAsset {
             path: nshdr_path,
             v_spv,
             f_spv,
+            info,
          })
       }
    }
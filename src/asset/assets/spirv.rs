@@ -0,0 +1,567 @@
+use crate::{NEError, NEResult};
+use std::collections::HashMap;
+
+const MAGIC: u32 = 0x07230203;
+
+//OPCODES
+const OP_NAME: u32 = 5;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+//DECORATIONS
+const DEC_BUILTIN: u32 = 11;
+const DEC_LOCATION: u32 = 30;
+const DEC_BINDING: u32 = 33;
+
+//STORAGE CLASSES
+const SC_UNIFORM_CONSTANT: u32 = 0;
+const SC_INPUT: u32 = 1;
+const SC_UNIFORM: u32 = 2;
+const SC_OUTPUT: u32 = 3;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SpvScalar {
+   Bool,
+   Int,
+   UInt,
+   Float,
+   Double,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpvType {
+   Scalar(SpvScalar),
+   Vector(SpvScalar, u32),
+   Matrix(SpvScalar, u32, u32), //(scalar, cols, rows)
+   Sampler,
+   Struct(String),
+   Unknown,
+}
+
+impl SpvType {
+   pub fn scalar(&self) -> Option<SpvScalar> {
+      match self {
+         SpvType::Scalar(s) | SpvType::Vector(s, _) | SpvType::Matrix(s, _, _) => Some(*s),
+         _ => None,
+      }
+   }
+   pub fn components(&self) -> u32 {
+      match self {
+         SpvType::Scalar(_) => 1,
+         SpvType::Vector(_, n) => *n,
+         SpvType::Matrix(_, c, r) => c * r,
+         _ => 0,
+      }
+   }
+   pub fn glsl_name(&self) -> String {
+      let prefix = |s: &SpvScalar| match s {
+         SpvScalar::Bool => "b",
+         SpvScalar::Int => "i",
+         SpvScalar::UInt => "u",
+         SpvScalar::Float => "",
+         SpvScalar::Double => "d",
+      };
+      match self {
+         SpvType::Scalar(s) => match s {
+            SpvScalar::Bool => "bool",
+            SpvScalar::Int => "int",
+            SpvScalar::UInt => "uint",
+            SpvScalar::Float => "float",
+            SpvScalar::Double => "double",
+         }
+         .to_string(),
+         SpvType::Vector(s, n) => format!("{}vec{n}", prefix(s)),
+         SpvType::Matrix(s, c, r) if c == r => format!("{}mat{c}", prefix(s)),
+         SpvType::Matrix(s, c, r) => format!("{}mat{c}x{r}", prefix(s)),
+         SpvType::Sampler => "sampler2D".to_string(),
+         SpvType::Struct(name) => name.clone(),
+         SpvType::Unknown => "unknown".to_string(),
+      }
+   }
+}
+
+#[derive(Clone, Debug)]
+pub struct NEShaderVar {
+   pub name: String,
+   pub location: Option<u32>,
+   pub binding: Option<u32>,
+   pub typ: SpvType,
+   pub array_len: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct NEShaderInfo {
+   pub attributes: Vec<NEShaderVar>,
+   pub uniforms: Vec<NEShaderVar>,
+   pub samplers: Vec<NEShaderVar>,
+   pub frag_outputs: Vec<NEShaderVar>,
+
+   //vertex to fragment interface, checked when compiling
+   pub(crate) varyings_out: Vec<NEShaderVar>,
+   pub(crate) varyings_in: Vec<NEShaderVar>,
+}
+
+impl NEShaderInfo {
+   pub(crate) fn empty() -> NEShaderInfo {
+      NEShaderInfo {
+         attributes: Vec::new(),
+         uniforms: Vec::new(),
+         samplers: Vec::new(),
+         frag_outputs: Vec::new(),
+         varyings_out: Vec::new(),
+         varyings_in: Vec::new(),
+      }
+   }
+
+   pub(crate) fn from_spv(path: &str, v_spv: &[u8], f_spv: &[u8]) -> NEResult<NEShaderInfo> {
+      let mut info = NEShaderInfo::empty();
+      if v_spv.is_empty() && f_spv.is_empty() {
+         return NEResult::OK(info);
+      }
+      let vert = match SpvModule::parse(path, v_spv) {
+         NEResult::ER(e) => return NEResult::ER(e),
+         NEResult::OK(m) => m,
+      };
+      let frag = match SpvModule::parse(path, f_spv) {
+         NEResult::ER(e) => return NEResult::ER(e),
+         NEResult::OK(m) => m,
+      };
+      info.attributes = vert.inputs;
+      info.varyings_out = vert.outputs;
+      info.varyings_in = frag.inputs;
+      info.frag_outputs = frag.outputs;
+
+      //uniforms shared by both stages are listed once
+      for var in vert.uniforms.into_iter().chain(frag.uniforms) {
+         let list = match var.typ {
+            SpvType::Sampler => &mut info.samplers,
+            _ => &mut info.uniforms,
+         };
+         if !list.iter().any(|u| u.name == var.name) {
+            list.push(var);
+         }
+      }
+      NEResult::OK(info)
+   }
+
+   pub fn attribute(&self, location: u32) -> Option<&NEShaderVar> {
+      self
+         .attributes
+         .iter()
+         .find(|a| a.location == Some(location))
+   }
+   pub fn attribute_named(&self, name: &str) -> Option<&NEShaderVar> {
+      self.attributes.iter().find(|a| a.name == name)
+   }
+   pub fn uniform(&self, name: &str) -> Option<&NEShaderVar> {
+      self.uniforms.iter().find(|u| u.name == name)
+   }
+   pub fn sampler(&self, name: &str) -> Option<&NEShaderVar> {
+      self.samplers.iter().find(|s| s.name == name)
+   }
+
+   //vertex inputs a mesh can feed, numeric scalars and vectors at explicit locations,
+   //`instanced_from` and up belong to NEInstances which also fills matrices
+   pub(crate) fn attribute_mismatches(&self, instanced_from: u32) -> Vec<String> {
+      let mut problems = Vec::new();
+      for input in self.attributes.iter() {
+         let location = match input.location {
+            None => {
+               problems.push(format!("vertex input '{}' has no location", input.name));
+               continue;
+            }
+            Some(l) => l,
+         };
+         let fits = match &input.typ {
+            SpvType::Scalar(s) | SpvType::Vector(s, _) => *s != SpvScalar::Bool,
+            SpvType::Matrix(s, _, _) => *s != SpvScalar::Bool && location >= instanced_from,
+            _ => false,
+         };
+         if !fits || input.array_len > 1 {
+            problems.push(format!(
+               "vertex input '{}' is {}, no vertex attribute can hold it",
+               input.name,
+               input.typ.glsl_name()
+            ));
+         }
+      }
+      problems
+   }
+
+   //every fragment input needs a vertex output of the same type at its location
+   pub(crate) fn interface_mismatches(&self) -> Vec<String> {
+      let mut problems = Vec::new();
+      for input in self.varyings_in.iter() {
+         let output = self
+            .varyings_out
+            .iter()
+            .find(|o| o.location.is_some() && o.location == input.location);
+         match output {
+            None => problems.push(format!(
+               "fragment input '{}' has no vertex output at location {:?}",
+               input.name, input.location
+            )),
+            Some(o) if o.typ != input.typ => problems.push(format!(
+               "'{}' is {} in the vertex stage but {} in the fragment stage",
+               input.name,
+               o.typ.glsl_name(),
+               input.typ.glsl_name()
+            )),
+            _ => {}
+         }
+      }
+      problems
+   }
+}
+
+struct SpvModule {
+   inputs: Vec<NEShaderVar>,
+   outputs: Vec<NEShaderVar>,
+   uniforms: Vec<NEShaderVar>,
+}
+
+impl SpvModule {
+   fn parse(path: &str, bytes: &[u8]) -> NEResult<SpvModule> {
+      let invalid = |msg: &str| NEResult::ER(NEError::spirv_invalid(path, msg));
+      if bytes.len() < 20 || bytes.len() % 4 != 0 {
+         return invalid("truncated module");
+      }
+      let le = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) == MAGIC;
+      let be = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) == MAGIC;
+      if !le && !be {
+         return invalid("bad magic number");
+      }
+      let words: Vec<u32> = bytes
+         .chunks(4)
+         .map(|w| match le {
+            true => u32::from_le_bytes([w[0], w[1], w[2], w[3]]),
+            false => u32::from_be_bytes([w[0], w[1], w[2], w[3]]),
+         })
+         .collect();
+
+      let mut names: HashMap<u32, String> = HashMap::new();
+      let mut locations: HashMap<u32, u32> = HashMap::new();
+      let mut bindings: HashMap<u32, u32> = HashMap::new();
+      let mut builtins: Vec<u32> = Vec::new();
+      let mut types: HashMap<u32, SpvType> = HashMap::new();
+      let mut arrays: HashMap<u32, (u32, u32)> = HashMap::new(); //(elem type, length id)
+      let mut pointers: HashMap<u32, u32> = HashMap::new();
+      let mut constants: HashMap<u32, u32> = HashMap::new();
+      let mut variables: Vec<(u32, u32, u32)> = Vec::new(); //(id, pointer type, storage)
+
+      let mut at = 5;
+      while at < words.len() {
+         let count = (words[at] >> 16) as usize;
+         let op = words[at] & 0xffff;
+         if count == 0 || at + count > words.len() {
+            return invalid("instruction overruns module");
+         }
+         let args = &words[at + 1..at + count];
+         match op {
+            OP_NAME if !args.is_empty() => {
+               names.insert(args[0], spv_string(&args[1..]));
+            }
+            OP_DECORATE if args.len() >= 2 => match args[1] {
+               DEC_LOCATION if args.len() >= 3 => {
+                  locations.insert(args[0], args[2]);
+               }
+               DEC_BINDING if args.len() >= 3 => {
+                  bindings.insert(args[0], args[2]);
+               }
+               DEC_BUILTIN => builtins.push(args[0]),
+               _ => {}
+            },
+            //blocks holding gl_Position and friends
+            OP_MEMBER_DECORATE if args.len() >= 3 && args[2] == DEC_BUILTIN => {
+               builtins.push(args[0])
+            }
+            OP_TYPE_BOOL if !args.is_empty() => {
+               types.insert(args[0], SpvType::Scalar(SpvScalar::Bool));
+            }
+            OP_TYPE_INT if args.len() >= 3 => {
+               let scalar = match args[2] {
+                  0 => SpvScalar::UInt,
+                  _ => SpvScalar::Int,
+               };
+               types.insert(args[0], SpvType::Scalar(scalar));
+            }
+            OP_TYPE_FLOAT if args.len() >= 2 => {
+               let scalar = match args[1] {
+                  64 => SpvScalar::Double,
+                  _ => SpvScalar::Float,
+               };
+               types.insert(args[0], SpvType::Scalar(scalar));
+            }
+            OP_TYPE_VECTOR if args.len() >= 3 => {
+               let typ = match types.get(&args[1]) {
+                  Some(SpvType::Scalar(s)) => SpvType::Vector(*s, args[2]),
+                  _ => SpvType::Unknown,
+               };
+               types.insert(args[0], typ);
+            }
+            OP_TYPE_MATRIX if args.len() >= 3 => {
+               let typ = match types.get(&args[1]) {
+                  Some(SpvType::Vector(s, rows)) => SpvType::Matrix(*s, args[2], *rows),
+                  _ => SpvType::Unknown,
+               };
+               types.insert(args[0], typ);
+            }
+            OP_TYPE_IMAGE | OP_TYPE_SAMPLER | OP_TYPE_SAMPLED_IMAGE if !args.is_empty() => {
+               types.insert(args[0], SpvType::Sampler);
+            }
+            OP_TYPE_ARRAY if args.len() >= 3 => {
+               arrays.insert(args[0], (args[1], args[2]));
+            }
+            OP_TYPE_STRUCT if !args.is_empty() => {
+               let name = names.get(&args[0]).cloned().unwrap_or_default();
+               types.insert(args[0], SpvType::Struct(name));
+            }
+            OP_TYPE_POINTER if args.len() >= 3 => {
+               pointers.insert(args[0], args[2]);
+            }
+            OP_CONSTANT if args.len() >= 3 => {
+               constants.insert(args[1], args[2]);
+            }
+            OP_VARIABLE if args.len() >= 3 => variables.push((args[1], args[0], args[2])),
+            _ => {}
+         }
+         at += count;
+      }
+
+      let mut module = SpvModule {
+         inputs: Vec::new(),
+         outputs: Vec::new(),
+         uniforms: Vec::new(),
+      };
+      for (id, ptr, storage) in variables {
+         let mut typ_id = match pointers.get(&ptr) {
+            None => continue,
+            Some(t) => *t,
+         };
+         let mut array_len = 1;
+         if let Some((elem, len_id)) = arrays.get(&typ_id) {
+            array_len = constants.get(len_id).copied().unwrap_or(1);
+            typ_id = *elem;
+         }
+         if builtins.contains(&id) || builtins.contains(&typ_id) {
+            continue;
+         }
+         let var = NEShaderVar {
+            name: names.get(&id).cloned().unwrap_or_default(),
+            location: locations.get(&id).copied(),
+            binding: bindings.get(&id).copied(),
+            typ: types.get(&typ_id).cloned().unwrap_or(SpvType::Unknown),
+            array_len,
+         };
+         match storage {
+            SC_INPUT => module.inputs.push(var),
+            SC_OUTPUT => module.outputs.push(var),
+            SC_UNIFORM_CONSTANT | SC_UNIFORM => module.uniforms.push(var),
+            _ => {}
+         }
+      }
+      for list in [&mut module.inputs, &mut module.outputs] {
+         list.sort_by_key(|v| v.location.unwrap_or(u32::MAX));
+      }
+      NEResult::OK(module)
+   }
+}
+
+//literal strings are nul terminated utf8 packed little end first into words
+fn spv_string(words: &[u32]) -> String {
+   let mut bytes = Vec::new();
+   'words: for word in words {
+      for byte in word.to_le_bytes() {
+         if byte == 0 {
+            break 'words;
+         }
+         bytes.push(byte);
+      }
+   }
+   String::from_utf8_lossy(&bytes).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   fn op(code: u32, args: &[u32]) -> Vec<u32> {
+      let mut words = vec![((args.len() as u32 + 1) << 16) | code];
+      words.extend(args);
+      words
+   }
+   fn name(id: u32, name: &str) -> Vec<u32> {
+      let mut bytes = name.as_bytes().to_vec();
+      bytes.resize((bytes.len() / 4 + 1) * 4, 0);
+      let mut args = vec![id];
+      args.extend(
+         bytes
+            .chunks(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]])),
+      );
+      op(OP_NAME, &args)
+   }
+   fn module(body: &[Vec<u32>]) -> Vec<u8> {
+      let mut words = vec![MAGIC, 0x10000, 0, 100, 0];
+      words.extend(body.iter().flatten());
+      words.iter().flat_map(|w| w.to_le_bytes()).collect()
+   }
+   fn parse(body: &[Vec<u32>]) -> NEResult<SpvModule> {
+      SpvModule::parse("test", &module(body))
+   }
+
+   //float 1, vec3 2, int 3, uint 4, bool 5, mat4 7
+   fn types() -> Vec<Vec<u32>> {
+      vec![
+         op(OP_TYPE_FLOAT, &[1, 32]),
+         op(OP_TYPE_VECTOR, &[2, 1, 3]),
+         op(OP_TYPE_INT, &[3, 32, 1]),
+         op(OP_TYPE_INT, &[4, 32, 0]),
+         op(OP_TYPE_BOOL, &[5]),
+         op(OP_TYPE_VECTOR, &[6, 1, 4]),
+         op(OP_TYPE_MATRIX, &[7, 6, 4]),
+      ]
+   }
+   //pointer `ptr` to `typ` in `storage` and a variable `id` of it
+   fn var(id: u32, ptr: u32, typ: u32, storage: u32) -> Vec<Vec<u32>> {
+      vec![
+         op(OP_TYPE_POINTER, &[ptr, storage, typ]),
+         op(OP_VARIABLE, &[ptr, id, storage]),
+      ]
+   }
+
+   #[test]
+   fn inputs_keep_locations_and_types() {
+      let mut body = vec![
+         name(20, "vNrm"),
+         name(21, "vPos"),
+         name(22, "vId"),
+         op(OP_DECORATE, &[20, DEC_LOCATION, 3]),
+         op(OP_DECORATE, &[21, DEC_LOCATION, 0]),
+         op(OP_DECORATE, &[22, DEC_LOCATION, 5]),
+      ];
+      body.extend(types());
+      body.extend(var(20, 10, 2, SC_INPUT));
+      body.extend(var(21, 11, 2, SC_INPUT));
+      body.extend(var(22, 12, 4, SC_INPUT));
+      let module = parse(&body).unpack();
+      let inputs: Vec<(&str, Option<u32>, SpvType)> = module
+         .inputs
+         .iter()
+         .map(|v| (v.name.as_str(), v.location, v.typ.clone()))
+         .collect();
+      assert_eq!(
+         inputs,
+         [
+            ("vPos", Some(0), SpvType::Vector(SpvScalar::Float, 3)),
+            ("vNrm", Some(3), SpvType::Vector(SpvScalar::Float, 3)),
+            ("vId", Some(5), SpvType::Scalar(SpvScalar::UInt)),
+         ]
+      );
+   }
+
+   #[test]
+   fn builtins_are_skipped() {
+      let mut body = vec![
+         name(20, "gl_VertexIndex"),
+         name(21, "vPos"),
+         op(OP_DECORATE, &[20, DEC_BUILTIN, 42]),
+         op(OP_DECORATE, &[21, DEC_LOCATION, 0]),
+         op(OP_MEMBER_DECORATE, &[8, 0, DEC_BUILTIN, 0]),
+      ];
+      body.extend(types());
+      body.push(op(OP_TYPE_STRUCT, &[8, 6]));
+      body.extend(var(20, 10, 3, SC_INPUT));
+      body.extend(var(21, 11, 2, SC_INPUT));
+      body.extend(var(22, 12, 8, SC_OUTPUT));
+      let module = parse(&body).unpack();
+      assert_eq!(module.inputs.len(), 1);
+      assert_eq!(module.inputs[0].name, "vPos");
+      assert!(module.outputs.is_empty());
+   }
+
+   #[test]
+   fn samplers_and_arrays() {
+      let mut body = vec![
+         name(20, "tDif"),
+         name(21, "uBones"),
+         op(OP_DECORATE, &[20, DEC_BINDING, 2]),
+      ];
+      body.extend(types());
+      body.extend([
+         op(OP_TYPE_IMAGE, &[30, 1, 1, 0, 0, 0, 1, 0]),
+         op(OP_TYPE_SAMPLED_IMAGE, &[31, 30]),
+         op(OP_CONSTANT, &[4, 32, 4]),
+         op(OP_CONSTANT, &[4, 33, 16]),
+         op(OP_TYPE_ARRAY, &[34, 31, 32]),
+         op(OP_TYPE_ARRAY, &[35, 7, 33]),
+      ]);
+      body.extend(var(20, 10, 34, SC_UNIFORM_CONSTANT));
+      body.extend(var(21, 11, 35, SC_UNIFORM_CONSTANT));
+      let module = parse(&body).unpack();
+      let sampler = &module.uniforms[0];
+      assert_eq!(
+         (sampler.name.as_str(), &sampler.typ),
+         ("tDif", &SpvType::Sampler)
+      );
+      assert_eq!((sampler.binding, sampler.array_len), (Some(2), 4));
+      let bones = &module.uniforms[1];
+      assert_eq!(bones.typ, SpvType::Matrix(SpvScalar::Float, 4, 4));
+      assert_eq!(
+         (bones.array_len, bones.typ.glsl_name().as_str()),
+         (16, "mat4")
+      );
+   }
+
+   #[test]
+   fn broken_modules_are_rejected() {
+      let mut bad_magic = module(&types());
+      bad_magic[0] ^= 0xff;
+      assert!(SpvModule::parse("test", &bad_magic).is_err());
+      assert!(SpvModule::parse("test", &module(&[])[..16]).is_err());
+      assert!(SpvModule::parse("test", &module(&types())[..21]).is_err());
+
+      let mut overrun = types();
+      overrun.push(vec![(9 << 16) | OP_NAME, 20]);
+      assert!(parse(&overrun).is_err());
+      assert!(parse(&[vec![OP_NAME]]).is_err());
+   }
+
+   #[test]
+   fn attribute_mismatches_name_the_input() {
+      let input = |name: &str, location: Option<u32>, typ: SpvType, array_len: u32| NEShaderVar {
+         name: name.to_string(),
+         location,
+         binding: None,
+         typ,
+         array_len,
+      };
+      let mut info = NEShaderInfo::empty();
+      info.attributes = vec![
+         input("vPos", Some(0), SpvType::Vector(SpvScalar::Float, 3), 1),
+         input("vFlag", Some(1), SpvType::Scalar(SpvScalar::Bool), 1),
+         input("vTfm", Some(2), SpvType::Matrix(SpvScalar::Float, 4, 4), 1),
+         input("iTfm", Some(8), SpvType::Matrix(SpvScalar::Float, 4, 4), 1),
+         input("vArr", Some(3), SpvType::Scalar(SpvScalar::Float), 2),
+         input("vLost", None, SpvType::Scalar(SpvScalar::Float), 1),
+      ];
+      let problems = info.attribute_mismatches(8);
+      assert_eq!(problems.len(), 4, "{problems:?}");
+      for name in ["vFlag", "vTfm", "vArr", "vLost"] {
+         assert!(problems.iter().any(|p| p.contains(name)), "{name}");
+      }
+   }
+}
@@ -0,0 +1,201 @@
+use crate::*;
+use cgmath::{InnerSpace, Vector3};
+
+pub(crate) struct STL;
+
+impl STL {
+   //facets keep their own vertices and face normal, weld_vertices shares them when wanted
+   pub(crate) fn load(raw_path: &str) -> NEResult<NEMeshAsset> {
+      let bytes = match file::read_as_bytes(raw_path) {
+         NEResult::ER(e) => return NEResult::ER(e),
+         NEResult::OK(b) => b,
+      };
+      match STL::parse(&bytes) {
+         Err(msg) => NEResult::ER(NEError::mesh_invalid(raw_path, &msg)),
+         Ok(nmesh) => NEResult::OK(nmesh),
+      }
+   }
+
+   fn parse(bytes: &[u8]) -> Result<NEMeshAsset, String> {
+      //binary files may start with "solid" too, their size gives them away
+      let facets = bytes
+         .get(80..84)
+         .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]) as usize);
+      let binary = match facets {
+         Some(n) if 84 + n * 50 == bytes.len() => true,
+         _ => !bytes.starts_with(b"solid"),
+      };
+      match binary {
+         true => STL::parse_binary(bytes),
+         false => STL::parse_ascii(&String::from_utf8_lossy(bytes)),
+      }
+   }
+
+   //(normal, corners, color) per facet
+   fn build(facets: Vec<([f32; 3], [[f32; 3]; 3], [f32; 3])>) -> Result<NEMeshAsset, String> {
+      if facets.is_empty() {
+         return Err("no facets".to_string());
+      }
+      let mut pos_attr = PosATTR::empty();
+      let mut col_attr = ColATTR::empty();
+      let mut nrm_attr = NrmATTR::empty();
+      for (normal, corners, color) in facets.iter() {
+         let mut n = Vector3::from(*normal);
+         //exporters often write zero normals, the winding is the reliable part
+         if n.magnitude2() < 1e-12 {
+            let [a, b, c] = corners.map(Vector3::from);
+            n = (b - a).cross(c - a);
+         }
+         let n = match n.magnitude2() > 0.0 {
+            true => n.normalize(),
+            false => n,
+         };
+         for corner in corners.iter() {
+            pos_attr.shove(*corner);
+            col_attr.shove(*color);
+            nrm_attr.shove([n.x, n.y, n.z]);
+         }
+      }
+      pos_attr.calc_info();
+      col_attr.calc_info();
+      nrm_attr.calc_info();
+      Ok(NEMeshAsset::from_attrs(
+         pos_attr,
+         col_attr,
+         UVMATTR::empty(),
+         nrm_attr,
+         Indices::empty(),
+      ))
+   }
+
+   //80 byte header, facet count, then normal, 3 corners and a u16 attribute per facet, the
+   //attribute holds a 5 bit rgb color when its top bit is set (VisCAM/SolidView)
+   fn parse_binary(bytes: &[u8]) -> Result<NEMeshAsset, String> {
+      let count = match bytes.get(80..84) {
+         None => return Err("file is shorter than the header".to_string()),
+         Some(c) => u32::from_le_bytes([c[0], c[1], c[2], c[3]]) as usize,
+      };
+      if bytes.len() < 84 + count * 50 {
+         return Err(format!("file is too short for {count} facets"));
+      }
+      let float =
+         |at: usize| f32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+      let vec3 = |at: usize| [float(at), float(at + 4), float(at + 8)];
+      let mut facets = Vec::with_capacity(count);
+      for f in 0..count {
+         let at = 84 + f * 50;
+         let attr = u16::from_le_bytes([bytes[at + 48], bytes[at + 49]]);
+         let color = match attr & 0x8000 != 0 {
+            true => {
+               let channel = |shift: u16| ((attr >> shift) & 0x1F) as f32 / 31.0;
+               [channel(10), channel(5), channel(0)]
+            }
+            false => [1.0, 1.0, 1.0],
+         };
+         facets.push((
+            vec3(at),
+            [vec3(at + 12), vec3(at + 24), vec3(at + 36)],
+            color,
+         ));
+      }
+      STL::build(facets)
+   }
+
+   fn parse_ascii(src: &str) -> Result<NEMeshAsset, String> {
+      let mut facets = Vec::new();
+      let mut normal = [0.0; 3];
+      let mut corners: Vec<[f32; 3]> = Vec::new();
+      for (n, line) in src.lines().enumerate() {
+         let words = line.split_whitespace().collect::<Vec<&str>>();
+         let vec3 = |from: usize| -> Result<[f32; 3], String> {
+            let mut v = [0.0; 3];
+            for (i, c) in v.iter_mut().enumerate() {
+               *c = words
+                  .get(from + i)
+                  .and_then(|w| w.parse::<f32>().ok())
+                  .ok_or(format!("line {}: expected 3 numbers", n + 1))?;
+            }
+            Ok(v)
+         };
+         match words.first() {
+            Some(&"facet") => {
+               normal = vec3(2)?;
+               corners.clear();
+            }
+            Some(&"vertex") => corners.push(vec3(1)?),
+            //facets with more than 3 corners are fanned
+            Some(&"endfacet") => {
+               if corners.len() < 3 {
+                  return Err(format!("line {}: facet has fewer than 3 corners", n + 1));
+               }
+               for i in 1..corners.len() - 1 {
+                  facets.push((normal, [corners[0], corners[i], corners[i + 1]], [1.0; 3]));
+               }
+            }
+            _ => {}
+         }
+      }
+      STL::build(facets)
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::STL;
+
+   //normal, corners and the u16 attribute
+   type Facet = ([f32; 3], [[f32; 3]; 3], u16);
+
+   //80 byte header, then a facet count and the facets
+   fn binary(header: &[u8], facets: &[Facet]) -> Vec<u8> {
+      let mut bytes = header.to_vec();
+      bytes.resize(80, 0);
+      bytes.extend((facets.len() as u32).to_le_bytes());
+      for (normal, corners, attr) in facets.iter() {
+         for v in std::iter::once(normal).chain(corners.iter()) {
+            bytes.extend(v.iter().flat_map(|c| c.to_le_bytes()));
+         }
+         bytes.extend(attr.to_le_bytes());
+      }
+      bytes
+   }
+   const TRI: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+   #[test]
+   fn ascii_facets() {
+      let src = "solid t\n\
+         facet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\n\
+         facet normal 0 0 0\nouter loop\nvertex 0 0 0\nvertex 0 1 0\nvertex 1 0 0\nendloop\nendfacet\n\
+         endsolid t\n";
+      let asset = STL::parse(src.as_bytes()).unwrap();
+      assert_eq!(asset.pos_attr.data().len(), 6);
+      assert_eq!(asset.pos_attr.data()[1], [1.0, 0.0, 0.0]);
+      assert_eq!(asset.nrm_attr.data()[0], [0.0, 0.0, 1.0]);
+      //the zero normal is taken from the winding
+      assert_eq!(asset.nrm_attr.data()[3], [0.0, 0.0, -1.0]);
+      assert_eq!(asset.col_attr.data()[0], [1.0, 1.0, 1.0]);
+      assert!(STL::parse(b"solid t\nfacet normal 0 0 1\nvertex 0 0 0\nendfacet\n").is_err());
+   }
+
+   #[test]
+   fn binary_facets_with_a_solid_header() {
+      let red = 0x8000 | (31 << 10);
+      let bytes = binary(
+         b"solid but binary",
+         &[([0.0, 0.0, 2.0], TRI, red), ([0.0, 0.0, 1.0], TRI, 0)],
+      );
+      let asset = STL::parse(&bytes).unwrap();
+      assert_eq!(asset.pos_attr.data().len(), 6);
+      assert_eq!(asset.pos_attr.data()[2], [0.0, 1.0, 0.0]);
+      assert_eq!(asset.nrm_attr.data()[0], [0.0, 0.0, 1.0]);
+      assert_eq!(asset.col_attr.data()[0], [1.0, 0.0, 0.0]);
+      assert_eq!(asset.col_attr.data()[3], [1.0, 1.0, 1.0]);
+   }
+
+   #[test]
+   fn short_binary_files_are_rejected() {
+      let bytes = binary(b"binary", &[([0.0, 0.0, 1.0], TRI, 0)]);
+      assert!(STL::parse(&bytes[..bytes.len() - 1]).is_err());
+      assert!(STL::parse(&bytes[..40]).is_err());
+   }
+}
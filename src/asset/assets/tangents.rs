@@ -0,0 +1,208 @@
+use crate::*;
+use cgmath::Vector3;
+use std::collections::HashMap;
+
+//corners in the order mikktspace walks them, its tangents are written per corner
+struct NEMikkGeometry<'a> {
+   asset: &'a NEMeshAsset,
+   triangles: &'a [[usize; 3]],
+   tangents: Vec<[f32; 4]>,
+}
+
+impl bevy_mikktspace::Geometry for NEMikkGeometry<'_> {
+   fn num_faces(&self) -> usize {
+      self.triangles.len()
+   }
+   fn num_vertices_of_face(&self, _face: usize) -> usize {
+      3
+   }
+   fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+      self.asset.pos_attr.data[self.triangles[face][vert]]
+   }
+   fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+      self.asset.nrm_attr.data[self.triangles[face][vert]]
+   }
+   fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+      self.asset.uvm_attr.data[self.triangles[face][vert]]
+   }
+   fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+      self.tangents[face * 3 + vert] = tangent;
+   }
+}
+
+impl NEMeshAsset {
+   //mikktspace tangents, so normal maps baked by other tools line up. corners of one vertex
+   //that end up with different tangents (uv seams, mirrored uvs) split it into copies
+   pub fn generate_tangents(&mut self) -> NEResult<()> {
+      if self.pos_attr.is_empty() || self.nrm_attr.is_empty() || self.uvm_attr.is_empty() {
+         return NEResult::ER(NEError::attr_missing(
+            "tangents need positions, normals and uvs",
+         ));
+      }
+      let vert_count = self.pos_attr.data.len();
+      if self.nrm_attr.data.len() < vert_count || self.uvm_attr.data.len() < vert_count {
+         return NEResult::ER(NEError::attr_missing(
+            "tangents need a normal and a uv for every position",
+         ));
+      }
+      let triangles = self.triangles();
+      if triangles.iter().flatten().any(|v| *v >= vert_count) {
+         return NEResult::ER(NEError::attr_missing(
+            "tangents need a position for every index",
+         ));
+      }
+
+      let mut geometry = NEMikkGeometry {
+         asset: self,
+         triangles: &triangles,
+         tangents: vec![[1.0, 0.0, 0.0, 1.0]; triangles.len() * 3],
+      };
+      if !bevy_mikktspace::generate_tangents(&mut geometry) {
+         return NEResult::ER(NEError::attr_missing(
+            "mikktspace found no usable triangles",
+         ));
+      }
+      let corners = geometry.tangents;
+
+      //the first tangent seen keeps the vertex, other ones get a copy each
+      let mut tangents = vec![[1.0, 0.0, 0.0, 1.0]; vert_count];
+      let mut seen: HashMap<usize, Vec<([u32; 4], usize)>> = HashMap::new();
+      let mut remap: Vec<usize> = Vec::with_capacity(corners.len());
+      let mut split: Vec<usize> = Vec::new();
+      for (v, tangent) in triangles.iter().flatten().zip(corners.iter()) {
+         let bits = tangent.map(f32::to_bits);
+         let variants = seen.entry(*v).or_default();
+         let target = match variants.iter().find(|(b, _)| *b == bits) {
+            Some((_, target)) => *target,
+            None => {
+               let target = match variants.is_empty() {
+                  true => {
+                     tangents[*v] = *tangent;
+                     *v
+                  }
+                  false => {
+                     split.push(*v);
+                     tangents.push(*tangent);
+                     tangents.len() - 1
+                  }
+               };
+               variants.push((bits, target));
+               target
+            }
+         };
+         remap.push(target);
+      }
+      for src in split.iter() {
+         self.copy_vertex(*src);
+      }
+      if self.indices.has_data() {
+         for (i, target) in remap.iter().enumerate() {
+            self.indices.data[i] = *target as u32;
+         }
+      }
+      self.tan_attr = TanATTR::from(tangents);
+      NEResult::OK(())
+   }
+
+   pub fn tangents(&self) -> &Vec<[f32; 4]> {
+      self.tan_attr.data()
+   }
+   //cross(normal, tangent) * w, what shaders rebuild from the tangent attribute
+   pub fn bitangents(&self) -> Vec<[f32; 3]> {
+      let mut bitangents = Vec::new();
+      for (t, n) in self.tan_attr.data.iter().zip(self.nrm_attr.data.iter()) {
+         let b = Vector3::from(*n).cross(Vector3::new(t[0], t[1], t[2])) * t[3];
+         bitangents.push([b.x, b.y, b.z]);
+      }
+      bitangents
+   }
+
+   //vertex triples from the indices, or consecutive vertices without them
+   pub(crate) fn triangles(&self) -> Vec<[usize; 3]> {
+      let indices: Vec<usize> = match self.indices.has_data() {
+         true => self.indices.data.iter().map(|i| *i as usize).collect(),
+         false => (0..self.pos_attr.data.len()).collect(),
+      };
+      indices
+         .chunks_exact(3)
+         .map(|c| [c[0], c[1], c[2]])
+         .collect()
+   }
+
+   //appends a copy of vertex `src` to every attribute
+   pub(crate) fn copy_vertex(&mut self, src: usize) {
+      if self.pos_attr.has_data() {
+         self.pos_attr.shove(self.pos_attr.data[src]);
+      }
+      if self.col_attr.has_data() {
+         self.col_attr.shove(self.col_attr.data[src]);
+      }
+      if self.uvm_attr.has_data() {
+         self.uvm_attr.shove(self.uvm_attr.data[src]);
+      }
+      if self.nrm_attr.has_data() {
+         self.nrm_attr.shove(self.nrm_attr.data[src]);
+      }
+      if self.tan_attr.has_data() {
+         self.tan_attr.shove(self.tan_attr.data[src]);
+      }
+      for cus_attr in self.cus_attrs.iter_mut() {
+         let size = cus_attr.info.byte_count * cus_attr.info.elem_count;
+         if size > 0 {
+            let elem = cus_attr.data[src * size..(src + 1) * size].to_vec();
+            cus_attr.data.extend(elem);
+         }
+      }
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use crate::*;
+
+   fn quad(uvs: Vec<[f32; 2]>) -> NEMeshAsset {
+      NEMeshAsset::from_attrs(
+         PosATTR::from(vec![
+            [-1.0, -1.0, 0.0],
+            [1.0, -1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [-1.0, 1.0, 0.0],
+         ]),
+         ColATTR::empty(),
+         UVMATTR::from(uvs),
+         NrmATTR::from(vec![[0.0, 0.0, 1.0]; 4]),
+         Indices::from(vec![0, 1, 2, 0, 2, 3]),
+      )
+   }
+
+   #[test]
+   fn tangents_follow_u() {
+      let mut asset = quad(vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]);
+      asset.generate_tangents().unpack();
+      assert_eq!(asset.tangents().len(), 4);
+      for t in asset.tangents() {
+         assert!(
+            (t[0] - 1.0).abs() < 1e-5 && t[1].abs() < 1e-5 && t[3] == 1.0,
+            "{t:?}"
+         );
+      }
+   }
+
+   #[test]
+   fn mirrored_uvs_flip_handedness() {
+      let mut asset = quad(vec![[1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0]]);
+      asset.generate_tangents().unpack();
+      for t in asset.tangents() {
+         assert!((t[0] + 1.0).abs() < 1e-5 && t[3] == -1.0, "{t:?}");
+      }
+   }
+
+   #[test]
+   fn short_attrs_are_rejected() {
+      let mut asset = quad(vec![[0.0, 0.0], [1.0, 0.0]]);
+      assert!(asset.generate_tangents().is_err());
+      let mut asset = quad(vec![[0.0, 0.0]; 4]);
+      asset.indices = Indices::from(vec![0, 1, 7]);
+      assert!(asset.generate_tangents().is_err());
+   }
+}
@@ -1,9 +1,13 @@
-use crate::Size2D;
-use png::{BitDepth, ColorType};
+use crate::asset::file;
+use crate::renderer::{NEHandle, NEResGuard};
+use crate::{NEError, NEOption, NEResult, Size2D};
+use png::{BitDepth, ColorType, Transformations};
 use std::fs::File;
+use std::path::PathBuf;
+use std::rc::Rc;
 
 #[derive(Debug, Clone, Copy)]
-pub(crate) enum TexFormat {
+pub enum TexFormat {
    R(u8), //(bit depth)
    RG(u8),
    RGB(u8),
@@ -46,6 +50,10 @@ impl TexFormat {
 }
 
 pub struct NETexture {
+   pub(crate) id: u32,
+   pub(crate) exists_on_gpu: bool,
+   //the texture is released when the last clone and binding holding this drops
+   pub(crate) gpu: Option<Rc<NEResGuard>>,
    pub(crate) exists: bool,
    pub(crate) bytes: Vec<u8>,
    pub(crate) bit_depth: u8,
@@ -60,6 +68,9 @@ pub struct NETexture {
 impl NETexture {
    pub fn empty() -> NETexture {
       NETexture {
+         id: 0,
+         exists_on_gpu: false,
+         gpu: None,
          bytes: Vec::new(),
          exists: false,
          typ: TexFormat::RGB(8),
@@ -76,7 +87,8 @@ impl NETexture {
          Ok(file) => file,
          Err(error) => panic!("{tex_path}: {error}"),
       };
-      let decoder = png::Decoder::new(tex);
+      let mut decoder = png::Decoder::new(tex);
+      decoder.set_transformations(Transformations::EXPAND);
       let mut reader = decoder.read_info().unwrap();
       let mut bytes = vec![0; reader.output_buffer_size()];
       let info = reader.next_frame(&mut bytes).unwrap();
@@ -101,6 +113,9 @@ impl NETexture {
 
       let size = Size2D::from(info.width, info.height);
       NETexture {
+         id: 0,
+         exists_on_gpu: false,
+         gpu: None,
          bytes,
          exists: true,
          size,
@@ -112,3 +127,180 @@ impl NETexture {
       }
    }
 }
+
+impl NETexture {
+   pub(crate) fn from_raw(bytes: Vec<u8>, size: Size2D, typ: TexFormat) -> NETexture {
+      let bit_depth = typ.bit_depth();
+      NETexture {
+         id: 0,
+         exists_on_gpu: false,
+         gpu: None,
+         bytes,
+         exists: true,
+         size,
+         bit_depth,
+         pixel_size: typ.elem_count() * bit_depth,
+         typ,
+         filter: TexFilter::Closest,
+         wrap: TexWrap::Extend,
+      }
+   }
+
+   //like NETexture::from but reports errors instead of panicking
+   pub(crate) fn read_png(tex_path: &str) -> NEResult<NETexture> {
+      if !file::exists_on_disk(tex_path) {
+         return NEResult::ER(NEError::file_missing(tex_path));
+      }
+      match file::read_as_bytes(tex_path) {
+         NEResult::ER(e) => NEResult::ER(e),
+         NEResult::OK(png) => NETexture::decode_png(&png, tex_path),
+      }
+   }
+   //png already in memory, like images embedded in model files, `name` is for errors
+   //palettes are expanded to rgb(a) and low bit depths to 8 bit
+   pub(crate) fn decode_png(png: &[u8], name: &str) -> NEResult<NETexture> {
+      let mut decoder = png::Decoder::new(png);
+      decoder.set_transformations(Transformations::EXPAND);
+      let mut reader = match decoder.read_info() {
+         Ok(r) => r,
+         Err(_) => return NEResult::ER(NEError::file_invalid(name)),
+      };
+      let mut bytes = vec![0; reader.output_buffer_size()];
+      let info = match reader.next_frame(&mut bytes) {
+         Ok(i) => i,
+         Err(_) => return NEResult::ER(NEError::file_invalid(name)),
+      };
+      bytes.truncate(info.buffer_size());
+      let bit_depth = match info.bit_depth {
+         BitDepth::Sixteen => 16,
+         _ => 8,
+      };
+      let typ = match info.color_type {
+         ColorType::Grayscale => TexFormat::R(bit_depth),
+         ColorType::GrayscaleAlpha => TexFormat::RG(bit_depth),
+         ColorType::Rgb => TexFormat::RGB(bit_depth),
+         ColorType::Indexed => TexFormat::Palette(bit_depth),
+         ColorType::Rgba => TexFormat::RGBA(bit_depth),
+      };
+      let size = Size2D::from(info.width, info.height);
+      NEResult::OK(NETexture::from_raw(bytes, size, typ))
+   }
+
+   pub fn handle(&self) -> Option<NEHandle> {
+      self.gpu.as_ref().map(|g| g.handle)
+   }
+   //palette textures hold bare indices without their palette, they can't be written
+   pub fn write_png(&self, path: &str) -> NEResult<()> {
+      let color = match self.typ {
+         TexFormat::R(_) => ColorType::Grayscale,
+         TexFormat::RG(_) => ColorType::GrayscaleAlpha,
+         TexFormat::RGB(_) => ColorType::Rgb,
+         TexFormat::RGBA(_) => ColorType::Rgba,
+         TexFormat::Palette(_) => return NEResult::ER(NEError::file_unsupported(path, "palette")),
+      };
+      let depth = match self.bit_depth {
+         16 => BitDepth::Sixteen,
+         _ => BitDepth::Eight,
+      };
+      let mut png_bytes = Vec::new();
+      {
+         let mut encoder = png::Encoder::new(&mut png_bytes, self.size.w, self.size.h);
+         encoder.set_color(color);
+         encoder.set_depth(depth);
+         let written = match encoder.write_header() {
+            Ok(mut writer) => writer.write_image_data(&self.bytes).is_ok(),
+            Err(_) => false,
+         };
+         if !written {
+            return NEResult::ER(NEError::file_couldnt_write(path));
+         }
+      }
+      let name = match file::name(path) {
+         NEOption::Empty => return NEResult::ER(NEError::file_invalid(path)),
+         NEOption::Exists(n) => format!("{n}.png"),
+      };
+      let dir = match PathBuf::from(path).parent() {
+         None => "".to_string(),
+         Some(p) if p.as_os_str().is_empty() => "".to_string(),
+         Some(p) => format!("{}/", p.to_string_lossy()),
+      };
+      file::write_bytes_to_disk(&dir, &name, &png_bytes)
+   }
+
+   //expands any format to 8 bit rgba, grey formats are splatted across rgb
+   pub(crate) fn rgba8(&self) -> Vec<u8> {
+      let channels = self.typ.elem_count() as usize;
+      let depth = match self.bit_depth {
+         16 => 2,
+         _ => 1,
+      };
+      let pixels = (self.size.w * self.size.h) as usize;
+      let mut out = Vec::with_capacity(pixels * 4);
+      for p in 0..pixels {
+         let mut c = [0u8; 4];
+         for i in 0..channels {
+            c[i] = *self.bytes.get((p * channels + i) * depth).unwrap_or(&0);
+         }
+         let rgba = match self.typ {
+            TexFormat::R(_) => [c[0], c[0], c[0], 255],
+            TexFormat::RG(_) => [c[0], c[0], c[0], c[1]],
+            TexFormat::RGB(_) | TexFormat::Palette(_) => [c[0], c[1], c[2], 255],
+            TexFormat::RGBA(_) => c,
+         };
+         out.extend_from_slice(&rgba);
+      }
+      out
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   fn temp_path(name: &str) -> String {
+      let dir = std::env::temp_dir().join("nerve-textureasset");
+      dir.join(name).to_string_lossy().to_string()
+   }
+
+   #[test]
+   fn png_roundtrip() {
+      let formats = [
+         (TexFormat::R(8), vec![0, 64, 128, 255]),
+         (TexFormat::RG(8), vec![0, 1, 2, 3, 4, 5, 6, 7]),
+         (TexFormat::RGBA(8), (0..16).collect()),
+      ];
+      for (i, (typ, bytes)) in formats.into_iter().enumerate() {
+         let path = temp_path(&format!("roundtrip{i}.png"));
+         let tex = NETexture::from_raw(bytes.clone(), Size2D::from(2, 2), typ);
+         tex.write_png(&path).unpack();
+         let read = NETexture::read_png(&path).unpack();
+         assert_eq!(read.bytes, bytes);
+         assert_eq!(read.typ.elem_count(), typ.elem_count());
+      }
+   }
+
+   #[test]
+   fn palettes_expand_on_read() {
+      let mut png_bytes = Vec::new();
+      {
+         let mut encoder = png::Encoder::new(&mut png_bytes, 2, 1);
+         encoder.set_color(ColorType::Indexed);
+         encoder.set_depth(BitDepth::Eight);
+         encoder.set_palette(vec![255, 0, 0, 0, 0, 255]);
+         let mut writer = encoder.write_header().unwrap();
+         writer.write_image_data(&[1, 0]).unwrap();
+      }
+      let tex = NETexture::decode_png(&png_bytes, "palette").unpack();
+      assert!(matches!(tex.typ, TexFormat::RGB(8)));
+      assert_eq!(tex.bytes, vec![0, 0, 255, 255, 0, 0]);
+   }
+
+   #[test]
+   fn palette_write_is_rejected() {
+      let tex = NETexture::from_raw(vec![0, 1], Size2D::from(2, 1), TexFormat::Palette(8));
+      assert!(matches!(
+         tex.write_png(&temp_path("palette.png")),
+         NEResult::ER(_)
+      ));
+   }
+}
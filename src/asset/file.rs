@@ -36,6 +36,21 @@ pub(crate) fn exists_on_disk(path: &str) -> bool {
    path.exists()
 }
 
+//(length, modified time in nanos), enough to notice a changed file
+pub(crate) fn stamp(path: &str) -> NEOption<(u64, u64)> {
+   let meta = match fs::metadata(path) {
+      Err(_) => return NEOption::Empty,
+      Ok(m) => m,
+   };
+   let modified = meta
+      .modified()
+      .ok()
+      .and_then(|m| m.duration_since(std::time::UNIX_EPOCH).ok())
+      .map(|d| d.as_nanos() as u64)
+      .unwrap_or(0);
+   NEOption::Exists((meta.len(), modified))
+}
+
 pub(crate) fn write_str_to_disk(path: &str, name: &str, content: &str) -> NEResult<()> {
    write_bytes_to_disk(path, name, content.as_bytes())
 }
@@ -1,7 +1,7 @@
 use crate::engine::game::cycle::NECycle;
 use crate::engine::{ButtonState, KeyBitMap, MouseBitMap};
 use crate::renderer::core::VKRenderer;
-use crate::renderer::{gl_renderer_init, CamProj, NECamera, Renderer};
+use crate::renderer::{gl_renderer_init, sw_renderer_init, CamProj, NECamera, Renderer};
 use crate::util::{NEError, NEResult};
 use crate::{
    NEEvents, NEGame, NERenderer, NEScene, NETime, NEWindow, ScreenCoord, ScreenOffset, Size2D,
@@ -16,6 +16,7 @@ use std::time::Instant;
 pub enum RenderAPI {
    OpenGL,
    Vulkan(u32, u32),
+   Software,
 }
 
 impl Display for RenderAPI {
@@ -29,6 +30,7 @@ impl RenderAPI {
       match self {
          RenderAPI::OpenGL => "OpenGL".to_string(),
          RenderAPI::Vulkan(v0, v1) => format!("Vulkan {v0}.{v1}"),
+         RenderAPI::Software => "Software".to_string(),
       }
    }
 }
@@ -36,7 +38,20 @@ impl RenderAPI {
 pub enum WinMode {
    Windowed(u32, u32),
    Full,
+   //no window or monitor, always drawn by the software backend
+   Headless(u32, u32),
 }
+
+impl WinMode {
+   //size of a window-less game, fullscreen has no monitor to take it from
+   fn headless_size(&self) -> Size2D {
+      match self {
+         WinMode::Windowed(w, h) | WinMode::Headless(w, h) => Size2D::from(*w, *h),
+         WinMode::Full => Size2D::from(1280, 720),
+      }
+   }
+}
+
 pub enum FPS {
    Vsync,
    Max,
@@ -96,7 +111,8 @@ fn window_from(
                monitor_size = Size2D::from(vid_mode.width, vid_mode.height);
 
                let mode = match mode {
-                  WinMode::Windowed(mut w, mut h) => {
+                  //headless games never reach glfw, see NEGameBuilder::build
+                  WinMode::Windowed(mut w, mut h) | WinMode::Headless(mut w, mut h) => {
                      const DIV: u32 = 10;
                      let min_h = vid_mode.height / DIV;
                      let min_w = vid_mode.width / DIV;
@@ -199,6 +215,10 @@ fn init_nerve(
             monitor_size,
          ))
       }
+      //never given a window, see NEGameBuilder::build
+      RenderAPI::Software => NEResult::ER(NEError::Init {
+         kind: NEInitErrKind::APIUnsupported(api.api_str()),
+      }),
    }
 }
 
@@ -216,6 +236,13 @@ pub(crate) enum NEInitErrKind {
 
 impl NEGameBuilder {
    pub fn build(&self) -> NEResult<NEGame> {
+      //the software backend draws into memory only, it needs no glfw or monitor
+      match (&self.mode, self.render_api) {
+         (WinMode::Headless(_, _), _) | (_, RenderAPI::Software) => {
+            return NEResult::OK(self.build_headless(self.mode.headless_size()));
+         }
+         _ => {}
+      }
       let api_str = self.render_api.api_str();
       let api_str_m = api_str.clone();
       //REMOVE WHEN VK SUPPORT IS ADDED
@@ -272,10 +299,9 @@ impl NEGameBuilder {
       let cursor_coord_global =
          ScreenCoord::from(cx as f64 + window_coord.x, cy as f64 + window_coord.y);
 
-      let current_time = Instant::now();
       let mut window = NEWindow {
-         glfw: glfw.clone(),
-         window,
+         glfw: Some(glfw.clone()),
+         window: Some(window),
          prev_cursor_coord: cursor_coord,
          cursor_offset: ScreenOffset::empty(),
          prev_coord: window_coord,
@@ -288,6 +314,7 @@ impl NEGameBuilder {
          is_resizable: true,
          is_running: true,
          is_vsync,
+         is_headless: false,
          size: window_size,
          coord: window_coord,
          title: self.title.clone(),
@@ -295,14 +322,54 @@ impl NEGameBuilder {
          cursor_coord_global,
       };
       window.set_coord(window_coord);
+      NEResult::OK(self.game_from(core, self.render_api, window, Some(events), Some(glfw)))
+   }
 
+   fn build_headless(&self, size: Size2D) -> NEGame {
+      let centre = ScreenCoord::from(size.w as f64 / 2.0, size.h as f64 / 2.0);
+      let window = NEWindow {
+         glfw: None,
+         window: None,
+         prev_cursor_coord: centre,
+         cursor_offset: ScreenOffset::empty(),
+         prev_coord: ScreenCoord::empty(),
+         prev_size: size,
+         is_cursor_hidden: false,
+         is_cursor_off: false,
+         is_fullscreen: false,
+         is_hidden: true,
+         is_borderless: true,
+         is_resizable: false,
+         is_running: true,
+         is_vsync: false,
+         is_headless: true,
+         size,
+         coord: ScreenCoord::empty(),
+         title: self.title.clone(),
+         cursor_coord: centre,
+         cursor_coord_global: centre,
+      };
+      let core = Box::new(sw_renderer_init(size));
+      self.game_from(core, RenderAPI::Software, window, None, None)
+   }
+
+   fn game_from(
+      &self,
+      core: Box<dyn Renderer>,
+      api: RenderAPI,
+      window: NEWindow,
+      events: Option<GlfwReceiver<(f64, WindowEvent)>>,
+      glfw: Option<Glfw>,
+   ) -> NEGame {
+      let window_size = window.size;
+      let current_time = Instant::now();
       let cam = NECamera::from(window_size, CamProj::Persp);
-      let renderer = NERenderer::from(core, self.render_api, cam.view_matrix, cam.proj_matrix);
+      let renderer = NERenderer::from(core, api, window_size, cam.view_matrix, cam.proj_matrix);
       let scene_name = format!("{}-init-scene", window.title);
       let mut scene = NEScene::new(&scene_name);
       scene.replace_cam(cam);
 
-      NEResult::OK(NEGame {
+      NEGame {
          renderer,
          window,
          events: NEEvents {
@@ -343,6 +410,6 @@ impl NEGameBuilder {
          },
          scene,
          cycle: NECycle { is_paused: false },
-      })
+      }
    }
 }
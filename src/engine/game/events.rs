@@ -182,7 +182,8 @@ pub(crate) fn key_index(key: &Key) -> usize {
 }
 
 pub struct NEEvents {
-   pub(crate) events: GlfwReceiver<(f64, WindowEvent)>,
+   //None for a headless game
+   pub(crate) events: Option<GlfwReceiver<(f64, WindowEvent)>>,
    pub(crate) is_uncleared: bool,
    pub(crate) key_bitmap: KeyBitMap,
    pub(crate) mouse_bitmap: MouseBitMap,
@@ -207,7 +208,11 @@ impl NEEvents {
    }
 
    fn catch(&mut self) {
-      for (_f, event) in flush_messages(&self.events) {
+      let events = match &self.events {
+         None => return,
+         Some(e) => e,
+      };
+      for (_f, event) in flush_messages(events) {
          match event {
             WindowEvent::Key(k, _s, a, _m) => {
                let key_in_bitmap = &mut self.key_bitmap.0[key_index(&k)];
@@ -21,6 +21,11 @@ impl NEGame {
          self.resize_children(self.events.window_resize_event.1);
          self.events.window_resize_event.0 = false;
       }
+      //without glfw there are no resize events, pick up set_size here
+      let (size, prev) = (self.window.size, self.renderer.size);
+      if self.window.window.is_none() && (size.w != prev.w || size.h != prev.h) {
+         self.resize_children(size);
+      }
 
       if self.events.window_close_event {
          self.window.close();
@@ -80,7 +85,7 @@ impl NEGame {
    }
 
    pub fn post_update(&mut self) {
-      self.renderer.post_update();
+      self.renderer.post_update(self.time.elapsed);
       self.window.post_update();
       self.events.post_update();
       self.time.post_update();
@@ -100,6 +105,9 @@ impl NEGame {
          &mut self.cycle,
          &mut self.time,
       );
+      //objects own their meshes, anything still alive after them has leaked
+      self.scene.objects.clear();
+      self.renderer.end();
       log_event!("game [{}] end!", self.window.title);
       drop(self);
    }
@@ -7,7 +7,7 @@ pub struct NETime {
    pub frame: u64,
    pub elapsed: f64,
 
-   pub(crate) glfw: Glfw,
+   pub(crate) glfw: Option<Glfw>,
    pub(crate) prev_sec: Instant,
    pub(crate) prev_time: Instant,
    pub(crate) prev_deltas: Vec<f64>,
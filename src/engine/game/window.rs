@@ -2,8 +2,9 @@ use crate::{ScreenCoord, ScreenOffset, Size2D};
 use glfw::{Context, CursorMode, Glfw, PWindow, SwapInterval, WindowMode};
 
 pub struct NEWindow {
-   pub(crate) glfw: Glfw,
-   pub(crate) window: PWindow,
+   //both are None for a headless game
+   pub(crate) glfw: Option<Glfw>,
+   pub(crate) window: Option<PWindow>,
 
    pub(crate) prev_cursor_coord: ScreenCoord,
    pub(crate) prev_coord: ScreenCoord,
@@ -17,6 +18,7 @@ pub struct NEWindow {
    pub is_running: bool,
    pub is_hidden: bool,
    pub is_vsync: bool,
+   pub is_headless: bool,
 
    pub size: Size2D,
    pub title: String,
@@ -34,17 +36,21 @@ impl NEWindow {
       prev_size: Size2D,
       refresh_rate: Option<u32>,
    ) {
-      self.window.set_monitor(
-         mode,
-         prev_pos.x as i32,
-         prev_pos.y as i32,
-         prev_size.w,
-         prev_size.h,
-         refresh_rate,
-      );
+      if let Some(window) = &mut self.window {
+         window.set_monitor(
+            mode,
+            prev_pos.x as i32,
+            prev_pos.y as i32,
+            prev_size.w,
+            prev_size.h,
+            refresh_rate,
+         );
+      }
    }
    pub(crate) fn pre_update(&mut self) {
-      self.glfw.poll_events();
+      if let Some(glfw) = &mut self.glfw {
+         glfw.poll_events();
+      }
       self.size = self.get_size();
       self.coord = self.get_coord();
       self.cursor_coord = self.get_cursor_coord();
@@ -62,28 +68,44 @@ impl NEWindow {
    }
 
    pub(crate) fn toggle_visibility(&mut self) {
+      let window = match &mut self.window {
+         None => return,
+         Some(w) => w,
+      };
       match self.is_hidden {
-         true => self.window.show(),
-         false => self.window.hide(),
+         true => window.show(),
+         false => window.hide(),
       }
       self.is_hidden = !self.is_hidden;
    }
 
    fn get_size(&mut self) -> Size2D {
-      let (w, h) = self.window.get_size();
-      Size2D::from(w as u32, h as u32)
+      match &self.window {
+         None => self.size,
+         Some(window) => {
+            let (w, h) = window.get_size();
+            Size2D::from(w as u32, h as u32)
+         }
+      }
    }
    fn get_coord(&self) -> ScreenCoord {
-      let (x, y) = self.window.get_pos();
-      ScreenCoord::from(x as f64, y as f64)
+      match &self.window {
+         None => self.coord,
+         Some(window) => {
+            let (x, y) = window.get_pos();
+            ScreenCoord::from(x as f64, y as f64)
+         }
+      }
    }
    fn get_cursor_coord(&self) -> ScreenCoord {
-      let (x, y) = self.window.get_cursor_pos();
-      ScreenCoord::from(x, y)
+      match &self.window {
+         None => self.cursor_coord,
+         Some(window) => ScreenCoord::from_tup(window.get_cursor_pos()),
+      }
    }
    fn get_cursor_coord_global(&self) -> ScreenCoord {
-      let (x, y) = self.window.get_cursor_pos();
-      ScreenCoord::from(x + self.coord.x, y + self.coord.y)
+      let coord = self.get_cursor_coord();
+      ScreenCoord::from(coord.x + self.coord.x, coord.y + self.coord.y)
    }
    fn get_cursor_offset(&mut self) -> ScreenOffset {
       let coord = self.cursor_coord;
@@ -96,28 +118,41 @@ impl NEWindow {
    }
 
    fn swap(&mut self) {
-      self.window.swap_buffers()
+      if let Some(window) = &mut self.window {
+         window.swap_buffers()
+      }
    }
 
    pub fn close(&mut self) {
-      self.window.set_should_close(true);
+      if let Some(window) = &mut self.window {
+         window.set_should_close(true);
+      }
       self.is_running = false
    }
 
    pub fn select(&mut self) {
-      self.window.make_current()
+      if let Some(window) = &mut self.window {
+         window.make_current()
+      }
    }
 
    pub fn set_title(&mut self, title: &str) {
-      self.window.set_title(&title);
+      if let Some(window) = &mut self.window {
+         window.set_title(&title);
+      }
       self.title = title.to_string();
    }
    pub fn set_size(&mut self, size: Size2D) {
-      self.window.set_size(size.w as i32, size.h as i32);
+      match &mut self.window {
+         None => self.size = size,
+         Some(window) => window.set_size(size.w as i32, size.h as i32),
+      }
    }
    pub fn set_coord(&mut self, coord: ScreenCoord) {
       self.coord = coord;
-      self.window.set_pos(coord.x as i32, coord.y as i32);
+      if let Some(window) = &mut self.window {
+         window.set_pos(coord.x as i32, coord.y as i32);
+      }
    }
 
    pub fn is_cursor_inside(&self) -> bool {
@@ -126,13 +161,15 @@ impl NEWindow {
 
    pub fn set_cursor_coord(&mut self, coord: ScreenCoord) {
       self.cursor_coord = coord;
-      self.window.set_cursor_pos(coord.x as f64, coord.y as f64)
+      if let Some(window) = &mut self.window {
+         window.set_cursor_pos(coord.x as f64, coord.y as f64)
+      }
    }
 
    pub fn set_cursor_visibility(&mut self, hide: bool) {
       self.is_cursor_hidden = hide;
-      if !self.is_cursor_off {
-         self.window.set_cursor_mode(match hide {
+      if let (false, Some(window)) = (self.is_cursor_off, &mut self.window) {
+         window.set_cursor_mode(match hide {
             true => CursorMode::Normal,
             false => CursorMode::Hidden,
          });
@@ -150,13 +187,16 @@ impl NEWindow {
    }
    pub fn toggle_cursor_usage(&mut self) {
       self.is_cursor_off = !self.is_cursor_off;
-      self.window.set_cursor_mode(match self.is_cursor_off {
+      let mode = match self.is_cursor_off {
          true => CursorMode::Disabled,
          false => match self.is_cursor_hidden {
             true => CursorMode::Hidden,
             false => CursorMode::Normal,
          },
-      })
+      };
+      if let Some(window) = &mut self.window {
+         window.set_cursor_mode(mode)
+      }
    }
 
    pub fn set_fullscreen(&mut self, enable: bool) {
@@ -167,12 +207,19 @@ impl NEWindow {
    }
 
    pub fn toggle_fullscreen(&mut self) {
+      if self.is_headless {
+         return;
+      }
       self.is_fullscreen = !self.is_fullscreen;
       if self.is_fullscreen {
          self.prev_coord = self.get_coord();
          self.prev_size = self.get_size();
 
-         self.glfw.with_primary_monitor(|_, m| {
+         let (glfw, window) = match (&mut self.glfw, &mut self.window) {
+            (Some(g), Some(w)) => (g, w),
+            _ => return,
+         };
+         glfw.with_primary_monitor(|_, m| {
             let monitor = match m {
                None => return,
                Some(m) => m,
@@ -181,7 +228,7 @@ impl NEWindow {
                None => return,
                Some(vm) => vm,
             };
-            self.window.set_monitor(
+            window.set_monitor(
                WindowMode::FullScreen(&monitor),
                0,
                0,
@@ -207,13 +254,17 @@ impl NEWindow {
    }
    pub fn toggle_resizable(&mut self) {
       self.is_resizable = !self.is_resizable;
-      self.window.set_resizable(self.is_resizable);
+      if let Some(window) = &mut self.window {
+         window.set_resizable(self.is_resizable);
+      }
    }
    pub fn set_vsync(&mut self, enable: bool) {
-      self.window.glfw.set_swap_interval(match enable {
-         true => SwapInterval::Adaptive,
-         false => SwapInterval::None,
-      });
+      if let Some(window) = &mut self.window {
+         window.glfw.set_swap_interval(match enable {
+            true => SwapInterval::Adaptive,
+            false => SwapInterval::None,
+         });
+      }
       self.is_vsync = enable;
    }
    pub fn toggle_vsync(&mut self) {
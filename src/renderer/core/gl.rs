@@ -1,8 +1,11 @@
 use crate::asset::{ATTRInfo, TexFormat};
 use crate::renderer::{Renderer, ShaderType};
 use crate::util::misc;
-use crate::{ansi, NEShaderAsset, NETexture, TexFilter, TexWrap, RGB};
-use crate::{log_info, ATTRType, Cull, DrawMode, NEError, NEResult, PolyMode, Size2D, Uniform};
+use crate::Uniform;
+use crate::{ansi, BlendMode, IndexFormat, NEShaderAsset, NETexture, TexFilter, TexWrap, RGB};
+use crate::{
+   log_info, ATTRKind, ATTRType, Cull, DrawMode, NEError, NEOption, NEResult, PolyMode, Size2D,
+};
 use cgmath::{Matrix, Matrix4};
 use glfw::{Context, PWindow};
 use gll as gl;
@@ -134,6 +137,26 @@ impl Renderer for GLRenderer {
          }
       }
    }
+   fn enable_blend(&self, enable: bool) {
+      let gl = &self.gl;
+      unsafe {
+         match enable {
+            true => gl.raw.Enable(gl::BLEND),
+            false => gl.raw.Disable(gl::BLEND),
+         }
+      }
+   }
+   //alpha is kept separate so read back frames stay opaque where the scene was
+   fn set_blend_mode(&self, mode: BlendMode) {
+      let over = (gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+      let ((src, dst), (src_a, dst_a)) = match mode {
+         BlendMode::Alpha => ((gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA), over),
+         BlendMode::Additive => ((gl::SRC_ALPHA, gl::ONE), over),
+         BlendMode::Multiply => ((gl::DST_COLOR, gl::ZERO), (gl::ZERO, gl::ONE)),
+         BlendMode::Premultiplied => (over, over),
+      };
+      unsafe { self.gl.raw.BlendFuncSeparate(src, dst, src_a, dst_a) }
+   }
    fn set_cull_face(&self, face: Cull) {
       let gl = &self.gl;
       unsafe {
@@ -338,38 +361,116 @@ impl Renderer for GLRenderer {
       }
    }
 
-   fn get_uni_location(&self, id: u32, name: &str) -> u32 {
-      unsafe {
-         let c_name = CString::new(name).unwrap();
-         let location = self.gl.raw.GetUniformLocation(id, c_name.as_ptr());
-         if location == -1 {
-            panic!("uniform '{name}' does not exist!");
-         } else {
-            location as u32
+   fn get_uni_location(&self, id: u32, name: &str) -> NEOption<i32> {
+      let c_name = match CString::new(name) {
+         Ok(c_name) => c_name,
+         Err(_) => return NEOption::Empty,
+      };
+      match unsafe { self.gl.raw.GetUniformLocation(id, c_name.as_ptr()) } {
+         -1 => NEOption::Empty,
+         location => NEOption::Exists(location),
+      }
+   }
+   fn active_uniforms(&self, id: u32) -> Vec<String> {
+      let raw = &self.gl.raw;
+      let mut names = Vec::new();
+      unsafe {
+         let (mut count, mut max_len) = (0, 0);
+         raw.GetProgramiv(id, gl::ACTIVE_UNIFORMS, &mut count);
+         raw.GetProgramiv(id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_len);
+         let mut buf: Vec<u8> = vec![0; max_len.max(1) as usize];
+         for i in 0..count as GLuint {
+            let (mut len, mut size, mut typ) = (0, 0, 0);
+            raw.GetActiveUniform(
+               id,
+               i,
+               buf.len() as GLsizei,
+               &mut len,
+               &mut size,
+               &mut typ,
+               buf.as_mut_ptr() as *mut GLchar,
+            );
+            let name = String::from_utf8_lossy(&buf[..len as usize]).to_string();
+            //arrays are reported as name[0], both spellings resolve to the first element
+            if let Some(base) = name.strip_suffix("[0]") {
+               names.push(base.to_string());
+            }
+            names.push(name);
          }
       }
+      names
    }
    fn set_uni(&self, id: u32, name: &str, uniform: Uniform) {
-      match uniform {
-         Uniform::Matrix4(m) => self.set_uni_m4f32(id, name, m),
-         Uniform::Int(i) => self.set_uni_i32(id, name, i),
+      if let NEOption::Exists(loc) = self.get_uni_location(id, name) {
+         self.set_uni_at(loc, uniform)
+      }
+   }
+   fn set_uni_at(&self, loc: i32, uniform: Uniform) {
+      if uniform.is_empty_array() {
+         return;
+      }
+      let loc = loc as GLint;
+      let raw = &self.gl.raw;
+      unsafe {
+         match uniform {
+            Uniform::Int(i) => raw.Uniform1i(loc, i),
+            Uniform::IVec2(v) => raw.Uniform2i(loc, v[0], v[1]),
+            Uniform::IVec3(v) => raw.Uniform3i(loc, v[0], v[1], v[2]),
+            Uniform::IVec4(v) => raw.Uniform4i(loc, v[0], v[1], v[2], v[3]),
+
+            Uniform::UInt(u) => raw.Uniform1ui(loc, u),
+            Uniform::UVec2(v) => raw.Uniform2ui(loc, v[0], v[1]),
+            Uniform::UVec3(v) => raw.Uniform3ui(loc, v[0], v[1], v[2]),
+            Uniform::UVec4(v) => raw.Uniform4ui(loc, v[0], v[1], v[2], v[3]),
+
+            Uniform::Float(f) => raw.Uniform1f(loc, f),
+            Uniform::Vec2(v) => raw.Uniform2f(loc, v[0], v[1]),
+            Uniform::Vec3(v) => raw.Uniform3f(loc, v[0], v[1], v[2]),
+            Uniform::Vec4(v) => raw.Uniform4f(loc, v[0], v[1], v[2], v[3]),
+
+            Uniform::Bool(b) => raw.Uniform1i(loc, b as GLint),
+            Uniform::Sampler(unit) => raw.Uniform1i(loc, unit as GLint),
+
+            Uniform::Matrix2(m) => raw.UniformMatrix2fv(loc, 1, gl::FALSE, m.as_ptr()),
+            Uniform::Matrix3(m) => raw.UniformMatrix3fv(loc, 1, gl::FALSE, m.as_ptr()),
+            Uniform::Matrix4(m) => raw.UniformMatrix4fv(loc, 1, gl::FALSE, m.as_ptr()),
+
+            //arrays are contiguous, the first element points at the whole block
+            Uniform::IntArray(a) => raw.Uniform1iv(loc, a.len() as GLsizei, a.as_ptr()),
+            Uniform::IVec2Array(a) => raw.Uniform2iv(loc, a.len() as GLsizei, a[0].as_ptr()),
+            Uniform::IVec3Array(a) => raw.Uniform3iv(loc, a.len() as GLsizei, a[0].as_ptr()),
+            Uniform::IVec4Array(a) => raw.Uniform4iv(loc, a.len() as GLsizei, a[0].as_ptr()),
+            Uniform::UIntArray(a) => raw.Uniform1uiv(loc, a.len() as GLsizei, a.as_ptr()),
+            Uniform::UVec2Array(a) => raw.Uniform2uiv(loc, a.len() as GLsizei, a[0].as_ptr()),
+            Uniform::UVec3Array(a) => raw.Uniform3uiv(loc, a.len() as GLsizei, a[0].as_ptr()),
+            Uniform::UVec4Array(a) => raw.Uniform4uiv(loc, a.len() as GLsizei, a[0].as_ptr()),
+            Uniform::FloatArray(a) => raw.Uniform1fv(loc, a.len() as GLsizei, a.as_ptr()),
+            Uniform::Vec2Array(a) => raw.Uniform2fv(loc, a.len() as GLsizei, a[0].as_ptr()),
+            Uniform::Vec3Array(a) => raw.Uniform3fv(loc, a.len() as GLsizei, a[0].as_ptr()),
+            Uniform::Vec4Array(a) => raw.Uniform4fv(loc, a.len() as GLsizei, a[0].as_ptr()),
+            //bools go up as ints like the single Bool
+            Uniform::BoolArray(a) => {
+               let ints: Vec<GLint> = a.iter().map(|b| *b as GLint).collect();
+               raw.Uniform1iv(loc, ints.len() as GLsizei, ints.as_ptr())
+            }
+            Uniform::Matrix2Array(a) => {
+               raw.UniformMatrix2fv(loc, a.len() as GLsizei, gl::FALSE, a[0].as_ptr())
+            }
+            Uniform::Matrix3Array(a) => {
+               raw.UniformMatrix3fv(loc, a.len() as GLsizei, gl::FALSE, a[0].as_ptr())
+            }
+            Uniform::Matrix4Array(a) => {
+               raw.UniformMatrix4fv(loc, a.len() as GLsizei, gl::FALSE, a[0].as_ptr())
+            }
+         }
       }
    }
 
    fn set_uni_i32(&self, id: u32, name: &str, int: i32) {
-      unsafe {
-         let loc = self.get_uni_location(id, name) as GLint;
-         self.gl.raw.Uniform1i(loc, int)
-      }
+      self.set_uni(id, name, Uniform::Int(int))
    }
    fn set_uni_m4f32(&self, id: u32, name: &str, matrix: Matrix4<f32>) {
-      unsafe {
-         let loc = self.get_uni_location(id, name) as GLint;
-         self
-            .gl
-            .raw
-            .UniformMatrix4fv(loc, 1, gl::FALSE, matrix.as_ptr())
-      }
+      self.set_uni(id, name, Uniform::Matrix4(matrix))
    }
 
    //BUFFERS
@@ -384,18 +485,31 @@ impl Renderer for GLRenderer {
    }
    fn set_attr_layout(&self, attr: &ATTRInfo, attr_id: u32, stride: usize, local_offset: usize) {
       let gl = &self.gl;
+      let size = attr.elem_count as GLint;
+      let typ = gl_match_attr_type(&attr.typ);
+      let offset = match local_offset {
+         0 => ptr::null(),
+         _ => local_offset as *const c_void,
+      };
       unsafe {
-         gl.raw.VertexAttribPointer(
-            attr_id,
-            attr.elem_count as GLint,
-            gl_match_attr_type(&attr.typ),
-            gl::FALSE,
-            stride as GLsizei,
-            match local_offset {
-               0 => ptr::null(),
-               _ => local_offset as *const c_void,
-            },
-         );
+         match attr.kind {
+            ATTRKind::Float => {
+               gl.raw
+                  .VertexAttribPointer(attr_id, size, typ, gl::FALSE, stride as GLsizei, offset)
+            }
+            ATTRKind::Normalized => {
+               gl.raw
+                  .VertexAttribPointer(attr_id, size, typ, gl::TRUE, stride as GLsizei, offset)
+            }
+            ATTRKind::Integer => {
+               gl.raw
+                  .VertexAttribIPointer(attr_id, size, typ, stride as GLsizei, offset)
+            }
+            ATTRKind::Double => {
+               gl.raw
+                  .VertexAttribLPointer(attr_id, size, typ, stride as GLsizei, offset)
+            }
+         }
          gl.raw.EnableVertexAttribArray(attr_id);
       }
    }
@@ -404,24 +518,46 @@ impl Renderer for GLRenderer {
          self.bind_buffer(v_id, b_id);
          self.gl.raw.BufferData(
             gl::ARRAY_BUFFER,
-            (buffer.len() * 4) as GLsizeiptr,
-            &buffer[0] as *const u8 as *const c_void,
+            buffer.len() as GLsizeiptr,
+            buffer.as_ptr() as *const c_void,
             gl::DYNAMIC_DRAW,
          );
       }
    }
 
-   fn fill_index_buffer(&self, id: u32, buffer: &Vec<u32>) {
+   fn fill_index_buffer(&self, id: u32, buffer: &Vec<u8>) {
       unsafe {
          self.bind_index_buffer(id);
          self.gl.raw.BufferData(
             gl::ELEMENT_ARRAY_BUFFER,
-            (buffer.len() * size_of::<GLint>()) as GLsizeiptr,
-            &buffer[0] as *const u32 as *const c_void,
+            buffer.len() as GLsizeiptr,
+            buffer.as_ptr() as *const c_void,
             gl::DYNAMIC_DRAW,
          );
       }
    }
+   fn update_buffer(&self, v_id: u32, b_id: u32, offset: usize, data: &[u8]) {
+      unsafe {
+         self.bind_buffer(v_id, b_id);
+         self.gl.raw.BufferSubData(
+            gl::ARRAY_BUFFER,
+            offset as GLintptr,
+            data.len() as GLsizeiptr,
+            data.as_ptr() as *const c_void,
+         );
+      }
+   }
+   fn update_index_buffer(&self, id: u32, offset: usize, data: &[u8]) {
+      unsafe {
+         self.bind_index_buffer(id);
+         self.gl.raw.BufferSubData(
+            gl::ELEMENT_ARRAY_BUFFER,
+            offset as GLintptr,
+            data.len() as GLsizeiptr,
+            data.as_ptr() as *const c_void,
+         );
+      }
+   }
 
    fn delete_buffer(&self, v_id: u32, b_id: u32) {
       let gl = &self.gl;
@@ -443,6 +579,49 @@ impl Renderer for GLRenderer {
          self.gl.raw.DeleteBuffers(1, &id);
       }
    }
+   fn create_vertex_buffer(&self) -> u32 {
+      let mut id: u32 = 0;
+      unsafe {
+         self.gl.raw.GenBuffers(1, &mut id);
+      }
+      id
+   }
+   fn delete_vertex_buffer(&self, id: u32) {
+      unsafe {
+         self.gl.raw.DeleteBuffers(1, &id);
+      }
+   }
+   fn create_instance_buffer(&self) -> u32 {
+      let mut id: u32 = 0;
+      unsafe {
+         self.gl.raw.GenBuffers(1, &mut id);
+      }
+      id
+   }
+   //same as set_attr_layout but advances once per instance
+   fn set_instance_layout(
+      &self,
+      attr: &ATTRInfo,
+      attr_id: u32,
+      stride: usize,
+      local_offset: usize,
+   ) {
+      self.set_attr_layout(attr, attr_id, stride, local_offset);
+      unsafe {
+         self.gl.raw.VertexAttribDivisor(attr_id, 1);
+      }
+   }
+   fn clear_instance_layout(&self, attr_id: u32) {
+      unsafe {
+         self.gl.raw.VertexAttribDivisor(attr_id, 0);
+         self.gl.raw.DisableVertexAttribArray(attr_id);
+      }
+   }
+   fn delete_instance_buffer(&self, id: u32) {
+      unsafe {
+         self.gl.raw.DeleteBuffers(1, &id);
+      }
+   }
 
    //DRAW
    fn clear(&self) {
@@ -453,13 +632,13 @@ impl Renderer for GLRenderer {
             .Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
       }
    }
-   fn draw_indexed(&self, draw_mode: &DrawMode, index_count: u32) {
+   fn draw_indexed(&self, draw_mode: &DrawMode, index_count: u32, format: IndexFormat) {
       let draw_mode = gl_match_draw_mode(draw_mode);
       unsafe {
          self.gl.raw.DrawElements(
             draw_mode,
             index_count as GLsizei,
-            gl::UNSIGNED_INT,
+            gl_match_index_format(&format),
             ptr::null(),
          );
       }
@@ -470,6 +649,181 @@ impl Renderer for GLRenderer {
          self.gl.raw.DrawArrays(draw_mode, 0, vert_count as GLsizei);
       }
    }
+   fn draw_indexed_instanced(
+      &self,
+      draw_mode: &DrawMode,
+      index_count: u32,
+      format: IndexFormat,
+      instances: u32,
+   ) {
+      let draw_mode = gl_match_draw_mode(draw_mode);
+      unsafe {
+         self.gl.raw.DrawElementsInstanced(
+            draw_mode,
+            index_count as GLsizei,
+            gl_match_index_format(&format),
+            ptr::null(),
+            instances as GLsizei,
+         );
+      }
+   }
+   fn draw_array_instanced(&self, draw_mode: &DrawMode, vert_count: u32, instances: u32) {
+      let draw_mode = gl_match_draw_mode(draw_mode);
+      unsafe {
+         self
+            .gl
+            .raw
+            .DrawArraysInstanced(draw_mode, 0, vert_count as GLsizei, instances as GLsizei);
+      }
+   }
+
+   //TARGETS
+   fn create_target(
+      &self,
+      size: Size2D,
+      colors: &[TexFormat],
+      depth: bool,
+   ) -> NEResult<(u32, Vec<u32>, u32)> {
+      let gl = &self.gl;
+      let (width, height) = (size.w as GLsizei, size.h as GLsizei);
+      let mut fbo = 0;
+      let mut color_ids = Vec::new();
+      let mut depth_id = 0;
+      unsafe {
+         gl.raw.GenFramebuffers(1, &mut fbo);
+         gl.raw.BindFramebuffer(gl::FRAMEBUFFER, fbo);
+
+         let mut attachments = Vec::new();
+         for (i, format) in colors.iter().enumerate() {
+            let mut id = 0;
+            gl.raw.GenTextures(1, &mut id);
+            self.bind_texture_at(id, 0);
+            gl_target_tex_params(gl);
+
+            let (base, sized) = gl_match_tex_format(format);
+            gl.raw.TexImage2D(
+               TEX,
+               0,
+               sized,
+               width,
+               height,
+               0,
+               base,
+               gl::UNSIGNED_BYTE,
+               ptr::null(),
+            );
+            let attachment = gl::COLOR_ATTACHMENT0 + i as GLenum;
+            gl.raw
+               .FramebufferTexture2D(gl::FRAMEBUFFER, attachment, TEX, id, 0);
+            attachments.push(attachment);
+            color_ids.push(id);
+         }
+         gl.raw
+            .DrawBuffers(attachments.len() as GLsizei, attachments.as_ptr());
+
+         if depth {
+            gl.raw.GenTextures(1, &mut depth_id);
+            self.bind_texture_at(depth_id, 0);
+            gl_target_tex_params(gl);
+            gl.raw.TexImage2D(
+               TEX,
+               0,
+               gl::DEPTH_COMPONENT24 as GLint,
+               width,
+               height,
+               0,
+               gl::DEPTH_COMPONENT,
+               gl::FLOAT,
+               ptr::null(),
+            );
+            gl.raw
+               .FramebufferTexture2D(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, TEX, depth_id, 0);
+         }
+
+         let status = gl.raw.CheckFramebufferStatus(gl::FRAMEBUFFER);
+         gl.raw.BindFramebuffer(gl::FRAMEBUFFER, 0);
+         if status != gl::FRAMEBUFFER_COMPLETE {
+            self.delete_target(fbo, &color_ids, depth_id);
+            return NEResult::ER(NEError::target_incomplete(format!(
+               "framebuffer status {status:#x}"
+            )));
+         }
+      }
+      NEResult::OK((fbo, color_ids, depth_id))
+   }
+   fn bind_target(&self, id: u32) {
+      unsafe { self.gl.raw.BindFramebuffer(gl::FRAMEBUFFER, id) }
+   }
+   fn unbind_target(&self) {
+      unsafe { self.gl.raw.BindFramebuffer(gl::FRAMEBUFFER, 0) }
+   }
+   fn delete_target(&self, id: u32, color_ids: &Vec<u32>, depth_id: u32) {
+      unsafe {
+         self.gl.raw.DeleteFramebuffers(1, &id);
+         for c in color_ids.iter() {
+            self.gl.raw.DeleteTextures(1, c);
+         }
+         if depth_id != 0 {
+            self.gl.raw.DeleteTextures(1, &depth_id);
+         }
+      }
+   }
+
+   //READBACK
+   fn read_color(&self, size: Size2D) -> Vec<u8> {
+      let mut pixels: Vec<u8> = vec![0; (size.w * size.h * 4) as usize];
+      unsafe {
+         self.gl.raw.PixelStorei(gl::PACK_ALIGNMENT, 1);
+         self.gl.raw.ReadPixels(
+            0,
+            0,
+            size.w as GLsizei,
+            size.h as GLsizei,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as *mut c_void,
+         );
+      }
+      gl_flip_rows(&pixels, size.w as usize * 4)
+   }
+   fn read_depth(&self, size: Size2D) -> Vec<f32> {
+      let mut depths: Vec<f32> = vec![0.0; (size.w * size.h) as usize];
+      unsafe {
+         self.gl.raw.PixelStorei(gl::PACK_ALIGNMENT, 1);
+         self.gl.raw.ReadPixels(
+            0,
+            0,
+            size.w as GLsizei,
+            size.h as GLsizei,
+            gl::DEPTH_COMPONENT,
+            gl::FLOAT,
+            depths.as_mut_ptr() as *mut c_void,
+         );
+      }
+      gl_flip_rows(&depths, size.w as usize)
+   }
+}
+
+//render targets are sampled without mipmaps
+unsafe fn gl_target_tex_params(gl: &gl::Context) {
+   let (min, max) = (gl::LINEAR as GLint, gl::LINEAR as GLint);
+   let wrap = gl::CLAMP_TO_EDGE as GLint;
+   gl.raw.TexParameteri(TEX, gl::TEXTURE_WRAP_S, wrap);
+   gl.raw.TexParameteri(TEX, gl::TEXTURE_WRAP_T, wrap);
+   gl.raw.TexParameteri(TEX, gl::TEXTURE_MIN_FILTER, min);
+   gl.raw.TexParameteri(TEX, gl::TEXTURE_MAG_FILTER, max);
+}
+
+//gl reads bottom row first, images are stored top row first
+fn gl_flip_rows<T: Copy>(data: &[T], row_len: usize) -> Vec<T> {
+   let mut flipped = Vec::with_capacity(data.len());
+   if row_len == 0 {
+      return flipped;
+   }
+   for row in data.chunks(row_len).rev() {
+      flipped.extend_from_slice(row);
+   }
+   flipped
 }
 
 fn gl_match_draw_mode(dm: &DrawMode) -> GLenum {
@@ -480,6 +834,13 @@ fn gl_match_draw_mode(dm: &DrawMode) -> GLenum {
       DrawMode::Strip => gl::TRIANGLE_STRIP,
    }
 }
+fn gl_match_index_format(format: &IndexFormat) -> GLenum {
+   match format {
+      IndexFormat::U8 => gl::UNSIGNED_BYTE,
+      IndexFormat::U16 => gl::UNSIGNED_SHORT,
+      IndexFormat::U32 => gl::UNSIGNED_INT,
+   }
+}
 fn gl_match_shader_type(t: &ShaderType) -> GLenum {
    match t {
       ShaderType::Vert => gl::VERTEX_SHADER,
@@ -1,5 +1,7 @@
 mod gl;
+mod sw;
 mod vk;
 
 pub use gl::*;
+pub use sw::*;
 pub use vk::*;
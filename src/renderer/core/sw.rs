@@ -0,0 +1,1407 @@
+use crate::asset::{ATTRInfo, TexFormat};
+use crate::renderer::{Renderer, ShaderType};
+use crate::NETexture;
+use crate::{
+   ansi, log_info, ATTRKind, ATTRType, Cull, DrawMode, NEOption, NEResult, NEShaderAsset,
+};
+use crate::{
+   BlendMode, IndexFormat, PolyMode, Size2D, TexFilter, TexWrap, Uniform, INSTANCE_COL_LOCATION,
+   INSTANCE_LOCATION, RGB,
+};
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, SquareMatrix, Vector3, Vector4};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+//same as GLRenderer::poly_mode
+const POINT_SIZE: f32 = 10.0;
+const LIGHT_DIR: [f32; 3] = [0.5, 1.0, 0.3];
+const SW_UNIFORMS: [&str; 5] = ["uCamView", "uCamProj", "uMeshTfm", "uLight", "tDif1"];
+
+struct SWAttr {
+   buf_id: u32,
+   info: ATTRInfo,
+   stride: usize,
+   offset: usize,
+}
+
+struct SWProgram {
+   locations: HashMap<String, u32>,
+   uniforms: HashMap<String, Uniform>,
+}
+
+struct SWTexture {
+   bytes: Vec<u8>,
+   size: Size2D,
+   typ: TexFormat,
+   filter: TexFilter,
+   wrap: TexWrap,
+}
+
+//while bound the target buffers are swapped into SWState, the screen buffers are parked here
+struct SWTarget {
+   size: Size2D,
+   color: Vec<[f32; 4]>,
+   depth: Vec<f32>,
+   color_ids: Vec<u32>,
+}
+
+#[derive(Copy, Clone)]
+struct SWVert {
+   clip: Vector4<f32>,
+   col: Vector4<f32>,
+   uvm: [f32; 2],
+   nrm: Vector3<f32>,
+}
+
+//screen space vertex, attributes are pre-divided by w for perspective correct interpolation
+#[derive(Copy, Clone)]
+struct SWFrag {
+   x: f32,
+   y: f32,
+   z: f32,
+   inv_w: f32,
+   col: Vector4<f32>,
+   uvm: [f32; 2],
+   nrm: Vector3<f32>,
+}
+
+struct SWState {
+   size: Size2D,
+   color: Vec<[f32; 4]>,
+   depth: Vec<f32>,
+   clear_color: RGB,
+
+   poly_mode: PolyMode,
+   depth_test: bool,
+   blend: bool,
+   blend_mode: BlendMode,
+   cull: bool,
+   front_face: Cull,
+   wire_width: f32,
+
+   bound_program: u32,
+   bound_vao: u32,
+   bound_buffer: u32,
+   bound_index_buffer: u32,
+   bound_textures: HashMap<u32, u32>,
+   bound_target: u32,
+
+   next_id: u32,
+   programs: HashMap<u32, SWProgram>,
+   vaos: HashMap<u32, HashMap<u32, SWAttr>>,
+   buffers: HashMap<u32, Vec<u8>>,
+   index_buffers: HashMap<u32, Vec<u8>>,
+   textures: HashMap<u32, SWTexture>,
+   targets: HashMap<u32, SWTarget>,
+}
+
+//cpu rasterizer, runs the fallback shader as fixed-function:
+//vertex color * texture at slot 0, lit by the default uLight when normals exist
+pub(crate) struct SWRenderer {
+   state: RefCell<SWState>,
+}
+
+pub(crate) fn sw_renderer_init(size: Size2D) -> SWRenderer {
+   let pixels = (size.w * size.h) as usize;
+   SWRenderer {
+      state: RefCell::new(SWState {
+         size,
+         color: vec![[0.0, 0.0, 0.0, 1.0]; pixels],
+         depth: vec![1.0; pixels],
+         clear_color: RGB(0.0, 0.0, 0.0),
+         poly_mode: PolyMode::Filled,
+         depth_test: false,
+         blend: false,
+         blend_mode: BlendMode::Alpha,
+         cull: false,
+         front_face: Cull::AntiClock,
+         wire_width: 1.0,
+         bound_program: 0,
+         bound_vao: 0,
+         bound_buffer: 0,
+         bound_index_buffer: 0,
+         bound_textures: HashMap::new(),
+         bound_target: 0,
+         next_id: 1,
+         programs: HashMap::new(),
+         vaos: HashMap::new(),
+         buffers: HashMap::new(),
+         index_buffers: HashMap::new(),
+         textures: HashMap::new(),
+         targets: HashMap::new(),
+      }),
+   }
+}
+
+impl SWState {
+   fn gen_id(&mut self) -> u32 {
+      let id = self.next_id;
+      self.next_id += 1;
+      id
+   }
+
+   fn new_program(&mut self) -> u32 {
+      let id = self.gen_id();
+      self.programs.insert(
+         id,
+         SWProgram {
+            locations: HashMap::new(),
+            uniforms: HashMap::new(),
+         },
+      );
+      id
+   }
+
+   fn swap_target(&mut self, id: u32) {
+      if let Some(t) = self.targets.get_mut(&id) {
+         std::mem::swap(&mut self.size, &mut t.size);
+         std::mem::swap(&mut self.color, &mut t.color);
+         std::mem::swap(&mut self.depth, &mut t.depth);
+      }
+   }
+
+   //copies the target color into its first attachment, rows bottom first like gl
+   fn resolve_target(&mut self, id: u32) {
+      let size = self.size;
+      let color_id = match self.targets.get(&id) {
+         None => return,
+         Some(t) => match t.color_ids.first() {
+            None => return,
+            Some(c) => *c,
+         },
+      };
+      let mut bytes = Vec::with_capacity(self.color.len() * 4);
+      for row in self.color.chunks(size.w.max(1) as usize).rev() {
+         for pixel in row {
+            for channel in pixel.iter() {
+               bytes.push((channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+            }
+         }
+      }
+      if let Some(tex) = self.textures.get_mut(&color_id) {
+         tex.bytes = bytes;
+         tex.size = size;
+      }
+   }
+
+   fn uni(&self, name: &str) -> Option<&Uniform> {
+      match self.programs.get(&self.bound_program) {
+         None => None,
+         Some(p) => p.uniforms.get(name),
+      }
+   }
+   fn uni_m4f32(&self, name: &str) -> Matrix4<f32> {
+      match self.uni(name) {
+         Some(Uniform::Matrix4(m)) => *m,
+         _ => Matrix4::identity(),
+      }
+   }
+
+   fn fetch(&self, attr: Option<&SWAttr>, index: usize, default: [f32; 4]) -> [f32; 4] {
+      let attr = match attr {
+         None => return default,
+         Some(a) => a,
+      };
+      let buffer = match self.buffers.get(&attr.buf_id) {
+         None => return default,
+         Some(b) => b,
+      };
+      let mut out = default;
+      let start = index * attr.stride + attr.offset;
+      let size = attr.info.byte_count;
+      for e in 0..attr.info.elem_count.min(4) {
+         let at = start + e * size;
+         if at + size > buffer.len() {
+            return default;
+         }
+         out[e] = read_elem(&attr.info, &buffer[at..at + size]);
+      }
+      out
+   }
+
+   fn bound_indices(&self, format: IndexFormat, count: u32) -> Option<Vec<usize>> {
+      let bytes = self.index_buffers.get(&self.bound_index_buffer)?;
+      let end = (count as usize * format.size()).min(bytes.len());
+      Some(
+         format
+            .unpack(&bytes[..end])
+            .iter()
+            .map(|i| *i as usize)
+            .collect(),
+      )
+   }
+
+   //instanced draws read iTfm and iCol from the fixed instance locations by instance index
+   fn shade_vert(
+      &self,
+      attrs: Option<&HashMap<u32, SWAttr>>,
+      index: usize,
+      instance: Option<usize>,
+   ) -> SWVert {
+      let attr = |loc: u32| attrs.and_then(|a| a.get(&loc));
+      let pos = self.fetch(attr(0), index, [0.0, 0.0, 0.0, 1.0]);
+      let mut col = self.fetch(attr(1), index, [1.0, 1.0, 1.0, 1.0]);
+      let uvm = self.fetch(attr(2), index, [0.0, 0.0, 0.0, 0.0]);
+      let nrm = self.fetch(attr(3), index, [0.0, 0.0, 0.0, 0.0]);
+
+      let mut tfm = self.uni_m4f32("uMeshTfm");
+      if let Some(i) = instance {
+         if attr(INSTANCE_LOCATION).is_some() {
+            let c = |n: u32| {
+               Vector4::from(self.fetch(attr(INSTANCE_LOCATION + n), i, [0.0, 0.0, 0.0, 0.0]))
+            };
+            tfm = tfm * Matrix4::from_cols(c(0), c(1), c(2), c(3));
+         }
+         let i_col = self.fetch(attr(INSTANCE_COL_LOCATION), i, [1.0, 1.0, 1.0, 1.0]);
+         for c in 0..3 {
+            col[c] *= i_col[c];
+         }
+      }
+      let mvp = self.uni_m4f32("uCamProj") * self.uni_m4f32("uCamView") * tfm;
+      let tfm3 = Matrix3::from_cols(tfm.x.truncate(), tfm.y.truncate(), tfm.z.truncate());
+      let nrm_matrix = tfm3.invert().unwrap_or(tfm3).transpose();
+
+      SWVert {
+         clip: mvp * Vector4::new(pos[0], pos[1], pos[2], 1.0),
+         col: Vector4::new(col[0], col[1], col[2], col[3]),
+         uvm: [uvm[0], uvm[1]],
+         nrm: nrm_matrix * Vector3::new(nrm[0], nrm[1], nrm[2]),
+      }
+   }
+
+   fn to_frag(&self, v: &SWVert) -> SWFrag {
+      let inv_w = 1.0 / v.clip.w;
+      let (w, h) = (self.size.w as f32, self.size.h as f32);
+      SWFrag {
+         x: (v.clip.x * inv_w + 1.0) * 0.5 * w,
+         y: (1.0 - v.clip.y * inv_w) * 0.5 * h,
+         z: (v.clip.z * inv_w) * 0.5 + 0.5,
+         inv_w,
+         col: v.col * inv_w,
+         uvm: [v.uvm[0] * inv_w, v.uvm[1] * inv_w],
+         nrm: v.nrm * inv_w,
+      }
+   }
+
+   fn shade_frag(&self, f: &SWFrag, has_nrm: bool) -> [f32; 4] {
+      let w = 1.0 / f.inv_w;
+      let mut col = f.col * w;
+      if let Some(tex_id) = self.bound_textures.get(&0) {
+         if let Some(tex) = self.textures.get(tex_id) {
+            let texel = tex.sample(f.uvm[0] * w, f.uvm[1] * w);
+            col = Vector4::new(
+               col.x * texel[0],
+               col.y * texel[1],
+               col.z * texel[2],
+               col.w * texel[3],
+            );
+         }
+      }
+      if has_nrm {
+         let nrm = f.nrm * w;
+         if nrm.magnitude2() > 0.0 {
+            let light_dir = match self.uni("uLight") {
+               Some(Uniform::Vec3(l)) => Vector3::from(*l).normalize(),
+               _ => Vector3::from(LIGHT_DIR).normalize(),
+            };
+            let light = 1.0 - nrm.normalize().dot(light_dir);
+            let shade = 1.0 - 0.45 * light;
+            col = Vector4::new(col.x * shade, col.y * shade, col.z * shade, col.w);
+         }
+      }
+      [
+         col.x.clamp(0.0, 1.0),
+         col.y.clamp(0.0, 1.0),
+         col.z.clamp(0.0, 1.0),
+         col.w.clamp(0.0, 1.0),
+      ]
+   }
+
+   fn plot(&mut self, x: i32, y: i32, z: f32, col: [f32; 4]) {
+      if x < 0 || y < 0 || x >= self.size.w as i32 || y >= self.size.h as i32 {
+         return;
+      }
+      if z < 0.0 || z > 1.0 {
+         return;
+      }
+      let i = (y as u32 * self.size.w + x as u32) as usize;
+      if self.depth_test {
+         if z >= self.depth[i] {
+            return;
+         }
+         self.depth[i] = z;
+      }
+      self.color[i] = match self.blend {
+         false => col,
+         true => blend(&self.blend_mode, col, self.color[i]),
+      };
+   }
+
+   fn is_culled(&self, a: &SWFrag, b: &SWFrag, c: &SWFrag) -> bool {
+      if !self.cull {
+         return false;
+      }
+      //screen y points down, so flip the sign to get gl's window space winding
+      let area = -((b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y));
+      let is_front = match self.front_face {
+         Cull::AntiClock => area > 0.0,
+         Cull::Clock => area < 0.0,
+      };
+      !is_front
+   }
+
+   fn fill_triangle(&mut self, a: &SWFrag, b: &SWFrag, c: &SWFrag, has_nrm: bool) {
+      let area = edge(a, b, c.x, c.y);
+      if area == 0.0 {
+         return;
+      }
+      let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as i32;
+      let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as i32;
+      let max_x = a.x.max(b.x).max(c.x).ceil().min(self.size.w as f32 - 1.0) as i32;
+      let max_y = a.y.max(b.y).max(c.y).ceil().min(self.size.h as f32 - 1.0) as i32;
+
+      for y in min_y..=max_y {
+         for x in min_x..=max_x {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let w0 = edge(b, c, px, py) / area;
+            let w1 = edge(c, a, px, py) / area;
+            let w2 = edge(a, b, px, py) / area;
+            if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+               continue;
+            }
+            let f = lerp3(a, b, c, w0, w1, w2);
+            let col = self.shade_frag(&f, has_nrm);
+            self.plot(x, y, f.z, col);
+         }
+      }
+   }
+
+   fn draw_line(&mut self, a: &SWFrag, b: &SWFrag, has_nrm: bool) {
+      let steps = (b.x - a.x).abs().max((b.y - a.y).abs()).ceil().max(1.0) as i32;
+      let half = (self.wire_width / 2.0).floor() as i32;
+      for s in 0..=steps {
+         let t = s as f32 / steps as f32;
+         let f = lerp2(a, b, t);
+         let col = self.shade_frag(&f, has_nrm);
+         for oy in -half..=half {
+            for ox in -half..=half {
+               self.plot(f.x as i32 + ox, f.y as i32 + oy, f.z, col);
+            }
+         }
+      }
+   }
+
+   fn draw_point(&mut self, p: &SWFrag, has_nrm: bool) {
+      let col = self.shade_frag(p, has_nrm);
+      let half = (POINT_SIZE / 2.0) as i32;
+      for oy in -half..half {
+         for ox in -half..half {
+            self.plot(p.x as i32 + ox, p.y as i32 + oy, p.z, col);
+         }
+      }
+   }
+
+   fn draw_tri(&mut self, verts: [SWVert; 3], has_nrm: bool) {
+      let poly = clip_near(&verts);
+      if poly.len() < 3 {
+         return;
+      }
+      let frags: Vec<SWFrag> = poly.iter().map(|v| self.to_frag(v)).collect();
+      for i in 1..frags.len() - 1 {
+         let (a, b, c) = (&frags[0], &frags[i], &frags[i + 1]);
+         if self.is_culled(a, b, c) {
+            continue;
+         }
+         match self.poly_mode {
+            PolyMode::Filled => self.fill_triangle(a, b, c, has_nrm),
+            PolyMode::WireFrame => {
+               self.draw_line(a, b, has_nrm);
+               self.draw_line(b, c, has_nrm);
+               self.draw_line(c, a, has_nrm);
+            }
+            PolyMode::Points => {
+               self.draw_point(a, has_nrm);
+               self.draw_point(b, has_nrm);
+               self.draw_point(c, has_nrm);
+            }
+         }
+      }
+   }
+
+   fn draw(&mut self, draw_mode: &DrawMode, indices: &Vec<usize>, instance: Option<usize>) {
+      let vaos = std::mem::take(&mut self.vaos);
+      let attrs = vaos.get(&self.bound_vao);
+      let has_nrm = attrs.map(|a| a.contains_key(&3)).unwrap_or(false);
+      let verts: Vec<SWVert> = indices
+         .iter()
+         .map(|i| self.shade_vert(attrs, *i, instance))
+         .collect();
+      self.vaos = vaos;
+
+      match draw_mode {
+         DrawMode::Triangles => {
+            for tri in verts.chunks_exact(3) {
+               self.draw_tri([tri[0], tri[1], tri[2]], has_nrm);
+            }
+         }
+         DrawMode::Strip => {
+            for i in 0..verts.len().saturating_sub(2) {
+               let tri = match i % 2 {
+                  0 => [verts[i], verts[i + 1], verts[i + 2]],
+                  _ => [verts[i + 1], verts[i], verts[i + 2]],
+               };
+               self.draw_tri(tri, has_nrm);
+            }
+         }
+         DrawMode::Lines => {
+            for line in verts.chunks_exact(2) {
+               if line[0].clip.w <= 0.0 || line[1].clip.w <= 0.0 {
+                  continue;
+               }
+               let (a, b) = (self.to_frag(&line[0]), self.to_frag(&line[1]));
+               self.draw_line(&a, &b, has_nrm);
+            }
+         }
+         DrawMode::Points => {
+            for vert in verts.iter() {
+               if vert.clip.w <= 0.0 {
+                  continue;
+               }
+               let p = self.to_frag(vert);
+               self.draw_point(&p, has_nrm);
+            }
+         }
+      }
+   }
+}
+
+impl SWTexture {
+   fn texel(&self, x: i32, y: i32) -> [f32; 4] {
+      let (w, h) = (self.size.w as i32, self.size.h as i32);
+      let (x, y) = match self.wrap {
+         TexWrap::Repeat => (x.rem_euclid(w), y.rem_euclid(h)),
+         TexWrap::Extend => (x.clamp(0, w - 1), y.clamp(0, h - 1)),
+         TexWrap::Clip => {
+            if x < 0 || y < 0 || x >= w || y >= h {
+               return [0.0, 0.0, 0.0, 0.0];
+            }
+            (x, y)
+         }
+      };
+      let channels = self.typ.elem_count() as usize;
+      let depth = match self.typ.bit_depth() {
+         16 => 2,
+         _ => 1,
+      };
+      let at = ((y * w + x) as usize) * channels * depth;
+      let mut out = [0.0, 0.0, 0.0, 1.0];
+      for c in 0..channels {
+         //16 bit pngs are big endian, the high byte is enough here
+         out[c] = match self.bytes.get(at + c * depth) {
+            None => 0.0,
+            Some(b) => *b as f32 / 255.0,
+         }
+      }
+      out
+   }
+
+   fn sample(&self, u: f32, v: f32) -> [f32; 4] {
+      if self.size.w == 0 || self.size.h == 0 {
+         return [1.0, 1.0, 1.0, 1.0];
+      }
+      let (x, y) = (u * self.size.w as f32, v * self.size.h as f32);
+      match self.filter {
+         TexFilter::Closest => self.texel(x.floor() as i32, y.floor() as i32),
+         TexFilter::Linear => {
+            let (x, y) = (x - 0.5, y - 0.5);
+            let (x0, y0) = (x.floor() as i32, y.floor() as i32);
+            let (tx, ty) = (x - x.floor(), y - y.floor());
+            let (a, b) = (self.texel(x0, y0), self.texel(x0 + 1, y0));
+            let (c, d) = (self.texel(x0, y0 + 1), self.texel(x0 + 1, y0 + 1));
+            let mut out = [0.0; 4];
+            for i in 0..4 {
+               let top = a[i] + (b[i] - a[i]) * tx;
+               let bottom = c[i] + (d[i] - c[i]) * tx;
+               out[i] = top + (bottom - top) * ty;
+            }
+            out
+         }
+      }
+   }
+}
+
+impl SWRenderer {
+   pub(crate) fn size(&self) -> Size2D {
+      self.state.borrow().size
+   }
+}
+
+impl Renderer for SWRenderer {
+   fn log_info(&self) {
+      let size = self.size();
+      log_info!("BACKEND");
+      log_info!("> api: Software [cpu rasterizer]");
+      log_info!("> shaders: fixed-function fallback");
+      log_info!("> framebuffer: {}x{}\n", size.w, size.h);
+   }
+
+   //STATE
+   fn set_clear(&self, color: RGB) {
+      self.state.borrow_mut().clear_color = color;
+   }
+   fn resize(&self, size: Size2D) {
+      let mut state = self.state.borrow_mut();
+      if state.size.w == size.w && state.size.h == size.h {
+         return;
+      }
+      let pixels = (size.w * size.h) as usize;
+      state.size = size;
+      state.color = vec![[0.0, 0.0, 0.0, 1.0]; pixels];
+      state.depth = vec![1.0; pixels];
+   }
+   fn poly_mode(&self, mode: PolyMode) {
+      self.state.borrow_mut().poly_mode = mode;
+   }
+   fn enable_msaa(&self, _enable: bool) {}
+   fn enable_depth(&self, enable: bool) {
+      self.state.borrow_mut().depth_test = enable;
+   }
+   fn enable_cull(&self, enable: bool) {
+      self.state.borrow_mut().cull = enable;
+   }
+   fn enable_blend(&self, enable: bool) {
+      self.state.borrow_mut().blend = enable;
+   }
+   fn set_blend_mode(&self, mode: BlendMode) {
+      self.state.borrow_mut().blend_mode = mode;
+   }
+   fn set_cull_face(&self, face: Cull) {
+      self.state.borrow_mut().front_face = face;
+   }
+   fn set_wire_width(&self, width: f32) {
+      self.state.borrow_mut().wire_width = width;
+   }
+
+   fn bind_program(&self, prog_id: u32) {
+      self.state.borrow_mut().bound_program = prog_id;
+   }
+   fn unbind_program(&self) {
+      self.state.borrow_mut().bound_program = 0;
+   }
+
+   fn bind_texture_at(&self, tex_id: u32, slot: u32) {
+      self.state.borrow_mut().bound_textures.insert(slot, tex_id);
+   }
+   fn unbind_texture(&self) {
+      self.state.borrow_mut().bound_textures.clear();
+   }
+
+   fn bind_buffer(&self, v_id: u32, b_id: u32) {
+      let mut state = self.state.borrow_mut();
+      state.bound_vao = v_id;
+      state.bound_buffer = b_id;
+   }
+   fn unbind_buffer(&self) {
+      let mut state = self.state.borrow_mut();
+      state.bound_vao = 0;
+      state.bound_buffer = 0;
+   }
+
+   fn bind_index_buffer(&self, id: u32) {
+      self.state.borrow_mut().bound_index_buffer = id;
+   }
+   fn unbind_index_buffer(&self) {
+      self.state.borrow_mut().bound_index_buffer = 0;
+   }
+
+   //SHADERS
+   fn create_spv_shader(&self, _spv: &Vec<u8>, _typ: ShaderType) -> NEResult<u32> {
+      NEResult::OK(self.state.borrow_mut().gen_id())
+   }
+   fn create_src_shader(&self, _src: &str, _typ: ShaderType) -> NEResult<u32> {
+      NEResult::OK(self.state.borrow_mut().gen_id())
+   }
+   fn delete_shader(&self, _id: u32) {}
+
+   fn create_spv_program(&self, _nshdr: &NEShaderAsset) -> NEResult<u32> {
+      NEResult::OK(self.state.borrow_mut().new_program())
+   }
+   fn create_src_program(&self, _vert: &str, _frag: &str) -> NEResult<u32> {
+      NEResult::OK(self.state.borrow_mut().new_program())
+   }
+   fn delete_program(&self, id: u32) {
+      self.state.borrow_mut().programs.remove(&id);
+   }
+
+   fn create_texture(&self, tex: &NETexture) -> u32 {
+      let mut state = self.state.borrow_mut();
+      let id = state.gen_id();
+      state.textures.insert(
+         id,
+         SWTexture {
+            bytes: tex.bytes.clone(),
+            size: tex.size,
+            typ: tex.typ,
+            filter: tex.filter,
+            wrap: tex.wrap,
+         },
+      );
+      state.bound_textures.insert(0, id);
+      id
+   }
+   fn delete_texture(&self, id: u32) {
+      let mut state = self.state.borrow_mut();
+      state.textures.remove(&id);
+      state.bound_textures.retain(|_, t| *t != id);
+   }
+
+   fn get_uni_location(&self, id: u32, name: &str) -> NEOption<i32> {
+      let mut state = self.state.borrow_mut();
+      match state.programs.get_mut(&id) {
+         None => NEOption::Empty,
+         Some(p) => {
+            let next = p.locations.len() as u32;
+            NEOption::Exists(*p.locations.entry(name.to_string()).or_insert(next) as i32)
+         }
+      }
+   }
+   //there is no source to reflect, every program exposes the fixed-function uniforms
+   fn active_uniforms(&self, _id: u32) -> Vec<String> {
+      SW_UNIFORMS.iter().map(|u| u.to_string()).collect()
+   }
+   fn set_uni(&self, id: u32, name: &str, uniform: Uniform) {
+      self.get_uni_location(id, name);
+      if let Some(p) = self.state.borrow_mut().programs.get_mut(&id) {
+         p.uniforms.insert(name.to_string(), uniform);
+      }
+   }
+   fn set_uni_at(&self, loc: i32, uniform: Uniform) {
+      if uniform.is_empty_array() {
+         return;
+      }
+      let mut state = self.state.borrow_mut();
+      let id = state.bound_program;
+      if let Some(p) = state.programs.get_mut(&id) {
+         let name = p.locations.iter().find(|(_, l)| **l as i32 == loc);
+         if let Some((name, _)) = name {
+            let name = name.clone();
+            p.uniforms.insert(name, uniform);
+         }
+      }
+   }
+
+   fn set_uni_i32(&self, id: u32, name: &str, int: i32) {
+      self.set_uni(id, name, Uniform::Int(int))
+   }
+   fn set_uni_m4f32(&self, id: u32, name: &str, matrix: Matrix4<f32>) {
+      self.set_uni(id, name, Uniform::Matrix4(matrix))
+   }
+
+   //BUFFERS
+   fn create_buffer(&self) -> (u32, u32) {
+      let mut state = self.state.borrow_mut();
+      let (v_id, b_id) = (state.gen_id(), state.gen_id());
+      state.vaos.insert(v_id, HashMap::new());
+      state.buffers.insert(b_id, Vec::new());
+      (v_id, b_id)
+   }
+   fn set_attr_layout(&self, attr: &ATTRInfo, attr_id: u32, stride: usize, local_offset: usize) {
+      let mut state = self.state.borrow_mut();
+      let (v_id, b_id) = (state.bound_vao, state.bound_buffer);
+      if let Some(vao) = state.vaos.get_mut(&v_id) {
+         vao.insert(
+            attr_id,
+            SWAttr {
+               buf_id: b_id,
+               info: attr.clone(),
+               stride,
+               offset: local_offset,
+            },
+         );
+      }
+   }
+   fn fill_buffer(&self, v_id: u32, b_id: u32, buffer: &Vec<u8>) {
+      self.bind_buffer(v_id, b_id);
+      self.state.borrow_mut().buffers.insert(b_id, buffer.clone());
+   }
+
+   fn fill_index_buffer(&self, id: u32, buffer: &Vec<u8>) {
+      self.bind_index_buffer(id);
+      let mut state = self.state.borrow_mut();
+      state.index_buffers.insert(id, buffer.clone());
+   }
+   fn update_buffer(&self, v_id: u32, b_id: u32, offset: usize, data: &[u8]) {
+      self.bind_buffer(v_id, b_id);
+      if let Some(buffer) = self.state.borrow_mut().buffers.get_mut(&b_id) {
+         let end = (offset + data.len()).min(buffer.len());
+         if offset < end {
+            buffer[offset..end].copy_from_slice(&data[..end - offset]);
+         }
+      }
+   }
+   fn update_index_buffer(&self, id: u32, offset: usize, data: &[u8]) {
+      self.bind_index_buffer(id);
+      if let Some(buffer) = self.state.borrow_mut().index_buffers.get_mut(&id) {
+         let end = (offset + data.len()).min(buffer.len());
+         if offset < end {
+            buffer[offset..end].copy_from_slice(&data[..end - offset]);
+         }
+      }
+   }
+
+   fn delete_buffer(&self, v_id: u32, b_id: u32) {
+      let mut state = self.state.borrow_mut();
+      state.vaos.remove(&v_id);
+      state.buffers.remove(&b_id);
+   }
+
+   fn create_index_buffer(&self) -> u32 {
+      let mut state = self.state.borrow_mut();
+      let id = state.gen_id();
+      state.index_buffers.insert(id, Vec::new());
+      id
+   }
+   fn delete_index_buffer(&self, id: u32) {
+      self.state.borrow_mut().index_buffers.remove(&id);
+   }
+   fn create_vertex_buffer(&self) -> u32 {
+      let mut state = self.state.borrow_mut();
+      let id = state.gen_id();
+      state.buffers.insert(id, Vec::new());
+      id
+   }
+   fn delete_vertex_buffer(&self, id: u32) {
+      self.state.borrow_mut().buffers.remove(&id);
+   }
+   fn create_instance_buffer(&self) -> u32 {
+      let mut state = self.state.borrow_mut();
+      let id = state.gen_id();
+      state.buffers.insert(id, Vec::new());
+      id
+   }
+   //instance data is fetched by instance index in shade_vert, the layout itself is the same
+   fn set_instance_layout(
+      &self,
+      attr: &ATTRInfo,
+      attr_id: u32,
+      stride: usize,
+      local_offset: usize,
+   ) {
+      self.set_attr_layout(attr, attr_id, stride, local_offset);
+   }
+   fn clear_instance_layout(&self, attr_id: u32) {
+      let mut state = self.state.borrow_mut();
+      let v_id = state.bound_vao;
+      if let Some(vao) = state.vaos.get_mut(&v_id) {
+         vao.remove(&attr_id);
+      }
+   }
+   fn delete_instance_buffer(&self, id: u32) {
+      self.state.borrow_mut().buffers.remove(&id);
+   }
+
+   //DRAW
+   fn clear(&self) {
+      let mut state = self.state.borrow_mut();
+      let c = state.clear_color;
+      state.color.fill([c.0, c.1, c.2, 1.0]);
+      state.depth.fill(1.0);
+   }
+   fn draw_indexed(&self, draw_mode: &DrawMode, index_count: u32, format: IndexFormat) {
+      let mut state = self.state.borrow_mut();
+      let indices = match state.bound_indices(format, index_count) {
+         None => return,
+         Some(indices) => indices,
+      };
+      state.draw(draw_mode, &indices, None);
+   }
+   fn draw_array(&self, draw_mode: &DrawMode, vert_count: u32) {
+      let indices = (0..vert_count as usize).collect();
+      self.state.borrow_mut().draw(draw_mode, &indices, None);
+   }
+   fn draw_indexed_instanced(
+      &self,
+      draw_mode: &DrawMode,
+      index_count: u32,
+      format: IndexFormat,
+      instances: u32,
+   ) {
+      let mut state = self.state.borrow_mut();
+      let indices = match state.bound_indices(format, index_count) {
+         None => return,
+         Some(indices) => indices,
+      };
+      for i in 0..instances as usize {
+         state.draw(draw_mode, &indices, Some(i));
+      }
+   }
+   fn draw_array_instanced(&self, draw_mode: &DrawMode, vert_count: u32, instances: u32) {
+      let indices = (0..vert_count as usize).collect();
+      let mut state = self.state.borrow_mut();
+      for i in 0..instances as usize {
+         state.draw(draw_mode, &indices, Some(i));
+      }
+   }
+
+   //READBACK
+   fn create_target(
+      &self,
+      size: Size2D,
+      colors: &[TexFormat],
+      depth: bool,
+   ) -> NEResult<(u32, Vec<u32>, u32)> {
+      let mut state = self.state.borrow_mut();
+      let pixels = (size.w * size.h) as usize;
+      let mut color_ids = Vec::new();
+      for _ in colors.iter() {
+         let id = state.gen_id();
+         state.textures.insert(
+            id,
+            SWTexture {
+               bytes: vec![0; pixels * 4],
+               size,
+               typ: TexFormat::RGBA(8),
+               filter: TexFilter::Linear,
+               wrap: TexWrap::Extend,
+            },
+         );
+         color_ids.push(id);
+      }
+      //depth always exists in software, the id only marks that it was asked for
+      let depth_id = match depth {
+         true => state.gen_id(),
+         false => 0,
+      };
+      let id = state.gen_id();
+      state.targets.insert(
+         id,
+         SWTarget {
+            size,
+            color: vec![[0.0, 0.0, 0.0, 1.0]; pixels],
+            depth: vec![1.0; pixels],
+            color_ids: color_ids.clone(),
+         },
+      );
+      NEResult::OK((id, color_ids, depth_id))
+   }
+   fn bind_target(&self, id: u32) {
+      self.unbind_target();
+      let mut state = self.state.borrow_mut();
+      if state.targets.contains_key(&id) {
+         state.swap_target(id);
+         state.bound_target = id;
+      }
+   }
+   fn unbind_target(&self) {
+      let mut state = self.state.borrow_mut();
+      let id = state.bound_target;
+      if id == 0 {
+         return;
+      }
+      state.resolve_target(id);
+      state.swap_target(id);
+      state.bound_target = 0;
+   }
+   fn delete_target(&self, id: u32, color_ids: &Vec<u32>, _depth_id: u32) {
+      if self.state.borrow().bound_target == id {
+         self.unbind_target();
+      }
+      let mut state = self.state.borrow_mut();
+      state.targets.remove(&id);
+      for c in color_ids.iter() {
+         state.textures.remove(c);
+         state.bound_textures.retain(|_, t| t != c);
+      }
+   }
+
+   fn read_color(&self, _size: Size2D) -> Vec<u8> {
+      let state = self.state.borrow();
+      let mut bytes = Vec::with_capacity(state.color.len() * 4);
+      for pixel in state.color.iter() {
+         for channel in pixel.iter() {
+            bytes.push((channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+         }
+      }
+      bytes
+   }
+   fn read_depth(&self, _size: Size2D) -> Vec<f32> {
+      self.state.borrow().depth.clone()
+   }
+}
+
+//same factors as GLRenderer::set_blend_mode
+fn blend(mode: &BlendMode, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+   let a = src[3];
+   let mut out = [0.0; 4];
+   for c in 0..3 {
+      out[c] = match mode {
+         BlendMode::Alpha => src[c] * a + dst[c] * (1.0 - a),
+         BlendMode::Additive => src[c] * a + dst[c],
+         BlendMode::Multiply => src[c] * dst[c],
+         BlendMode::Premultiplied => src[c] + dst[c] * (1.0 - a),
+      }
+      .clamp(0.0, 1.0);
+   }
+   out[3] = match mode {
+      BlendMode::Multiply => dst[3],
+      _ => (a + dst[3] * (1.0 - a)).clamp(0.0, 1.0),
+   };
+   out
+}
+
+//normalized ints map to 0..1 (unsigned) or -1..1 (signed) like gl does, everything else converts as is
+fn read_elem(info: &ATTRInfo, bytes: &[u8]) -> f32 {
+   let value = read_raw(&info.typ, bytes);
+   if info.kind != ATTRKind::Normalized {
+      return value;
+   }
+   match info.typ {
+      ATTRType::U8 => value / u8::MAX as f32,
+      ATTRType::I8 => (value / i8::MAX as f32).max(-1.0),
+      ATTRType::U16 => value / u16::MAX as f32,
+      ATTRType::I16 => (value / i16::MAX as f32).max(-1.0),
+      ATTRType::U32 => (value as f64 / u32::MAX as f64) as f32,
+      ATTRType::I32 => (value as f64 / i32::MAX as f64).max(-1.0) as f32,
+      ATTRType::F32 | ATTRType::F64 => value,
+   }
+}
+
+fn read_raw(typ: &ATTRType, bytes: &[u8]) -> f32 {
+   match typ {
+      ATTRType::U8 => bytes[0] as f32,
+      ATTRType::I8 => bytes[0] as i8 as f32,
+      ATTRType::U16 => u16::from_ne_bytes([bytes[0], bytes[1]]) as f32,
+      ATTRType::I16 => i16::from_ne_bytes([bytes[0], bytes[1]]) as f32,
+      ATTRType::U32 => u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32,
+      ATTRType::I32 => i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32,
+      ATTRType::F32 => f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+      ATTRType::F64 => {
+         let mut b = [0; 8];
+         b.copy_from_slice(&bytes[0..8]);
+         f64::from_ne_bytes(b) as f32
+      }
+   }
+}
+
+fn edge(a: &SWFrag, b: &SWFrag, x: f32, y: f32) -> f32 {
+   (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+fn lerp2(a: &SWFrag, b: &SWFrag, t: f32) -> SWFrag {
+   lerp3(a, b, b, 1.0 - t, t, 0.0)
+}
+
+fn lerp3(a: &SWFrag, b: &SWFrag, c: &SWFrag, w0: f32, w1: f32, w2: f32) -> SWFrag {
+   SWFrag {
+      x: a.x * w0 + b.x * w1 + c.x * w2,
+      y: a.y * w0 + b.y * w1 + c.y * w2,
+      z: a.z * w0 + b.z * w1 + c.z * w2,
+      inv_w: a.inv_w * w0 + b.inv_w * w1 + c.inv_w * w2,
+      col: a.col * w0 + b.col * w1 + c.col * w2,
+      uvm: [
+         a.uvm[0] * w0 + b.uvm[0] * w1 + c.uvm[0] * w2,
+         a.uvm[1] * w0 + b.uvm[1] * w1 + c.uvm[1] * w2,
+      ],
+      nrm: a.nrm * w0 + b.nrm * w1 + c.nrm * w2,
+   }
+}
+
+fn lerp_vert(a: &SWVert, b: &SWVert, t: f32) -> SWVert {
+   SWVert {
+      clip: a.clip + (b.clip - a.clip) * t,
+      col: a.col + (b.col - a.col) * t,
+      uvm: [
+         a.uvm[0] + (b.uvm[0] - a.uvm[0]) * t,
+         a.uvm[1] + (b.uvm[1] - a.uvm[1]) * t,
+      ],
+      nrm: a.nrm + (b.nrm - a.nrm) * t,
+   }
+}
+
+//clips against the near plane (z > -w), returns a convex polygon
+fn clip_near(tri: &[SWVert; 3]) -> Vec<SWVert> {
+   let dist = |v: &SWVert| v.clip.z + v.clip.w;
+   let mut poly = Vec::new();
+   for i in 0..3 {
+      let (cur, next) = (&tri[i], &tri[(i + 1) % 3]);
+      let (d0, d1) = (dist(cur), dist(next));
+      if d0 >= 0.0 {
+         poly.push(*cur);
+      }
+      if (d0 >= 0.0) != (d1 >= 0.0) {
+         poly.push(lerp_vert(cur, next, d0 / (d0 - d1)));
+      }
+   }
+   poly
+}
+
+#[cfg(test)]
+mod tests {
+   use crate::*;
+   use cgmath::{Matrix2, Matrix3, SquareMatrix};
+
+   fn renderer(size: u32) -> (NERenderer, NECamera) {
+      let size = Size2D::from(size, size);
+      let core = super::sw_renderer_init(size);
+      let mut cam = NECamera::from(size, CamProj::Persp);
+      cam.pre_update();
+      let renderer = NERenderer::from(
+         Box::new(core),
+         RenderAPI::Software,
+         size,
+         cam.view_matrix,
+         cam.proj_matrix,
+      );
+      (renderer, cam)
+   }
+
+   fn triangle(z: f32, color: [f32; 3]) -> NEMeshAsset {
+      NEMeshAsset::from_attrs(
+         PosATTR::from(vec![[-1.0, -1.0, z], [1.0, -1.0, z], [0.0, 1.0, z]]),
+         ColATTR::from(vec![color; 3]),
+         UVMATTR::empty(),
+         NrmATTR::empty(),
+         Indices::from(vec![0, 1, 2]),
+      )
+   }
+
+   fn pixel(r: &NERenderer, x: u32, y: u32) -> [u8; 4] {
+      let frame = r.read_color();
+      let at = ((y * frame.size.w + x) * 4) as usize;
+      [
+         frame.bytes[at],
+         frame.bytes[at + 1],
+         frame.bytes[at + 2],
+         frame.bytes[at + 3],
+      ]
+   }
+
+   #[test]
+   fn fills_triangle_over_clear_color() {
+      let (mut r, cam) = renderer(32);
+      r.set_bg_color(RGB(0.0, 0.0, 1.0));
+      r.pre_update(&cam);
+      let mut mesh = r.mesh(triangle(0.0, [1.0, 0.0, 0.0]));
+      r.render(&mut mesh);
+      assert_eq!(pixel(&r, 16, 16), [255, 0, 0, 255]);
+      assert_eq!(pixel(&r, 0, 0), [0, 0, 255, 255]);
+   }
+
+   #[test]
+   fn culls_back_faces() {
+      let (mut r, cam) = renderer(32);
+      r.set_bg_color(RGB(0.0, 0.0, 1.0));
+      r.set_cull_face(Cull::Clock);
+      r.pre_update(&cam);
+      let mut mesh = r.mesh(triangle(0.0, [1.0, 0.0, 0.0]));
+      r.render(&mut mesh);
+      assert_eq!(pixel(&r, 16, 16), [0, 0, 255, 255]);
+   }
+
+   #[test]
+   fn depth_test_keeps_nearest() {
+      let (mut r, cam) = renderer(32);
+      r.pre_update(&cam);
+      let mut near = r.mesh(triangle(0.5, [0.0, 1.0, 0.0]));
+      let mut far = r.mesh(triangle(-0.5, [1.0, 0.0, 0.0]));
+      r.render(&mut near);
+      r.render(&mut far);
+      assert_eq!(pixel(&r, 16, 16), [0, 255, 0, 255]);
+   }
+
+   #[test]
+   fn queued_draws_stay_in_their_target() {
+      let (mut r, cam) = renderer(32);
+      r.set_bg_color(RGB(0.0, 0.0, 1.0));
+      r.set_batching(true);
+      r.pre_update(&cam);
+      let target = r.render_target(Size2D::from(32, 32), &[TexFormat::RGBA(8)], true);
+      let target = target.unpack();
+      let mut mesh = r.mesh(triangle(0.0, [1.0, 0.0, 0.0]));
+      r.bind_target(&target);
+      r.render(&mut mesh);
+      assert_eq!(pixel(&r, 16, 16), [0, 0, 255, 255]);
+      r.unbind_target();
+      r.post_update(0.0);
+      assert_eq!(pixel(&r, 16, 16), [0, 0, 255, 255]);
+      r.delete_target(target);
+   }
+
+   #[test]
+   fn deleted_mesh_still_draws_when_queued() {
+      let (mut r, cam) = renderer(32);
+      r.set_bg_color(RGB(0.0, 0.0, 1.0));
+      r.set_batching(true);
+      r.pre_update(&cam);
+      let mut mesh = r.mesh(triangle(0.0, [1.0, 0.0, 0.0]));
+      r.render(&mut mesh);
+      r.delete_mesh(mesh);
+      r.post_update(0.0);
+      assert_eq!(pixel(&r, 16, 16), [255, 0, 0, 255]);
+   }
+
+   #[test]
+   fn live_targets_are_reported_as_leaks() {
+      let (mut r, cam) = renderer(8);
+      r.pre_update(&cam);
+      let kept = r.render_target(Size2D::from(8, 8), &[TexFormat::RGBA(8)], false);
+      let deleted = r.render_target(Size2D::from(8, 8), &[TexFormat::RGBA(8)], false);
+      r.delete_target(deleted.unpack());
+      r.post_update(0.0);
+      assert_eq!(r.end(), 1);
+      drop(kept);
+   }
+
+   #[test]
+   fn interleaves_every_attr_type() {
+      let (r, _) = renderer(8);
+      let mut asset = NEMeshAsset::from_attrs(
+         PosATTR::from(vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]),
+         ColATTR::empty(),
+         UVMATTR::empty(),
+         NrmATTR::empty(),
+         Indices::empty(),
+      );
+      let customs = [
+         CustomATTR::from(vec![[1u8, 2, 3, 4], [5, 6, 7, 8], [9, 10, 11, 12]]),
+         CustomATTR::from(vec![[-1i8, 2], [-3, 4], [-5, 6]]),
+         CustomATTR::from(vec![[1u16, 2, 3], [4, 5, 6], [7, 8, 9]]),
+         CustomATTR::from(vec![-1i16, -2, -3]),
+         CustomATTR::from(vec![[1u32, 2], [3, 4], [5, 6]]),
+         CustomATTR::from(vec![-1i32, -2, -3]),
+         CustomATTR::from(vec![[0.5f32, 1.5], [2.5, 3.5], [4.5, 5.5]]),
+         CustomATTR::from(vec![0.25f64, 0.5, 0.75]),
+      ];
+      let mut expected = vec![(
+         0,
+         [[0.0f32, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+            .iter()
+            .map(|p| p.u8ify())
+            .collect::<Vec<_>>(),
+      )];
+      for cus in customs {
+         let size = cus.data().len() / 3;
+         let verts = cus.data().chunks(size).map(|c| c.to_vec()).collect();
+         expected.push((size, verts));
+         asset.attach_custom_attr(cus);
+      }
+      let mesh = r.dynamic_mesh(asset);
+      assert!(mesh
+         .attrs
+         .iter()
+         .all(|(_, info, _)| info.kind == ATTRKind::Float));
+
+      let mut attrs = mesh.attrs.clone();
+      attrs.sort_by_key(|(_, _, offset)| *offset);
+      let offsets: Vec<usize> = attrs.iter().map(|(_, _, offset)| *offset).collect();
+      assert_eq!(offsets, [0, 12, 16, 18, 24, 26, 34, 38, 46]);
+      let stride = mesh.streams[0].stride;
+      assert_eq!(stride, 54);
+
+      let data = mesh.vertex_data().unwrap();
+      assert_eq!(data.len(), stride * 3);
+      for ((_, verts), offset) in expected.iter().zip(offsets.iter()) {
+         for (v, bytes) in verts.iter().enumerate() {
+            let at = v * stride + offset;
+            assert_eq!(&data[at..at + bytes.len()], &bytes[..]);
+         }
+      }
+   }
+
+   #[test]
+   fn kinds_are_opt_in_and_checked() {
+      let (r, _) = renderer(8);
+      let mut asset = triangle(0.0, [1.0, 0.0, 0.0]);
+      let mut ids = CustomATTR::from(vec![[3u16, 40000], [4, 5], [6, 7]]);
+      ids.set_kind(ATTRKind::Integer).unpack();
+      let mut weights = CustomATTR::from(vec![[255u8, 0, 51, 0]; 3]);
+      weights.set_kind(ATTRKind::Normalized).unpack();
+      let mut exact = CustomATTR::from(vec![0.1f64, 0.2, 0.3]);
+      exact.set_kind(ATTRKind::Double).unpack();
+      let plain = CustomATTR::from(vec![[-2i16, 300]; 3]);
+      assert_eq!(plain.kind(), ATTRKind::Float);
+      for (i, cus) in [ids, weights, exact, plain].into_iter().enumerate() {
+         asset.attach_custom_attr_at(5 + i as u32, cus).unpack();
+      }
+
+      let mesh = r.dynamic_mesh(asset);
+      let data = mesh.vertex_data().unwrap();
+      //what the software core hands the shader for vertex 0
+      let read = |location: u32| -> (ATTRKind, Vec<f32>) {
+         let (_, info, offset) = mesh.attrs.iter().find(|(l, _, _)| *l == location).unwrap();
+         let size = info.byte_count;
+         let values = (0..info.elem_count)
+            .map(|e| super::read_elem(info, &data[offset + e * size..offset + (e + 1) * size]))
+            .collect();
+         (info.kind, values)
+      };
+      assert_eq!(read(5), (ATTRKind::Integer, vec![3.0, 40000.0]));
+      assert_eq!(read(6), (ATTRKind::Normalized, vec![1.0, 0.0, 0.2, 0.0]));
+      assert_eq!(read(7), (ATTRKind::Double, vec![0.1]));
+      assert_eq!(read(8), (ATTRKind::Float, vec![-2.0, 300.0]));
+
+      //kinds that don't fit the data are refused and leave it Float
+      let mut floats = CustomATTR::from(vec![0.5f32; 3]);
+      assert!(floats.set_kind(ATTRKind::Integer).is_err());
+      assert!(floats.set_kind(ATTRKind::Normalized).is_err());
+      assert!(floats.set_kind(ATTRKind::Double).is_err());
+      assert_eq!(floats.kind(), ATTRKind::Float);
+      let mut bytes = CustomATTR::from(vec![1u8; 3]);
+      assert!(bytes.set_kind(ATTRKind::Double).is_err());
+      assert!(CustomATTR::from(vec![1.0f64; 3])
+         .set_kind(ATTRKind::Integer)
+         .is_err());
+      bytes.set_kind(ATTRKind::Float).unpack();
+   }
+
+   #[test]
+   fn uniform_arrays_reach_the_program() {
+      let core = super::sw_renderer_init(Size2D::from(4, 4));
+      let id = core.create_src_program("", "").unpack();
+      core.bind_program(id);
+      let loc = |name: &str| match core.get_uni_location(id, name) {
+         NEOption::Exists(l) => l,
+         NEOption::Empty => panic!("{name} has no location"),
+      };
+      let (flags, bones) = (loc("uFlags"), loc("uBones"));
+      core.set_uni_at(flags, Uniform::BoolArray(vec![true, false]));
+      core.set_uni_at(bones, Uniform::Matrix3Array(vec![Matrix3::identity(); 3]));
+      core.set_uni_at(flags, Uniform::BoolArray(Vec::new()));
+      let state = core.state.borrow();
+      assert!(matches!(state.uni("uFlags"), Some(Uniform::BoolArray(b)) if b == &[true, false]));
+      assert!(matches!(state.uni("uBones"), Some(Uniform::Matrix3Array(m)) if m.len() == 3));
+      for (uniform, glsl) in [
+         (Uniform::IVec3Array(vec![[1, 2, 3]]), "ivec3"),
+         (Uniform::UVec2Array(vec![[1, 2]]), "uvec2"),
+         (Uniform::Matrix2Array(vec![Matrix2::identity()]), "mat2"),
+      ] {
+         assert_eq!(uniform.glsl_type(), glsl);
+         assert!(!uniform.is_empty_array());
+      }
+   }
+
+   #[test]
+   fn resize_leaves_shared_streams_alone() {
+      let (mut r, cam) = renderer(32);
+      let mut source = r.mesh(triangle(0.0, [1.0, 0.0, 0.0]));
+      let mut asset = triangle(0.0, [0.0, 1.0, 0.0]);
+      asset.pos_attr = PosATTR::empty();
+      asset.col_attr = ColATTR::empty();
+      let mut shared = r.shared_mesh(asset, &source, 0);
+      r.resize_vertices(&mut shared, 3);
+      r.pre_update(&cam);
+      r.render(&mut source);
+      assert_eq!(pixel(&r, 16, 16), [255, 0, 0, 255]);
+      //growing past the shared stream is clamped until its owner grows it
+      r.resize_vertices(&mut shared, 10);
+      assert_eq!(shared.vert_count(), 3);
+      r.resize_vertices(&mut source, 5);
+      r.resize_vertices(&mut shared, 10);
+      assert_eq!(shared.vert_count(), 5);
+      r.resize_vertices(&mut shared, 2);
+      assert_eq!(shared.vert_count(), 2);
+      drop(shared);
+   }
+
+   #[test]
+   fn update_attr_needs_a_stream() {
+      let (mut r, cam) = renderer(32);
+      let mut mesh = r.dynamic_mesh(triangle(0.0, [1.0, 0.0, 0.0]));
+      r.update_attr(&mut mesh, 1, 0, &[[0.0f32, 1.0, 0.0]; 3])
+         .unpack();
+      r.pre_update(&cam);
+      r.render(&mut mesh);
+      assert_eq!(pixel(&r, 16, 16), [0, 255, 0, 255]);
+      assert!(r.update_attr(&mut mesh, 7, 0, &[1.0f32]).is_err());
+      mesh.streams.clear();
+      assert!(r
+         .update_attr(&mut mesh, 1, 0, &[[0.0f32, 0.0, 1.0]])
+         .is_err());
+   }
+
+   #[test]
+   fn layout_mismatch_still_draws() {
+      let (mut r, cam) = renderer(32);
+      r.pre_update(&cam);
+      let mut shader = r.fallback_shader();
+      shader.info.attributes = vec![NEShaderVar {
+         name: "vUvm".to_string(),
+         location: Some(2),
+         binding: None,
+         typ: SpvType::Vector(SpvScalar::Float, 2),
+         array_len: 1,
+      }];
+      let mut mesh = r.mesh(triangle(0.0, [1.0, 0.0, 0.0]));
+      mesh.set_shader(shader);
+      r.render(&mut mesh);
+      assert_eq!(pixel(&r, 16, 16), [255, 0, 0, 255]);
+   }
+
+   #[test]
+   fn instance_clash_skips_the_draw() {
+      let (mut r, cam) = renderer(32);
+      r.pre_update(&cam);
+      let mut asset = triangle(0.0, [1.0, 0.0, 0.0]);
+      asset
+         .attach_custom_attr_at(INSTANCE_LOCATION, CustomATTR::from(vec![1.0f32; 3]))
+         .unpack();
+      let mut mesh = r.mesh(asset);
+      let mut instances = NEInstances::from_transforms(vec![Transform::default()]);
+      r.render_instanced(&mut mesh, &mut instances);
+      let mut other = r.mesh(triangle(0.5, [0.0, 1.0, 0.0]));
+      r.render(&mut other);
+      assert_eq!(pixel(&r, 16, 16), [0, 255, 0, 255]);
+   }
+
+   #[test]
+   fn deleted_target_textures_are_dead() {
+      let (mut r, cam) = renderer(32);
+      let target = r
+         .render_target(Size2D::from(8, 8), &[TexFormat::RGBA(8)], false)
+         .unpack();
+      let tex = target.color_texture(0);
+      let mut shader = r.fallback_shader();
+      assert!(shader.attach_texture(&tex));
+      let handle = match &shader.textures[0].gpu {
+         Some(guard) => guard.handle,
+         None => panic!("color texture without a guard"),
+      };
+      assert!(r.is_alive(handle));
+      r.delete_target(target);
+      assert!(!r.is_alive(handle));
+      r.post_update(0.0);
+      let mut mesh = r.mesh(triangle(0.0, [1.0, 0.0, 0.0]));
+      mesh.set_shader(shader);
+      r.pre_update(&cam);
+      r.render(&mut mesh);
+      assert_eq!(pixel(&r, 16, 16), [255, 0, 0, 255]);
+   }
+
+   #[test]
+   fn resize_changes_readback() {
+      let (mut r, cam) = renderer(16);
+      r.set_size(Size2D::from(24, 8));
+      r.pre_update(&cam);
+      let frame = r.read_color();
+      assert_eq!((frame.size.w, frame.size.h), (24, 8));
+      assert_eq!(frame.bytes.len(), 24 * 8 * 4);
+   }
+
+   #[test]
+   fn software_game_needs_no_window() {
+      let builder = NEGameBuilder {
+         render_api: RenderAPI::Software,
+         mode: WinMode::Windowed(32, 16),
+         ..Default::default()
+      };
+      let mut game = builder.build().unpack();
+      assert!(game.window.window.is_none());
+      game.window.set_size(Size2D::from(8, 8));
+      game.pre_update();
+      game.render();
+      game.post_update();
+      let frame = game.renderer.read_color();
+      assert_eq!((frame.size.w, frame.size.h), (8, 8));
+      game.end();
+   }
+}
@@ -1,6 +1,9 @@
-use crate::asset::ATTRInfo;
+use crate::asset::{ATTRInfo, TexFormat};
 use crate::renderer::{Renderer, ShaderType};
-use crate::{Cull, DrawMode, NEResult, NEShaderAsset, NETexture, PolyMode, Size2D, Uniform, RGB};
+use crate::{
+   BlendMode, Cull, DrawMode, IndexFormat, NEOption, NEResult, NEShaderAsset, NETexture, PolyMode,
+   Size2D, Uniform, RGB,
+};
 use cgmath::Matrix4;
 
 #[derive(Copy, Clone)]
@@ -35,6 +38,14 @@ impl Renderer for VKRenderer {
       todo!()
    }
 
+   fn enable_blend(&self, enable: bool) {
+      todo!()
+   }
+
+   fn set_blend_mode(&self, mode: BlendMode) {
+      todo!()
+   }
+
    fn set_cull_face(&self, face: Cull) {
       todo!()
    }
@@ -108,7 +119,11 @@ impl Renderer for VKRenderer {
       todo!()
    }
 
-   fn get_uni_location(&self, id: u32, name: &str) -> u32 {
+   fn get_uni_location(&self, id: u32, name: &str) -> NEOption<i32> {
+      todo!()
+   }
+
+   fn active_uniforms(&self, id: u32) -> Vec<String> {
       todo!()
    }
 
@@ -116,6 +131,10 @@ impl Renderer for VKRenderer {
       todo!()
    }
 
+   fn set_uni_at(&self, loc: i32, uniform: Uniform) {
+      todo!()
+   }
+
    fn set_uni_i32(&self, id: u32, name: &str, int: i32) {
       todo!()
    }
@@ -136,7 +155,15 @@ impl Renderer for VKRenderer {
       todo!()
    }
 
-   fn fill_index_buffer(&self, id: u32, buffer: &Vec<u32>) {
+   fn fill_index_buffer(&self, id: u32, buffer: &Vec<u8>) {
+      todo!()
+   }
+
+   fn update_buffer(&self, v_id: u32, b_id: u32, offset: usize, data: &[u8]) {
+      todo!()
+   }
+
+   fn update_index_buffer(&self, id: u32, offset: usize, data: &[u8]) {
       todo!()
    }
 
@@ -152,15 +179,88 @@ impl Renderer for VKRenderer {
       todo!()
    }
 
+   fn create_vertex_buffer(&self) -> u32 {
+      todo!()
+   }
+
+   fn delete_vertex_buffer(&self, id: u32) {
+      todo!()
+   }
+
+   fn create_instance_buffer(&self) -> u32 {
+      todo!()
+   }
+
+   fn set_instance_layout(
+      &self,
+      info: &ATTRInfo,
+      attr_id: u32,
+      stride: usize,
+      local_offset: usize,
+   ) {
+      todo!()
+   }
+
+   fn clear_instance_layout(&self, attr_id: u32) {
+      todo!()
+   }
+
+   fn delete_instance_buffer(&self, id: u32) {
+      todo!()
+   }
+
    fn clear(&self) {
       todo!()
    }
 
-   fn draw_indexed(&self, draw_mode: &DrawMode, index_count: u32) {
+   fn draw_indexed(&self, draw_mode: &DrawMode, index_count: u32, format: IndexFormat) {
       todo!()
    }
 
    fn draw_array(&self, draw_mode: &DrawMode, vert_count: u32) {
       todo!()
    }
+
+   fn draw_indexed_instanced(
+      &self,
+      draw_mode: &DrawMode,
+      index_count: u32,
+      format: IndexFormat,
+      instances: u32,
+   ) {
+      todo!()
+   }
+
+   fn draw_array_instanced(&self, draw_mode: &DrawMode, vert_count: u32, instances: u32) {
+      todo!()
+   }
+
+   fn create_target(
+      &self,
+      size: Size2D,
+      colors: &[TexFormat],
+      depth: bool,
+   ) -> NEResult<(u32, Vec<u32>, u32)> {
+      todo!()
+   }
+
+   fn bind_target(&self, id: u32) {
+      todo!()
+   }
+
+   fn unbind_target(&self) {
+      todo!()
+   }
+
+   fn delete_target(&self, id: u32, color_ids: &Vec<u32>, depth_id: u32) {
+      todo!()
+   }
+
+   fn read_color(&self, size: Size2D) -> Vec<u8> {
+      todo!()
+   }
+
+   fn read_depth(&self, size: Size2D) -> Vec<f32> {
+      todo!()
+   }
 }
@@ -0,0 +1,2 @@
+mod target;
+pub use target::*;
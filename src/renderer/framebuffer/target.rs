@@ -0,0 +1,38 @@
+use crate::asset::TexFormat;
+use crate::renderer::resource::NEResGuard;
+use crate::{NETexture, Size2D};
+use std::rc::Rc;
+
+pub struct NERenderTarget {
+   pub(crate) id: u32,
+   pub(crate) color_ids: Vec<u32>,
+   pub(crate) formats: Vec<TexFormat>,
+   pub(crate) has_depth: bool,
+   pub size: Size2D,
+   pub(crate) guard: Option<Rc<NEResGuard>>,
+}
+
+impl NERenderTarget {
+   pub fn color_count(&self) -> usize {
+      self.color_ids.len()
+   }
+   pub fn has_depth(&self) -> bool {
+      self.has_depth
+   }
+   //gpu side handle to a color attachment, attach it to a shader to sample it.
+   //it shares the target's guard, so bindings of it are skipped once the target is deleted
+   pub fn color_texture(&self, index: usize) -> NETexture {
+      let mut tex = NETexture::empty();
+      if index >= self.color_ids.len() {
+         return tex;
+      }
+      tex.id = self.color_ids[index];
+      tex.exists_on_gpu = true;
+      tex.gpu = self.guard.clone();
+      tex.typ = self.formats[index];
+      tex.bit_depth = tex.typ.bit_depth();
+      tex.pixel_size = tex.typ.elem_count() * tex.bit_depth;
+      tex.size = self.size;
+      tex
+   }
+}
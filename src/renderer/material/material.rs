@@ -0,0 +1,121 @@
+use crate::renderer::shader::{set_binding, NETexBinding};
+use crate::{BlendMode, Cull, NEShader, NETexture, PolyMode, Uniform};
+use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+//sampler names the material texture slots bind to
+pub const ALBEDO: &str = "tDif1";
+pub const NORMAL: &str = "tNrm1";
+pub const ROUGHNESS: &str = "tRgh1";
+pub const EMISSIVE: &str = "tEmi1";
+
+pub(crate) struct NEMaterialState {
+   pub(crate) shader: NEShader,
+   pub(crate) uniforms: HashMap<String, Uniform>,
+   pub(crate) textures: Vec<NETexBinding>,
+
+   //render state overrides, None keeps the renderer's setting
+   pub(crate) culling: Option<bool>,
+   pub(crate) cull_face: Option<Cull>,
+   pub(crate) depth_test: Option<bool>,
+   pub(crate) poly_mode: Option<PolyMode>,
+   //blended materials are transparent and can be deferred to the sorted queue
+   pub(crate) blend: Option<BlendMode>,
+}
+
+//cloning shares the material, edits show up on every mesh using it
+#[derive(Clone)]
+pub struct NEMaterial {
+   pub(crate) state: Rc<RefCell<NEMaterialState>>,
+}
+
+impl NEMaterial {
+   pub fn from(shader: NEShader) -> NEMaterial {
+      NEMaterial {
+         state: Rc::new(RefCell::new(NEMaterialState {
+            shader,
+            uniforms: HashMap::new(),
+            textures: Vec::new(),
+            culling: None,
+            cull_face: None,
+            depth_test: None,
+            poly_mode: None,
+            blend: None,
+         })),
+      }
+   }
+   pub(crate) fn state(&self) -> Ref<NEMaterialState> {
+      self.state.borrow()
+   }
+
+   pub fn shader(&self) -> NEShader {
+      self.state.borrow().shader.clone()
+   }
+   pub fn set_shader(&self, shader: NEShader) {
+      self.state.borrow_mut().shader = shader
+   }
+
+   pub fn set_uniform(&self, name: &str, uniform: Uniform) {
+      self
+         .state
+         .borrow_mut()
+         .uniforms
+         .insert(name.to_string(), uniform);
+   }
+   pub fn remove_uniform(&self, name: &str) -> Option<Uniform> {
+      self.state.borrow_mut().uniforms.remove(name)
+   }
+
+   pub fn set_texture(&self, sampler: &str, tex: &NETexture) -> bool {
+      match NETexBinding::from(sampler, tex) {
+         None => false,
+         Some(binding) => {
+            set_binding(&mut self.state.borrow_mut().textures, binding);
+            true
+         }
+      }
+   }
+   pub fn remove_texture(&self, sampler: &str) {
+      self
+         .state
+         .borrow_mut()
+         .textures
+         .retain(|b| b.sampler != sampler)
+   }
+   pub fn set_albedo(&self, tex: &NETexture) -> bool {
+      self.set_texture(ALBEDO, tex)
+   }
+   pub fn set_normal(&self, tex: &NETexture) -> bool {
+      self.set_texture(NORMAL, tex)
+   }
+   pub fn set_roughness(&self, tex: &NETexture) -> bool {
+      self.set_texture(ROUGHNESS, tex)
+   }
+   pub fn set_emissive(&self, tex: &NETexture) -> bool {
+      self.set_texture(EMISSIVE, tex)
+   }
+
+   pub fn set_culling(&self, culling: Option<bool>) {
+      self.state.borrow_mut().culling = culling
+   }
+   pub fn set_cull_face(&self, cull_face: Option<Cull>) {
+      self.state.borrow_mut().cull_face = cull_face
+   }
+   pub fn set_depth_test(&self, depth_test: Option<bool>) {
+      self.state.borrow_mut().depth_test = depth_test
+   }
+   pub fn set_poly_mode(&self, poly_mode: Option<PolyMode>) {
+      self.state.borrow_mut().poly_mode = poly_mode
+   }
+   pub fn set_blend(&self, blend: Option<BlendMode>) {
+      self.state.borrow_mut().blend = blend
+   }
+   pub fn is_transparent(&self) -> bool {
+      self.state.borrow().blend.is_some()
+   }
+
+   pub fn is_shared(&self) -> bool {
+      Rc::strong_count(&self.state) > 1
+   }
+}
@@ -0,0 +1,2 @@
+mod material;
+pub use material::*;
@@ -0,0 +1,162 @@
+use crate::asset::{get_format, ATTRInfo};
+use crate::renderer::resource::NEResGuard;
+use crate::{ATTRKind, CustomATTR, DataType, Transform};
+use cgmath::Matrix4;
+
+//instance attributes sit at fixed locations so shaders don't depend on the mesh's layout:
+//layout(location = 8) in mat4 iTfm; layout(location = 12) in vec3 iCol; custom from 13
+pub const INSTANCE_LOCATION: u32 = 8;
+pub const INSTANCE_COL_LOCATION: u32 = 12;
+pub const INSTANCE_CUS_LOCATION: u32 = 13;
+
+//where the uploaded per-instance data lives and how it is laid out
+#[derive(Clone)]
+pub(crate) struct NEInstanceLayout {
+   pub(crate) buf_id: u32,
+   pub(crate) count: u32,
+   pub(crate) stride: usize,
+   pub(crate) attrs: Vec<(u32, ATTRInfo, usize)>,
+}
+
+//per-instance data for drawing one mesh many times, iTfm is applied before the mesh's own transform
+pub struct NEInstances {
+   transforms: Vec<Matrix4<f32>>,
+   colors: Vec<[f32; 3]>,
+   cus_attrs: Vec<CustomATTR>,
+
+   pub(crate) layout: Option<NEInstanceLayout>,
+   pub(crate) dirty: bool,
+   pub(crate) guard: Option<NEResGuard>,
+}
+
+impl NEInstances {
+   pub fn new() -> NEInstances {
+      NEInstances {
+         transforms: Vec::new(),
+         colors: Vec::new(),
+         cus_attrs: Vec::new(),
+         layout: None,
+         dirty: true,
+         guard: None,
+      }
+   }
+   pub fn from_transforms(transforms: Vec<Transform>) -> NEInstances {
+      let mut instances = NEInstances::new();
+      instances.set_transforms(transforms);
+      instances
+   }
+   pub fn set_transforms(&mut self, mut transforms: Vec<Transform>) {
+      self.transforms = transforms
+         .iter_mut()
+         .map(|t| {
+            t.calc_matrix();
+            t.matrix
+         })
+         .collect();
+      self.dirty = true;
+   }
+   pub fn push_transform(&mut self, mut transform: Transform) {
+      transform.calc_matrix();
+      self.transforms.push(transform.matrix);
+      self.dirty = true;
+   }
+   pub fn set_colors(&mut self, colors: Vec<[f32; 3]>) {
+      self.colors = colors;
+      self.dirty = true;
+   }
+   //one element per instance, bound from INSTANCE_CUS_LOCATION in attach order
+   pub fn attach_custom_attr(&mut self, cus_attr: CustomATTR) {
+      self.cus_attrs.push(cus_attr);
+      self.dirty = true;
+   }
+   pub fn clear(&mut self) {
+      self.transforms.clear();
+      self.colors.clear();
+      self.cus_attrs.clear();
+      self.dirty = true;
+   }
+   //the longest stream decides, shorter ones repeat their defaults
+   pub fn count(&self) -> u32 {
+      let mut count = self.transforms.len().max(self.colors.len());
+      for cus_attr in self.cus_attrs.iter() {
+         let info = cus_attr.info();
+         if info.exists {
+            count = count.max(cus_attr.data().len() / (info.byte_count * info.elem_count));
+         }
+      }
+      count as u32
+   }
+
+   //interleaves every stream into one buffer, returns it with the attribute table and stride
+   pub(crate) fn buffer(&self) -> (Vec<u8>, Vec<(u32, ATTRInfo, usize)>, usize) {
+      let count = self.count() as usize;
+      let mut attrs = Vec::new();
+      let mut stride = 0;
+      if !self.transforms.is_empty() {
+         let (typ, typ_str, byte_count, elem_count) = get_format(&[0.0f32; 4]);
+         for col in 0..4 {
+            let info = ATTRInfo {
+               typ: typ.clone(),
+               typ_str: typ_str.clone(),
+               exists: true,
+               byte_count,
+               elem_count,
+               kind: ATTRKind::Float,
+            };
+            attrs.push((INSTANCE_LOCATION + col, info, stride));
+            stride += byte_count * elem_count;
+         }
+      }
+      if !self.colors.is_empty() {
+         let (typ, typ_str, byte_count, elem_count) = get_format(&[0.0f32; 3]);
+         let info = ATTRInfo {
+            typ,
+            typ_str,
+            exists: true,
+            byte_count,
+            elem_count,
+            kind: ATTRKind::Float,
+         };
+         attrs.push((INSTANCE_COL_LOCATION, info, stride));
+         stride += byte_count * elem_count;
+      }
+      let mut location = INSTANCE_CUS_LOCATION;
+      for cus_attr in self.cus_attrs.iter() {
+         let info = cus_attr.info();
+         if info.exists {
+            let size = info.byte_count * info.elem_count;
+            attrs.push((location, info, stride));
+            stride += size;
+            location += 1;
+         }
+      }
+
+      let mut buffer: Vec<u8> = Vec::with_capacity(count * stride);
+      for i in 0..count {
+         if !self.transforms.is_empty() {
+            let m: [[f32; 4]; 4] = match self.transforms.get(i) {
+               Some(m) => (*m).into(),
+               None => Matrix4::from_scale(1.0).into(),
+            };
+            for col in m.iter() {
+               buffer.extend(col.u8ify());
+            }
+         }
+         if !self.colors.is_empty() {
+            let col = self.colors.get(i).unwrap_or(&[1.0, 1.0, 1.0]);
+            buffer.extend(col.u8ify());
+         }
+         for cus_attr in self.cus_attrs.iter() {
+            let info = cus_attr.info();
+            if info.exists {
+               let size = info.byte_count * info.elem_count;
+               match cus_attr.data().get(i * size..(i + 1) * size) {
+                  Some(bytes) => buffer.extend(bytes),
+                  None => buffer.extend(vec![0; size]),
+               }
+            }
+         }
+      }
+      (buffer, attrs, stride)
+   }
+}
@@ -1,7 +1,14 @@
+use crate::asset::ATTRInfo;
+use crate::renderer::resource::NEResGuard;
+use crate::renderer::shader::{set_binding, NETexBinding};
 use crate::{ansi, log_info};
-use crate::{NEShader, Transform};
+use crate::{NEHandle, NEMaterial, NEShader, NETexture, Transform, Uniform};
 use cgmath::Matrix4;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
+#[derive(Copy, Clone)]
 pub enum DrawMode {
    Points,
    Lines,
@@ -9,6 +16,74 @@ pub enum DrawMode {
    Strip,
 }
 
+//width of one index in the index buffer, meshes pick the smallest that fits
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IndexFormat {
+   U8,
+   U16,
+   U32,
+}
+
+impl IndexFormat {
+   pub fn fitting(max_index: u32) -> IndexFormat {
+      match max_index {
+         0..=0xFF => IndexFormat::U8,
+         0x100..=0xFFFF => IndexFormat::U16,
+         _ => IndexFormat::U32,
+      }
+   }
+   pub fn size(&self) -> usize {
+      match self {
+         IndexFormat::U8 => 1,
+         IndexFormat::U16 => 2,
+         IndexFormat::U32 => 4,
+      }
+   }
+   pub fn max(&self) -> u32 {
+      match self {
+         IndexFormat::U8 => u8::MAX as u32,
+         IndexFormat::U16 => u16::MAX as u32,
+         IndexFormat::U32 => u32::MAX,
+      }
+   }
+   //native endian, values must fit the format
+   pub(crate) fn pack(&self, indices: &[u32]) -> Vec<u8> {
+      let mut bytes = Vec::with_capacity(indices.len() * self.size());
+      for index in indices.iter() {
+         match self {
+            IndexFormat::U8 => bytes.push(*index as u8),
+            IndexFormat::U16 => bytes.extend((*index as u16).to_ne_bytes()),
+            IndexFormat::U32 => bytes.extend(index.to_ne_bytes()),
+         }
+      }
+      bytes
+   }
+   pub(crate) fn unpack(&self, bytes: &[u8]) -> Vec<u32> {
+      bytes
+         .chunks_exact(self.size())
+         .map(|b| match self {
+            IndexFormat::U8 => b[0] as u32,
+            IndexFormat::U16 => u16::from_ne_bytes([b[0], b[1]]) as u32,
+            IndexFormat::U32 => u32::from_ne_bytes([b[0], b[1], b[2], b[3]]),
+         })
+         .collect()
+   }
+}
+
+//one vertex buffer holding some of the mesh's attributes interleaved
+pub(crate) struct NEVertexStream {
+   pub(crate) buf_id: u32,
+   pub(crate) stride: usize,
+   pub(crate) locations: Vec<u32>,
+   pub(crate) cpu: Option<Vec<u8>>,
+   //borrowed from another mesh, deleting this one leaves it alone
+   pub(crate) shared: bool,
+   //meshes sharing the stream hold the same guard, the buffer lives until the last one drops
+   pub(crate) guard: Option<Rc<NEResGuard>>,
+   //vertices the buffer holds, shared with the meshes borrowing it
+   pub(crate) verts: Rc<Cell<u32>>,
+}
+
 pub struct NEMesh {
    pub visible: bool,
    pub transform: Transform,
@@ -21,14 +96,55 @@ pub struct NEMesh {
    pub(crate) ind_count: u32,
    pub(crate) buf_id: (u32, u32),
    pub(crate) index_buf_id: u32,
+   pub(crate) index_format: IndexFormat,
    pub(crate) shader: NEShader,
+   pub(crate) uniforms: HashMap<String, Uniform>,
+   pub(crate) textures: Vec<NETexBinding>,
+   pub(crate) material: Option<NEMaterial>,
    pub(crate) layouts: Vec<String>,
+   //location, format and byte offset inside the vertex of its stream
+   pub(crate) attrs: Vec<(u32, ATTRInfo, usize)>,
+   pub(crate) streams: Vec<NEVertexStream>,
+   //kept for dynamic meshes so partial updates and resizes don't lose data
+   pub(crate) cpu_indices: Option<Vec<u32>>,
+   //dropping the mesh releases its buffers
+   pub(crate) guard: Option<Rc<NEResGuard>>,
 }
 
 impl NEMesh {
    pub fn set_shader(&mut self, shader: NEShader) {
       self.shader = shader
    }
+   //the material's shader replaces the mesh's own while it is set
+   pub fn set_material(&mut self, material: &NEMaterial) {
+      self.material = Some(material.clone())
+   }
+   pub fn clear_material(&mut self) -> Option<NEMaterial> {
+      self.material.take()
+   }
+   pub fn material(&self) -> Option<&NEMaterial> {
+      self.material.as_ref()
+   }
+   //overrides the shader's value of the same name for this mesh only
+   pub fn set_uniform(&mut self, name: &str, uniform: Uniform) {
+      self.uniforms.insert(name.to_string(), uniform);
+   }
+   pub fn remove_uniform(&mut self, name: &str) -> Option<Uniform> {
+      self.uniforms.remove(name)
+   }
+   //overrides the shader's texture on the same sampler for this mesh only
+   pub fn set_texture(&mut self, sampler: &str, tex: &NETexture) -> bool {
+      match NETexBinding::from(sampler, tex) {
+         None => false,
+         Some(binding) => {
+            set_binding(&mut self.textures, binding);
+            true
+         }
+      }
+   }
+   pub fn remove_texture(&mut self, sampler: &str) {
+      self.textures.retain(|b| b.sampler != sampler)
+   }
    pub fn set_draw_mode(&mut self, draw_mode: DrawMode) {
       self.draw_mode = draw_mode
    }
@@ -39,6 +155,45 @@ impl NEMesh {
       self.visible = !self.visible;
    }
 
+   pub fn handle(&self) -> Option<NEHandle> {
+      self.guard.as_ref().map(|g| g.handle)
+   }
+   pub fn vert_count(&self) -> u32 {
+      self.vert_count
+   }
+   pub fn ind_count(&self) -> u32 {
+      self.ind_count
+   }
+   pub fn index_format(&self) -> IndexFormat {
+      self.index_format
+   }
+   //the first stream, which holds every attribute unless the asset picked another vertex layout
+   pub fn vertex_data(&self) -> Option<&Vec<u8>> {
+      self.stream_data(0)
+   }
+   pub fn stream_data(&self, stream: usize) -> Option<&Vec<u8>> {
+      self.streams.get(stream).and_then(|s| s.cpu.as_ref())
+   }
+   pub fn stream_count(&self) -> usize {
+      self.streams.len()
+   }
+   //the stream holding the attribute at `location`
+   pub fn stream_of(&self, location: u32) -> Option<usize> {
+      self
+         .streams
+         .iter()
+         .position(|s| s.locations.contains(&location))
+   }
+   pub fn index_data(&self) -> Option<&Vec<u32>> {
+      self.cpu_indices.as_ref()
+   }
+   pub fn drop_cpu_copy(&mut self) {
+      for stream in self.streams.iter_mut() {
+         stream.cpu = None;
+      }
+      self.cpu_indices = None;
+   }
+
    pub(crate) fn matrix(&self) -> Matrix4<f32> {
       self.transform.matrix
    }
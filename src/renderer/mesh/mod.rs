@@ -1,3 +1,5 @@
+mod instances;
 mod mesh;
 
+pub use instances::*;
 pub use mesh::*;
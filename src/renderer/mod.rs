@@ -1,13 +1,21 @@
 mod camera;
 pub(crate) mod core;
+mod framebuffer;
+mod material;
 mod mesh;
+mod queue;
 mod renderer;
+mod resource;
 mod shader;
 mod util;
 
 pub use camera::*;
 pub use core::*;
+pub use framebuffer::*;
+pub use material::*;
 pub use mesh::*;
+pub use queue::*;
 pub use renderer::*;
+pub use resource::*;
 pub use shader::*;
 pub use util::*;
@@ -0,0 +1,110 @@
+use crate::renderer::mesh::NEInstanceLayout;
+use crate::renderer::shader::NETexBinding;
+use crate::{DrawMode, IndexFormat, NEMaterial, NEMesh, NEShader, Uniform};
+use cgmath::Matrix4;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+//everything a draw needs, borrowed from the mesh when drawn right away
+//and copied out with into_owned when it runs later in the frame
+pub(crate) struct NEDrawCmd<'a> {
+   pub(crate) shader: Cow<'a, NEShader>,
+   pub(crate) material: Option<NEMaterial>,
+   pub(crate) uniforms: Cow<'a, HashMap<String, Uniform>>,
+   pub(crate) textures: Cow<'a, [NETexBinding]>,
+   pub(crate) matrix: Matrix4<f32>,
+
+   pub(crate) draw_mode: DrawMode,
+   pub(crate) has_indices: bool,
+   pub(crate) vert_count: u32,
+   pub(crate) ind_count: u32,
+   pub(crate) index_format: IndexFormat,
+   pub(crate) buf_id: (u32, u32),
+   pub(crate) index_buf_id: u32,
+   pub(crate) instances: Option<Cow<'a, NEInstanceLayout>>,
+
+   //squared distance from the camera to the mesh origin
+   pub(crate) depth: f32,
+}
+
+impl<'a> NEDrawCmd<'a> {
+   pub(crate) fn from(mesh: &'a NEMesh, shader: &'a NEShader, depth: f32) -> NEDrawCmd<'a> {
+      NEDrawCmd {
+         shader: Cow::Borrowed(shader),
+         material: mesh.material.clone(),
+         uniforms: Cow::Borrowed(&mesh.uniforms),
+         textures: Cow::Borrowed(&mesh.textures),
+         matrix: mesh.matrix(),
+         draw_mode: mesh.draw_mode,
+         has_indices: mesh.has_indices,
+         vert_count: mesh.vert_count,
+         ind_count: mesh.ind_count,
+         index_format: mesh.index_format,
+         buf_id: mesh.buf_id,
+         index_buf_id: mesh.index_buf_id,
+         instances: None,
+         depth,
+      }
+   }
+   pub(crate) fn into_owned(self) -> NEDrawCmd<'static> {
+      NEDrawCmd {
+         shader: Cow::Owned(self.shader.into_owned()),
+         material: self.material,
+         uniforms: Cow::Owned(self.uniforms.into_owned()),
+         textures: Cow::Owned(self.textures.into_owned()),
+         matrix: self.matrix,
+         draw_mode: self.draw_mode,
+         has_indices: self.has_indices,
+         vert_count: self.vert_count,
+         ind_count: self.ind_count,
+         index_format: self.index_format,
+         buf_id: self.buf_id,
+         index_buf_id: self.index_buf_id,
+         instances: self.instances.map(|i| Cow::Owned(i.into_owned())),
+         depth: self.depth,
+      }
+   }
+}
+
+//back to front so blended surfaces composite over what is behind them
+pub(crate) fn sort_back_to_front(cmds: &mut [NEDrawCmd]) {
+   cmds.sort_by(|a, b| b.depth.total_cmp(&a.depth))
+}
+
+//sorts by program, then first texture, then vertex buffer so neighbours share binds
+pub(crate) fn sort_by_state(cmds: &mut [NEDrawCmd]) {
+   cmds.sort_by_cached_key(|cmd| (cmd.shader.id, cmd.tex_key(), cmd.buf_id))
+}
+
+impl NEDrawCmd<'_> {
+   fn tex_key(&self) -> u32 {
+      if let Some(b) = self.textures.first() {
+         return b.id;
+      }
+      if let Some(mat) = &self.material {
+         if let Some(b) = mat.state().textures.first() {
+            return b.id;
+         }
+      }
+      self.shader.textures.first().map_or(0, |b| b.id)
+   }
+}
+
+//what the backend currently has bound, None is unknown
+#[derive(Default)]
+pub(crate) struct NEBindCache {
+   pub(crate) program: Option<u32>,
+   pub(crate) buffer: Option<(u32, u32)>,
+   pub(crate) index_buffer: Option<u32>,
+   pub(crate) units: HashMap<u32, u32>,
+}
+
+//counted from pre_update, complete once post_update has run
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NEFrameStats {
+   pub draw_calls: u32,
+   pub program_binds: u32,
+   pub texture_binds: u32,
+   pub buffer_binds: u32,
+   pub skipped_binds: u32,
+}
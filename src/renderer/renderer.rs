@@ -1,9 +1,20 @@
-use crate::asset::ATTRInfo;
+use crate::asset::{get_format, ATTRInfo, TexFormat};
+use crate::renderer::material::NEMaterialState;
+use crate::renderer::mesh::{NEInstanceLayout, NEVertexStream};
+use crate::renderer::queue::{sort_back_to_front, sort_by_state, NEBindCache, NEDrawCmd};
+use crate::renderer::resource::{NEGpuRes, NEHandle, NEResGuard, NEResources};
+use crate::renderer::shader::NETexBinding;
 use crate::{
-   ansi, color, log_info, DataType, DrawMode, NECamera, NEError, NEMesh, NEMeshAsset, NEResult,
-   NEShader, NEShaderAsset, NETexture, RenderAPI, Size2D, Uniform, RGB,
+   ansi, color, log_info, log_warn, ATTRKind, ATTRType, DataType, DrawMode, IndexFormat, NECamera,
+   NEError, NEFrameStats, NEInstances, NEMesh, NEMeshAsset, NEOption, NERenderTarget, NEResult,
+   NEShader, NEShaderAsset, NETexture, RenderAPI, Size2D, SpvScalar, Uniform, VertexAttr,
+   INSTANCE_LOCATION, RGB,
 };
-use cgmath::Matrix4;
+use cgmath::{InnerSpace, Matrix4, SquareMatrix};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 
 #[derive(Copy, Clone)]
 pub enum PolyMode {
@@ -16,6 +27,13 @@ pub enum Cull {
    Clock,
    AntiClock,
 }
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BlendMode {
+   Alpha,
+   Additive,
+   Multiply,
+   Premultiplied,
+}
 
 #[derive(Copy, Clone, Debug)]
 pub(crate) enum ShaderType {
@@ -33,6 +51,8 @@ pub(crate) trait Renderer {
    fn enable_msaa(&self, enable: bool);
    fn enable_depth(&self, enable: bool);
    fn enable_cull(&self, enable: bool);
+   fn enable_blend(&self, enable: bool);
+   fn set_blend_mode(&self, mode: BlendMode);
    fn set_cull_face(&self, face: Cull);
    fn set_wire_width(&self, thickness: f32);
 
@@ -57,9 +77,12 @@ pub(crate) trait Renderer {
 
    fn create_texture(&self, tex: &NETexture) -> u32;
    fn delete_texture(&self, id: u32);
-   fn get_uni_location(&self, id: u32, name: &str) -> u32;
+   fn get_uni_location(&self, id: u32, name: &str) -> NEOption<i32>;
+   fn active_uniforms(&self, id: u32) -> Vec<String>;
 
+   //by name lookups are silently ignored when missing, set_uni_at targets the bound program
    fn set_uni(&self, id: u32, name: &str, uniform: Uniform);
+   fn set_uni_at(&self, loc: i32, uniform: Uniform);
    fn set_uni_i32(&self, id: u32, name: &str, int: i32);
    fn set_uni_m4f32(&self, id: u32, name: &str, matrix: Matrix4<f32>);
 
@@ -67,15 +90,51 @@ pub(crate) trait Renderer {
    fn create_buffer(&self) -> (u32, u32);
    fn set_attr_layout(&self, info: &ATTRInfo, attr_id: u32, stride: usize, local_offset: usize);
    fn fill_buffer(&self, v_id: u32, b_id: u32, buffer: &Vec<u8>);
-   fn fill_index_buffer(&self, id: u32, buffer: &Vec<u32>);
+   //index data arrives packed in the mesh's IndexFormat
+   fn fill_index_buffer(&self, id: u32, buffer: &Vec<u8>);
+   //overwrite part of a filled buffer in place, offsets are in bytes
+   fn update_buffer(&self, v_id: u32, b_id: u32, offset: usize, data: &[u8]);
+   fn update_index_buffer(&self, id: u32, offset: usize, data: &[u8]);
    fn delete_buffer(&self, v_id: u32, b_id: u32);
    fn create_index_buffer(&self) -> u32;
    fn delete_index_buffer(&self, id: u32);
+   //extra vertex streams, bound next to the buffer create_buffer made for the vertex array
+   fn create_vertex_buffer(&self) -> u32;
+   fn delete_vertex_buffer(&self, id: u32);
+   //filled with fill_buffer against the vertex array it is drawn with
+   fn create_instance_buffer(&self) -> u32;
+   fn set_instance_layout(&self, info: &ATTRInfo, attr_id: u32, stride: usize, local_offset: usize);
+   //disables the location and resets its divisor so plain draws of the vertex array ignore it
+   fn clear_instance_layout(&self, attr_id: u32);
+   fn delete_instance_buffer(&self, id: u32);
 
    //DRAW
    fn clear(&self);
-   fn draw_indexed(&self, draw_mode: &DrawMode, index_count: u32);
+   fn draw_indexed(&self, draw_mode: &DrawMode, index_count: u32, format: IndexFormat);
    fn draw_array(&self, draw_mode: &DrawMode, vert_count: u32);
+   fn draw_indexed_instanced(
+      &self,
+      draw_mode: &DrawMode,
+      index_count: u32,
+      format: IndexFormat,
+      instances: u32,
+   );
+   fn draw_array_instanced(&self, draw_mode: &DrawMode, vert_count: u32, instances: u32);
+
+   //TARGETS
+   fn create_target(
+      &self,
+      size: Size2D,
+      colors: &[TexFormat],
+      depth: bool,
+   ) -> NEResult<(u32, Vec<u32>, u32)>;
+   fn bind_target(&self, id: u32);
+   fn unbind_target(&self);
+   fn delete_target(&self, id: u32, color_ids: &Vec<u32>, depth_id: u32);
+
+   //READBACK
+   fn read_color(&self, size: Size2D) -> Vec<u8>;
+   fn read_depth(&self, size: Size2D) -> Vec<f32>;
 }
 
 pub enum NERendererErrKing {
@@ -83,6 +142,17 @@ pub enum NERendererErrKing {
    GLSLCompileFailed,
    CreateShaderFailed,
    CreateProgramFailed,
+   TargetIncomplete,
+   UniformMissing,
+   SpirvInvalid,
+   LayoutMismatch,
+   BufferRange,
+}
+
+#[derive(Copy, Clone)]
+pub enum MissingUniform {
+   Warn,
+   Ignore,
 }
 
 pub struct NERenderer {
@@ -90,9 +160,14 @@ pub struct NERenderer {
 
    pub(crate) cam_view: Matrix4<f32>,
    pub(crate) cam_proj: Matrix4<f32>,
+   pub(crate) size: Size2D,
 
    pub fallback_shader: NEShader,
 
+   pub(crate) post_passes: Vec<NEShader>,
+   pub(crate) post_targets: Vec<NERenderTarget>,
+   pub(crate) post_quad: Option<NEMesh>,
+
    pub api: RenderAPI,
    pub poly_mode: PolyMode,
    pub cull_face: Cull,
@@ -100,12 +175,31 @@ pub struct NERenderer {
    pub msaa: bool,
    pub msaa_samples: u32,
    pub culling: bool,
+   pub depth_test: bool,
+   pub blend: bool,
+   pub blend_mode: BlendMode,
+   //defer blended meshes until every opaque one is drawn
+   pub sort_transparent: bool,
+   //queue every other mesh too and draw it sorted by state at post_update
+   pub batching: bool,
+   pub missing_uniform: MissingUniform,
+
+   pub(crate) transparent: RefCell<Vec<NEDrawCmd<'static>>>,
+   pub(crate) opaque: RefCell<Vec<NEDrawCmd<'static>>>,
+   pub(crate) binds: RefCell<NEBindCache>,
+   pub(crate) stats: RefCell<NEFrameStats>,
+
+   pub(crate) warned_uniforms: RefCell<HashSet<(u32, String)>>,
+   //(program, vertex array) pairs already run through validate_layout
+   pub(crate) checked_layouts: RefCell<HashSet<(u32, u32)>>,
+   pub(crate) resources: Rc<RefCell<NEResources>>,
 }
 //PRIVATE
 impl NERenderer {
    pub(crate) fn from(
       core: Box<dyn Renderer>,
       api: RenderAPI,
+      size: Size2D,
       cam_view: Matrix4<f32>,
       cam_proj: Matrix4<f32>,
    ) -> Self {
@@ -116,7 +210,11 @@ impl NERenderer {
          core,
          cam_view,
          cam_proj,
+         size,
          fallback_shader: NEShader::temporary(),
+         post_passes: Vec::new(),
+         post_targets: Vec::new(),
+         post_quad: None,
          api,
          poly_mode: PolyMode::Filled,
          cull_face: Cull::AntiClock,
@@ -124,8 +222,25 @@ impl NERenderer {
          msaa: true,
          msaa_samples: 4,
          culling: true,
+         depth_test: true,
+         blend: false,
+         blend_mode: BlendMode::Alpha,
+         sort_transparent: false,
+         batching: false,
+         missing_uniform: MissingUniform::Warn,
+         transparent: RefCell::new(Vec::new()),
+         opaque: RefCell::new(Vec::new()),
+         binds: RefCell::new(NEBindCache::default()),
+         stats: RefCell::new(NEFrameStats::default()),
+         warned_uniforms: RefCell::new(HashSet::new()),
+         checked_layouts: RefCell::new(HashSet::new()),
+         resources: Rc::new(RefCell::new(NEResources::new())),
+      };
+      let fallback_shader_asset = match api {
+         //cpu backend shades with a fixed-function fallback, no spirv needed
+         RenderAPI::Software => NEShaderAsset::empty("software-fallback"),
+         _ => NEShaderAsset::fallback().unpack(),
       };
-      let fallback_shader_asset = NEShaderAsset::fallback().unpack();
       renderer.fallback_shader = renderer.compile(fallback_shader_asset).unpack();
       renderer.set_msaa(true);
       renderer.set_culling(true);
@@ -133,7 +248,350 @@ impl NERenderer {
       renderer.set_bg_color(bg_color);
       renderer
    }
+   //engine provided uniforms are optional, shaders only declare the ones they use
+   fn set_builtin_uni(&self, shader: &NEShader, name: &str, uniform: Uniform) {
+      if let Some(loc) = shader.locations.get(name) {
+         self.core.set_uni_at(*loc, uniform)
+      }
+   }
+   fn set_shader_uni(&self, shader: &NEShader, name: &str, uniform: Uniform) {
+      match shader.locations.get(name) {
+         Some(loc) => self.core.set_uni_at(*loc, uniform),
+         None => self.missing_uni(shader, name),
+      }
+   }
+   //warns once per program and name, compilers strip unused uniforms
+   fn missing_uni(&self, shader: &NEShader, name: &str) {
+      if let MissingUniform::Ignore = self.missing_uniform {
+         return;
+      }
+      let key = (shader.id, name.to_string());
+      if self.warned_uniforms.borrow_mut().insert(key) {
+         NEError::uniform_missing(name, shader.id).log();
+      }
+   }
+   //later layers replace earlier bindings of the same sampler, units follow first appearance
+   fn bind_textures(&self, shader: &NEShader, layers: &[&[NETexBinding]], first_unit: u32) {
+      let mut merged: Vec<&NETexBinding> = shader.textures.iter().collect();
+      for layer in layers.iter() {
+         for binding in layer.iter() {
+            match merged.iter_mut().find(|b| b.sampler == binding.sampler) {
+               Some(b) => *b = binding,
+               None => merged.push(binding),
+            }
+         }
+      }
+      merged.retain(|b| self.guard_alive(b.gpu.as_deref()));
+      //nothing may sample a texture left bound by the previous draw
+      if merged.is_empty() && first_unit == 0 {
+         self.core.unbind_texture();
+         self.binds.borrow_mut().units.clear();
+      }
+      for (i, binding) in merged.iter().enumerate() {
+         let unit = first_unit + i as u32;
+         self.use_texture(binding.id, unit);
+         self.set_shader_uni(shader, &binding.sampler, Uniform::Sampler(unit));
+      }
+   }
+   //overrides are set before the draw and the renderer's own state is restored after it
+   fn apply_material_state(&self, mat: &NEMaterialState) {
+      if let Some(culling) = mat.culling {
+         self.core.enable_cull(culling);
+      }
+      if let Some(face) = mat.cull_face {
+         self.core.set_cull_face(face);
+      }
+      if let Some(depth) = mat.depth_test {
+         self.core.enable_depth(depth);
+      }
+      if let Some(mode) = mat.poly_mode {
+         self.core.poly_mode(mode);
+      }
+      if let Some(mode) = mat.blend {
+         self.core.enable_blend(true);
+         self.core.set_blend_mode(mode);
+      }
+   }
+   fn restore_material_state(&self, mat: &NEMaterialState) {
+      if mat.culling.is_some() {
+         self.core.enable_cull(self.culling);
+      }
+      if mat.cull_face.is_some() {
+         self.core.set_cull_face(self.cull_face);
+      }
+      if mat.depth_test.is_some() {
+         self.core.enable_depth(self.depth_test);
+      }
+      if mat.poly_mode.is_some() {
+         self.core.poly_mode(self.poly_mode);
+      }
+      if mat.blend.is_some() {
+         self.core.enable_blend(self.blend);
+         self.core.set_blend_mode(self.blend_mode);
+      }
+   }
+   fn guard(&self, res: NEGpuRes, label: &str) -> NEResGuard {
+      let handle = self.resources.borrow_mut().insert(res, label);
+      NEResGuard {
+         handle,
+         registry: Rc::downgrade(&self.resources),
+      }
+   }
+   //stale handles are ignored, so explicit deletes and later drops don't double free
+   fn release(&self, handle: NEHandle) {
+      let res = self.resources.borrow_mut().remove(handle);
+      if let Some(res) = res {
+         self.destroy(res)
+      }
+   }
+   //explicit deletes are dead for new draws right away, queued ones still use the gpu side
+   //until free_released runs after the flush
+   fn retire(&self, handle: NEHandle) {
+      self.resources.borrow_mut().retire(handle)
+   }
+   fn destroy(&self, res: NEGpuRes) {
+      match res {
+         NEGpuRes::Mesh { vao, vbo, ibo } => {
+            self.core.delete_buffer(vao, vbo);
+            self.core.delete_index_buffer(ibo);
+         }
+         NEGpuRes::Program(id) => self.core.delete_program(id),
+         NEGpuRes::Texture(id) => self.core.delete_texture(id),
+         NEGpuRes::Instances(id) => self.core.delete_instance_buffer(id),
+         NEGpuRes::Buffer(id) => self.core.delete_vertex_buffer(id),
+         NEGpuRes::Target { id, colors, depth } => self.core.delete_target(id, &colors, depth),
+      }
+   }
+   //frees everything whose last owner dropped since the previous call
+   fn free_released(&self) {
+      let pending = self.resources.borrow_mut().take_pending();
+      for handle in pending {
+         self.release(handle);
+      }
+      let retired = self.resources.borrow_mut().take_retired();
+      for res in retired {
+         self.destroy(res);
+      }
+   }
+   fn guard_alive(&self, guard: Option<&NEResGuard>) -> bool {
+      guard.map_or(true, |g| self.is_alive(g.handle))
+   }
+   fn uni_locations(&self, id: u32) -> HashMap<String, i32> {
+      let mut locations = HashMap::new();
+      for name in self.core.active_uniforms(id) {
+         if let NEOption::Exists(loc) = self.core.get_uni_location(id, &name) {
+            locations.insert(name, loc);
+         }
+      }
+      locations
+   }
+   fn cam_distance(&self, matrix: &Matrix4<f32>) -> f32 {
+      let cam = match self.cam_view.invert() {
+         Some(inv) => inv.w.truncate(),
+         None => return 0.0,
+      };
+      (matrix.w.truncate() - cam).magnitude2()
+   }
+   fn draw(&self, cmd: &NEDrawCmd) {
+      let material = cmd.material.as_ref().map(|m| m.state());
+      let shader = cmd.shader.as_ref();
+      //the camera is fixed for the frame, programs keep it until rebound
+      if self.use_program(shader.id) {
+         self.set_builtin_uni(shader, "uCamView", Uniform::Matrix4(self.cam_view));
+         self.set_builtin_uni(shader, "uCamProj", Uniform::Matrix4(self.cam_proj));
+      }
+      self.set_builtin_uni(shader, "uMeshTfm", Uniform::Matrix4(cmd.matrix));
+
+      //the mesh's values win over the material's, which win over the shader's
+      let empty = HashMap::new();
+      let mat_uniforms = material.as_ref().map_or(&empty, |m| &m.uniforms);
+      let mut merged: HashMap<&str, &Uniform> = HashMap::new();
+      for uniforms in [&shader.uniforms, mat_uniforms, cmd.uniforms.as_ref()] {
+         for (name, uniform) in uniforms.iter() {
+            merged.insert(name, uniform);
+         }
+      }
+      for (name, uniform) in merged {
+         self.set_shader_uni(shader, name, uniform.clone());
+      }
+      match &material {
+         Some(mat) => self.bind_textures(shader, &[&mat.textures, &cmd.textures], 0),
+         None => self.bind_textures(shader, &[&cmd.textures], 0),
+      }
+      if let Some(mat) = &material {
+         self.apply_material_state(mat);
+      }
+
+      self.use_buffer(cmd.buf_id);
+      match &cmd.instances {
+         None => match cmd.has_indices {
+            false => self.core.draw_array(&cmd.draw_mode, cmd.vert_count),
+            true => {
+               self.use_index_buffer(cmd.index_buf_id);
+               self
+                  .core
+                  .draw_indexed(&cmd.draw_mode, cmd.ind_count, cmd.index_format);
+            }
+         },
+         Some(inst) => self.draw_instanced(cmd, inst),
+      }
+      self.stats.borrow_mut().draw_calls += 1;
+      if let Some(mat) = &material {
+         self.restore_material_state(mat);
+      }
+   }
+   //draws now or queues, depending on batching and the material's blend
+   fn submit(&self, mesh: &mut NEMesh, instances: Option<&NEInstanceLayout>) {
+      if !mesh
+         .streams
+         .iter()
+         .all(|s| self.guard_alive(s.guard.as_deref()))
+      {
+         return;
+      }
+      mesh.update();
+      let mesh: &NEMesh = mesh;
+      let transparent = mesh.material.as_ref().map_or(false, |m| m.is_transparent());
+      let material = mesh.material.as_ref().map(|m| m.state());
+      let shader = match &material {
+         Some(mat) => &mat.shader,
+         None => &mesh.shader,
+      };
+      let alive = shader.exists_on_gpu && self.guard_alive(shader.guard.as_deref());
+      let shader = match alive {
+         false => &self.fallback_shader,
+         true => shader,
+      };
+      self.check_layout(shader, mesh);
+      let mut cmd = NEDrawCmd::from(mesh, shader, self.cam_distance(&mesh.matrix()));
+      cmd.instances = instances.map(Cow::Borrowed);
+      match (transparent && self.sort_transparent, self.batching) {
+         (true, _) => self.transparent.borrow_mut().push(cmd.into_owned()),
+         (false, true) => self.opaque.borrow_mut().push(cmd.into_owned()),
+         (false, false) => {
+            self.reset_binds();
+            self.draw(&cmd);
+         }
+      }
+   }
+   //each mesh is checked against each shader it is drawn with once, mismatches are warned about
+   //and the mesh is still drawn
+   fn check_layout(&self, shader: &NEShader, mesh: &NEMesh) {
+      if shader.info.attributes.is_empty() {
+         return;
+      }
+      let key = (shader.id, mesh.buf_id.0);
+      if !self.checked_layouts.borrow_mut().insert(key) {
+         return;
+      }
+      if let NEResult::ER(e) = self.validate_layout(shader, mesh) {
+         e.log_warn();
+      }
+   }
+   fn upload_instances(&self, mesh: &NEMesh, instances: &mut NEInstances) {
+      let (buffer, attrs, stride) = instances.buffer();
+      let buf_id = match &instances.layout {
+         Some(layout) => layout.buf_id,
+         None => {
+            let id = self.core.create_instance_buffer();
+            instances.guard = Some(self.guard(NEGpuRes::Instances(id), "instances"));
+            id
+         }
+      };
+      if !buffer.is_empty() {
+         self.core.fill_buffer(mesh.buf_id.0, buf_id, &buffer);
+         self.core.unbind_buffer();
+         self.reset_binds();
+      }
+      instances.layout = Some(NEInstanceLayout {
+         buf_id,
+         count: instances.count(),
+         stride,
+         attrs,
+      });
+      instances.dirty = false;
+   }
+   //instance layouts live in the mesh's vertex array, so they are set for every draw
+   //and cleared after it
+   fn draw_instanced(&self, cmd: &NEDrawCmd, inst: &NEInstanceLayout) {
+      self.use_buffer((cmd.buf_id.0, inst.buf_id));
+      for (location, info, offset) in inst.attrs.iter() {
+         self
+            .core
+            .set_instance_layout(info, *location, inst.stride, *offset);
+      }
+      match cmd.has_indices {
+         false => self
+            .core
+            .draw_array_instanced(&cmd.draw_mode, cmd.vert_count, inst.count),
+         true => {
+            self.use_index_buffer(cmd.index_buf_id);
+            self.core.draw_indexed_instanced(
+               &cmd.draw_mode,
+               cmd.ind_count,
+               cmd.index_format,
+               inst.count,
+            );
+         }
+      }
+      for (location, _, _) in inst.attrs.iter() {
+         self.core.clear_instance_layout(*location);
+      }
+   }
+   //bind helpers skip what the cache says is already bound, use_program reports whether it bound
+   fn use_program(&self, id: u32) -> bool {
+      let mut stats = self.stats.borrow_mut();
+      let mut binds = self.binds.borrow_mut();
+      if binds.program == Some(id) {
+         stats.skipped_binds += 1;
+         return false;
+      }
+      self.core.bind_program(id);
+      binds.program = Some(id);
+      stats.program_binds += 1;
+      true
+   }
+   fn use_texture(&self, id: u32, unit: u32) {
+      let mut stats = self.stats.borrow_mut();
+      let mut binds = self.binds.borrow_mut();
+      if binds.units.get(&unit) == Some(&id) {
+         stats.skipped_binds += 1;
+         return;
+      }
+      self.core.bind_texture_at(id, unit);
+      binds.units.insert(unit, id);
+      stats.texture_binds += 1;
+   }
+   fn use_buffer(&self, buf_id: (u32, u32)) {
+      let mut stats = self.stats.borrow_mut();
+      let mut binds = self.binds.borrow_mut();
+      if binds.buffer == Some(buf_id) {
+         stats.skipped_binds += 1;
+         return;
+      }
+      self.core.bind_buffer(buf_id.0, buf_id.1);
+      binds.buffer = Some(buf_id);
+      //the index buffer binding belongs to the vertex array
+      binds.index_buffer = None;
+      stats.buffer_binds += 1;
+   }
+   fn use_index_buffer(&self, id: u32) {
+      let mut stats = self.stats.borrow_mut();
+      let mut binds = self.binds.borrow_mut();
+      if binds.index_buffer == Some(id) {
+         stats.skipped_binds += 1;
+         return;
+      }
+      self.core.bind_index_buffer(id);
+      binds.index_buffer = Some(id);
+      stats.buffer_binds += 1;
+   }
+   //anything binding through core directly leaves the cache stale
+   fn reset_binds(&self) {
+      *self.binds.borrow_mut() = NEBindCache::default();
+   }
    pub(crate) fn set_size(&mut self, size: Size2D) {
+      self.size = size;
       self.core.resize(size);
    }
    fn clear(&self) {
@@ -143,9 +601,119 @@ impl NERenderer {
    pub(crate) fn pre_update(&mut self, cam: &NECamera) {
       self.cam_view = cam.view_matrix;
       self.cam_proj = cam.proj_matrix;
-      self.clear()
+      *self.stats.get_mut() = NEFrameStats::default();
+      self.clear();
+      if !self.post_passes.is_empty() && self.prepare_post() {
+         self.core.bind_target(self.post_targets[0].id);
+         self.core.resize(self.size);
+         self.clear();
+      }
+   }
+   pub(crate) fn post_update(&mut self, elapsed: f64) {
+      self.flush();
+      if !self.post_passes.is_empty() && !self.post_targets.is_empty() {
+         self.run_post(elapsed as f32);
+      }
+      self.free_released();
+   }
+
+   //frees the renderer's own resources, then reports whatever is still alive as leaked and frees it
+   pub(crate) fn end(&mut self) -> usize {
+      self.opaque.take();
+      self.transparent.take();
+      self.post_quad = None;
+      self.clear_post_passes();
+      self.fallback_shader = NEShader::temporary();
+      self.free_released();
+
+      let leaks = self.resources.borrow().live();
+      for (handle, label) in leaks.iter() {
+         log_warn!("leaked {label} {:?}", handle);
+         self.release(*handle);
+      }
+      leaks.len()
+   }
+
+   //scene and pass outputs ping-pong between two targets the size of the window,
+   //false when there are none this frame and the scene goes straight to the screen
+   fn prepare_post(&mut self) -> bool {
+      let stale = match self.post_targets.first() {
+         None => true,
+         Some(t) => t.size.w != self.size.w || t.size.h != self.size.h,
+      };
+      if stale {
+         for target in std::mem::take(&mut self.post_targets) {
+            self.delete_target(target);
+         }
+         //minimized windows, the passes pick up again once there is a size
+         if self.size.w == 0 || self.size.h == 0 {
+            return false;
+         }
+         for _ in 0..2 {
+            match self.render_target(self.size, &[TexFormat::RGBA(8)], true) {
+               NEResult::OK(t) => self.post_targets.push(t),
+               //warned rather than logged, the scene still reaches the screen without the passes
+               NEResult::ER(e) => {
+                  e.log_warn();
+                  for target in std::mem::take(&mut self.post_targets) {
+                     self.delete_target(target);
+                  }
+                  self.post_passes.clear();
+                  return false;
+               }
+            }
+         }
+      }
+      if self.post_quad.is_none() {
+         self.post_quad = Some(self.mesh(NEMeshAsset::fullscreen_quad()));
+      }
+      true
+   }
+   fn run_post(&self, elapsed: f32) {
+      let quad = match &self.post_quad {
+         None => return,
+         Some(q) => q,
+      };
+      self.core.enable_depth(false);
+      self.core.enable_cull(false);
+
+      let resolution = [self.size.w as f32, self.size.h as f32];
+      let last = self.post_passes.len() - 1;
+      for (i, pass) in self.post_passes.iter().enumerate() {
+         let src = &self.post_targets[i % 2];
+         match i == last {
+            true => self.core.unbind_target(),
+            false => self.core.bind_target(self.post_targets[(i + 1) % 2].id),
+         }
+         self.core.resize(self.size);
+         self.clear();
+
+         let s = pass.id;
+         self.core.bind_program(s);
+         self.core.bind_texture_at(src.color_ids[0], 0);
+         self.set_builtin_uni(pass, &post_source(pass), Uniform::Sampler(0));
+         self.bind_textures(pass, &[], 1);
+         for (name, uniform) in [
+            ("uResolution", Uniform::Vec2(resolution)),
+            ("uTime", Uniform::Float(elapsed)),
+            ("uPass", Uniform::Int(i as i32)),
+         ] {
+            self.set_builtin_uni(pass, name, uniform);
+         }
+         for (name, uniform) in pass.uniforms.iter() {
+            self.set_shader_uni(pass, name, uniform.clone());
+         }
+         self.core.bind_buffer(quad.buf_id.0, quad.buf_id.1);
+         self.core.bind_index_buffer(quad.index_buf_id);
+         self
+            .core
+            .draw_indexed(&quad.draw_mode, quad.ind_count, quad.index_format);
+      }
+
+      self.core.enable_depth(self.depth_test);
+      self.core.enable_cull(self.culling);
+      self.reset_binds();
    }
-   pub(crate) fn post_update(&self) {}
 }
 //PUBLIC
 impl NERenderer {
@@ -231,9 +799,111 @@ impl NERenderer {
       };
       self.core.set_cull_face(self.cull_face);
    }
+   pub fn set_depth_test(&mut self, enable: bool) {
+      self.depth_test = enable;
+      self.core.enable_depth(enable);
+   }
+   pub fn set_blend(&mut self, enable: bool) {
+      self.blend = enable;
+      self.core.enable_blend(enable);
+   }
+   pub fn set_blend_mode(&mut self, mode: BlendMode) {
+      self.blend_mode = mode;
+      self.core.set_blend_mode(mode);
+   }
+   pub fn set_sort_transparent(&mut self, enable: bool) {
+      self.sort_transparent = enable
+   }
+   pub fn set_batching(&mut self, enable: bool) {
+      self.batching = enable
+   }
+   pub fn set_missing_uniform(&mut self, policy: MissingUniform) {
+      self.missing_uniform = policy
+   }
    pub fn set_wire_width(&mut self, width: f32) {
       self.core.set_wire_width(width);
    }
+   pub fn read_color(&self) -> NETexture {
+      let bytes = self.core.read_color(self.size);
+      NETexture::from_raw(bytes, self.size, TexFormat::RGBA(8))
+   }
+   pub fn read_depth(&self) -> NETexture {
+      let mut bytes = Vec::new();
+      for depth in self.core.read_depth(self.size) {
+         let depth = (depth.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+         bytes.extend_from_slice(&depth.to_be_bytes());
+      }
+      NETexture::from_raw(bytes, self.size, TexFormat::R(16))
+   }
+   pub fn render_target(
+      &self,
+      size: Size2D,
+      colors: &[TexFormat],
+      depth: bool,
+   ) -> NEResult<NERenderTarget> {
+      match self.core.create_target(size, colors, depth) {
+         NEResult::ER(e) => NEResult::ER(e),
+         NEResult::OK((id, color_ids, depth_id)) => {
+            let res = NEGpuRes::Target {
+               id,
+               colors: color_ids.clone(),
+               depth: depth_id,
+            };
+            let label = format!("render target ({}x{})", size.w, size.h);
+            NEResult::OK(NERenderTarget {
+               id,
+               color_ids,
+               size,
+               formats: colors.to_vec(),
+               has_depth: depth,
+               guard: Some(Rc::new(self.guard(res, &label))),
+            })
+         }
+      }
+   }
+   //draws go to the target until unbind_target, the target is cleared on bind.
+   //queued draws are flushed first so they land in the target they were rendered to
+   pub fn bind_target(&mut self, target: &NERenderTarget) {
+      self.flush();
+      self.core.bind_target(target.id);
+      self.core.resize(target.size);
+      self.clear();
+   }
+   pub fn unbind_target(&mut self) {
+      self.flush();
+      self.core.unbind_target();
+      self.core.resize(self.size);
+   }
+   //bindings of its color_texture handles are skipped from now on
+   pub fn delete_target(&self, target: NERenderTarget) {
+      if let Some(guard) = &target.guard {
+         self.retire(guard.handle)
+      }
+   }
+   //passes run in order after the scene, each samples the previous output at texture unit 0
+   //through its first sampler without a texture of its own (tDif1 without reflection)
+   //and receives uResolution, uTime and uPass when it declares them
+   pub fn add_post_pass(&mut self, shader: NEShader) {
+      self.post_passes.push(shader)
+   }
+   pub fn post_pass_count(&self) -> usize {
+      self.post_passes.len()
+   }
+   pub fn clear_post_passes(&mut self) -> Vec<NEShader> {
+      for target in std::mem::take(&mut self.post_targets) {
+         self.delete_target(target);
+      }
+      std::mem::take(&mut self.post_passes)
+   }
+   //the gpu copy lives until every shader, mesh and texture holding it is dropped
+   pub fn upload_texture(&self, tex: &mut NETexture) {
+      if tex.exists_on_gpu || !tex.exists {
+         return;
+      }
+      tex.id = self.core.create_texture(tex);
+      tex.exists_on_gpu = true;
+      tex.gpu = Some(Rc::new(self.guard(NEGpuRes::Texture(tex.id), "texture")));
+   }
    pub fn fallback_shader(&self) -> NEShader {
       self.fallback_shader.clone()
    }
@@ -252,161 +922,342 @@ impl NERenderer {
          }
       };
 
-      self.core.bind_program(id);
-
-      let mut image_ids = Vec::new();
-      //for (i, texture) in asset.textures.iter().enumerate() {
-      //   if texture.exists {
-      //      let name = format!("tDif{}", i + 1);
-      //      let tex_id = self.core.create_texture(texture);
-      //      self.core.set_uni_i32(prog_id, &name, i);
-      //      image_ids.push(tex_id);
-      //   }
-      //}
+      let mut problems = nshdr.info.interface_mismatches();
+      problems.extend(nshdr.info.attribute_mismatches(INSTANCE_LOCATION));
+      if !problems.is_empty() {
+         self.core.delete_program(id);
+         return NEResult::ER(NEError::layout_mismatch(&nshdr.path, problems));
+      }
+      let guard = self.guard(NEGpuRes::Program(id), &format!("shader {}", nshdr.path));
       NEResult::OK(NEShader {
          id,
-         image_ids,
+         textures: Vec::new(),
+         uniforms: HashMap::new(),
+         locations: self.uni_locations(id),
+         info: nshdr.info,
          exists_on_gpu: true,
+         guard: Some(Rc::new(guard)),
       })
    }
+   //compiles and checks the vertex inputs against the attributes the mesh uploads
+   pub fn compile_for(&self, nshdr: NEShaderAsset, mesh: &NEMesh) -> NEResult<NEShader> {
+      let path = nshdr.path.clone();
+      let shader = match self.compile(nshdr) {
+         NEResult::ER(e) => return NEResult::ER(e),
+         NEResult::OK(s) => s,
+      };
+      match self.validate_layout(&shader, mesh) {
+         NEResult::OK(_) => NEResult::OK(shader),
+         NEResult::ER(e) => {
+            self.delete_shader(shader);
+            NEResult::ER(match e {
+               NEError::Renderer { kind, msg, .. } => NEError::Renderer { kind, path, msg },
+               _ => e,
+            })
+         }
+      }
+   }
+   pub fn validate_layout(&self, shader: &NEShader, mesh: &NEMesh) -> NEResult<()> {
+      let mut problems = Vec::new();
+      for input in shader.info.attributes.iter() {
+         //instance inputs come from NEInstances, see render_instanced
+         let location = match input.location {
+            Some(l) if l < INSTANCE_LOCATION => l,
+            _ => continue,
+         };
+         let attr = match mesh.attrs.iter().find(|(id, _, _)| *id == location) {
+            None => {
+               problems.push(format!(
+                  "'{}' expects a {} at location {location}, the mesh has none",
+                  input.name,
+                  input.typ.glsl_name()
+               ));
+               continue;
+            }
+            Some((_, info, _)) => info,
+         };
+         if attr.elem_count as u32 != input.typ.components() {
+            problems.push(format!(
+               "'{}' is {} but location {location} holds {}x{}",
+               input.name,
+               input.typ.glsl_name(),
+               attr.typ_str,
+               attr.elem_count
+            ));
+         }
+         let expected = match attr.kind {
+            ATTRKind::Float | ATTRKind::Normalized => SpvScalar::Float,
+            ATTRKind::Double => SpvScalar::Double,
+            ATTRKind::Integer => match attr.typ {
+               ATTRType::U8 | ATTRType::U16 | ATTRType::U32 => SpvScalar::UInt,
+               _ => SpvScalar::Int,
+            },
+         };
+         if input.typ.scalar() != Some(expected) {
+            problems.push(format!(
+               "'{}' is {} but location {location} holds {:?} {} data",
+               input.name,
+               input.typ.glsl_name(),
+               attr.kind,
+               attr.typ_str
+            ));
+         }
+      }
+      match problems.is_empty() {
+         true => NEResult::OK(()),
+         false => NEResult::ER(NEError::layout_mismatch("", problems)),
+      }
+   }
+   //explicit deletes are dead right away, the gpu side goes after the queued draws at post_update
+   pub fn delete_instances(&self, instances: NEInstances) {
+      if let Some(guard) = &instances.guard {
+         self.retire(guard.handle)
+      }
+   }
+   //other clones of the shader draw with the fallback from now on
    pub fn delete_shader(&self, shader: NEShader) {
-      self.core.delete_shader(shader.id)
+      if let Some(guard) = &shader.guard {
+         self.retire(guard.handle)
+      }
+   }
+   //bindings of the texture elsewhere are skipped from now on
+   pub fn delete_texture(&self, tex: NETexture) {
+      if let Some(guard) = &tex.gpu {
+         self.retire(guard.handle)
+      }
+   }
+   //meshes sharing one of its streams are skipped from now on
+   pub fn delete_mesh(&self, mesh: NEMesh) {
+      if let Some(guard) = &mesh.guard {
+         self.retire(guard.handle)
+      }
+      for stream in mesh.streams.iter().filter(|s| !s.shared) {
+         if let Some(guard) = &stream.guard {
+            self.retire(guard.handle)
+         }
+      }
+   }
+   pub fn is_alive(&self, handle: NEHandle) -> bool {
+      self.resources.borrow().is_alive(handle)
+   }
+   pub fn live_resources(&self) -> usize {
+      self.resources.borrow().live().len()
    }
-   pub fn mesh(&self, mut asset: NEMeshAsset) -> NEMesh {
+   pub fn mesh(&self, asset: NEMeshAsset) -> NEMesh {
+      self.build_mesh(asset, false, None)
+   }
+   //keeps a cpu copy of the vertex and index data for partial updates and resizes
+   pub fn dynamic_mesh(&self, asset: NEMeshAsset) -> NEMesh {
+      self.build_mesh(asset, true, None)
+   }
+   //binds a stream of another mesh, its attributes win over the asset's at the same locations
+   pub fn shared_mesh(&self, asset: NEMeshAsset, from: &NEMesh, stream: usize) -> NEMesh {
+      self.build_mesh(asset, false, Some((from, stream)))
+   }
+   fn build_mesh(
+      &self,
+      mut asset: NEMeshAsset,
+      keep_cpu: bool,
+      shared: Option<(&NEMesh, usize)>,
+   ) -> NEMesh {
       let (vao_id, bfo_id) = self.core.create_buffer();
       let i_id = self.core.create_index_buffer();
 
-      let (mut pos_info, mut pos_data) = (ATTRInfo::empty(), &Vec::new());
-      let (mut col_info, mut col_data) = (ATTRInfo::empty(), &Vec::new());
-      let (mut uvm_info, mut uvm_data) = (ATTRInfo::empty(), &Vec::new());
-      let (mut nrm_info, mut nrm_data) = (ATTRInfo::empty(), &Vec::new());
       let (mut ind_info, mut ind_data) = (ATTRInfo::empty(), &Vec::new());
+      let (mut ind_count, mut vert_count) = (0, 0);
 
-      let mut cus_infos: Vec<ATTRInfo> = Vec::new();
-      let mut cus_datas: Vec<&Vec<u8>> = Vec::new();
-
-      let (mut ind_count, mut vert_count, mut stride) = (0, 0, 0);
-
-      let mut pos_exists = asset.pos_attr.has_data();
-      let mut col_exists = asset.col_attr.has_data();
-      let mut uvm_exists = asset.uvm_attr.has_data();
-      let mut nrm_exists = asset.nrm_attr.has_data();
-      let mut cus_exists = asset.has_custom_attrs();
-
-      if pos_exists {
-         pos_info = asset.pos_attr.info();
-         pos_data = asset.pos_attr.data();
-         stride += pos_info.elem_count * pos_info.byte_count;
-      }
-      if col_exists {
-         col_info = asset.col_attr.info();
-         col_data = asset.col_attr.data();
-         stride += col_info.elem_count * col_info.byte_count;
-      }
-      if uvm_exists {
-         uvm_info = asset.uvm_attr.info();
-         uvm_data = asset.uvm_attr.data();
-         stride += uvm_info.elem_count * uvm_info.byte_count;
-      }
-      if nrm_exists {
-         nrm_info = asset.nrm_attr.info();
-         nrm_data = asset.nrm_attr.data();
-         stride += nrm_info.elem_count * nrm_info.byte_count;
-      }
-      if cus_exists {
-         for cus_attr in asset.cus_attrs.iter() {
-            let cus_info = cus_attr.info();
-            let cus_data = cus_attr.data();
-            stride += cus_info.elem_count * cus_info.byte_count;
-            cus_infos.push(cus_info);
-            cus_datas.push(cus_data);
-         }
-      }
-      let mut end = pos_data.len();
-      if cus_exists && asset.starts_with_custom() {
-         end = cus_datas[0].len() / (cus_infos[0].byte_count * cus_infos[0].elem_count);
-      }
-
-      let mut buffer: Vec<u8> = Vec::new();
-      for i in 0..end {
-         vert_count += 1;
-         if pos_exists {
-            buffer.push_attr(&pos_data[i]);
-         }
-         if col_exists {
-            buffer.push_attr(&col_data[i]);
-         }
-         if uvm_exists {
-            buffer.push_attr(&uvm_data[i]);
-         }
-         if nrm_exists {
-            buffer.push_attr(&nrm_data[i]);
-         }
-         if cus_exists {
-            for (j, _attr) in asset.cus_attrs.iter().enumerate() {
-               let cus_byte_count = cus_infos[j].byte_count * cus_infos[j].elem_count;
-               let cus_data = cus_datas[j];
-               let start = i * cus_byte_count;
-               let end = ((i + 1) * (cus_byte_count)) - 1;
-               buffer.push_attr(&cus_data[start..=end]);
-            }
+      //every attribute the asset has, in layout order
+      let (builtin_locs, cus_locs) = asset.attr_locations();
+      let mut sources: Vec<(VertexAttr, String, ATTRInfo, Vec<u8>, u32)> = Vec::new();
+      if let Some(location) = builtin_locs[0] {
+         let mut bytes = Vec::new();
+         bytes.push_attr(asset.pos_attr.data());
+         let info = asset.pos_attr.info();
+         sources.push((
+            VertexAttr::Pos,
+            "pos attr".to_string(),
+            info,
+            bytes,
+            location,
+         ));
+      }
+      if let Some(location) = builtin_locs[1] {
+         let mut bytes = Vec::new();
+         bytes.push_attr(asset.col_attr.data());
+         let info = asset.col_attr.info();
+         sources.push((
+            VertexAttr::Col,
+            "col attr".to_string(),
+            info,
+            bytes,
+            location,
+         ));
+      }
+      if let Some(location) = builtin_locs[2] {
+         let mut bytes = Vec::new();
+         bytes.push_attr(asset.uvm_attr.data());
+         let info = asset.uvm_attr.info();
+         sources.push((
+            VertexAttr::UVM,
+            "uvm attr".to_string(),
+            info,
+            bytes,
+            location,
+         ));
+      }
+      if let Some(location) = builtin_locs[3] {
+         let mut bytes = Vec::new();
+         bytes.push_attr(asset.nrm_attr.data());
+         let info = asset.nrm_attr.info();
+         sources.push((
+            VertexAttr::Nrm,
+            "nrm attr".to_string(),
+            info,
+            bytes,
+            location,
+         ));
+      }
+      if let Some(location) = builtin_locs[4] {
+         let mut bytes = Vec::new();
+         bytes.push_attr(asset.tan_attr.data());
+         let info = asset.tan_attr.info();
+         sources.push((
+            VertexAttr::Tan,
+            "tan attr".to_string(),
+            info,
+            bytes,
+            location,
+         ));
+      }
+      for (i, cus_attr) in asset.cus_attrs.iter().enumerate() {
+         if cus_attr.has_data() {
+            let label = match cus_attr.name() {
+               None => format!("custom attr {i}"),
+               Some(n) => format!("custom attr {i} '{n}'"),
+            };
+            let data = cus_attr.data().clone();
+            sources.push((
+               VertexAttr::Custom(i),
+               label,
+               cus_attr.info(),
+               data,
+               cus_locs[i],
+            ));
          }
       }
 
-      let mut attr_id = 0;
-      let mut local_offset = 0;
-      self.core.bind_buffer(vao_id, bfo_id);
       let mut layouts: Vec<String> = Vec::new();
-      if pos_info.exists {
-         self
-            .core
-            .set_attr_layout(&pos_info, attr_id, stride, local_offset);
-         local_offset += pos_info.elem_count * pos_info.byte_count;
-         layouts.push(format!("pos attr (f32x3): {:?}", attr_id));
-         attr_id += 1;
-      }
-      if col_info.exists {
-         self
-            .core
-            .set_attr_layout(&col_info, attr_id, stride, local_offset);
-         local_offset += col_info.elem_count * col_info.byte_count;
-         layouts.push(format!("col attr (f32x3): {:?}", attr_id));
-         attr_id += 1;
-      }
-      if uvm_info.exists {
-         self
-            .core
-            .set_attr_layout(&uvm_info, attr_id, stride, local_offset);
-         local_offset += uvm_info.elem_count * uvm_info.byte_count;
-         layouts.push(format!("uvm attr (f32x2): {:?}", attr_id));
-         attr_id += 1;
+      let mut attrs: Vec<(u32, ATTRInfo, usize)> = Vec::new();
+      let mut streams: Vec<NEVertexStream> = Vec::new();
+      if let Some((from, index)) = shared {
+         match from.streams.get(index) {
+            None => {
+               log_warn!("the shared mesh has no vertex stream {index}");
+            }
+            Some(stream) => {
+               self.core.bind_buffer(vao_id, stream.buf_id);
+               for (location, info, offset) in from.attrs.iter() {
+                  if stream.locations.contains(location) {
+                     self
+                        .core
+                        .set_attr_layout(info, *location, stream.stride, *offset);
+                     attrs.push((*location, info.clone(), *offset));
+                     layouts.push(format!("shared attr ({}): {:?}", info.typ_str, location));
+                  }
+               }
+               layouts.push(format!("stream 0 (shared): {} bytes", stream.stride));
+               vert_count = from.vert_count;
+               streams.push(NEVertexStream {
+                  buf_id: stream.buf_id,
+                  stride: stream.stride,
+                  locations: stream.locations.clone(),
+                  cpu: None,
+                  shared: true,
+                  guard: stream.guard.clone(),
+                  verts: stream.verts.clone(),
+               });
+            }
+         }
       }
-      if nrm_info.exists {
-         self
-            .core
-            .set_attr_layout(&nrm_info, attr_id, stride, local_offset);
-         local_offset += nrm_info.elem_count * nrm_info.byte_count;
-         layouts.push(format!("nrm attr (f32x3): {:?}", attr_id));
-         attr_id += 1;
+      let mut used: Vec<u32> = attrs.iter().map(|(id, _, _)| *id).collect();
+      sources.retain(|(_, label, _, _, location)| {
+         let taken = used.contains(location);
+         match taken {
+            true => {
+               log_warn!("{label} skipped, location {location} is already used");
+            }
+            false => used.push(*location),
+         }
+         !taken
+      });
+
+      //the shortest attribute decides the vertex count
+      let own_count = sources
+         .iter()
+         .map(|(_, _, info, data, _)| data.len() / (info.byte_count * info.elem_count).max(1))
+         .min()
+         .unwrap_or(0);
+      if shared.is_none() {
+         vert_count = own_count as u32;
+      } else if !sources.is_empty() && own_count != vert_count as usize {
+         log_warn!("mesh has {own_count} verts but the shared stream has {vert_count}");
       }
 
-      for (i, cus_info) in cus_infos.iter().enumerate() {
-         if cus_info.exists {
+      let present: Vec<VertexAttr> = sources.iter().map(|s| s.0.clone()).collect();
+      let own_first = streams.len();
+      for (g, group) in asset.vertex_layout.group(&present).iter().enumerate() {
+         let (b_id, guard) = match g {
+            0 => (bfo_id, None),
+            _ => {
+               let id = self.core.create_vertex_buffer();
+               let guard = self.guard(NEGpuRes::Buffer(id), "vertex stream");
+               (id, Some(Rc::new(guard)))
+            }
+         };
+         let sizes: Vec<usize> = group
+            .iter()
+            .map(|i| sources[*i].2.byte_count * sources[*i].2.elem_count)
+            .collect();
+         let stride: usize = sizes.iter().sum();
+
+         let mut buffer: Vec<u8> = Vec::with_capacity(own_count * stride);
+         for v in 0..own_count {
+            for (i, size) in group.iter().zip(sizes.iter()) {
+               buffer.extend_from_slice(&sources[*i].3[v * size..(v + 1) * size]);
+            }
+         }
+
+         self.core.bind_buffer(vao_id, b_id);
+         let mut local_offset = 0;
+         let mut locations = Vec::new();
+         for (i, size) in group.iter().zip(sizes.iter()) {
+            let (_, label, info, _, location) = &sources[*i];
             self
                .core
-               .set_attr_layout(&cus_info, attr_id, stride, local_offset);
-            local_offset += nrm_info.elem_count * nrm_info.byte_count;
-            let format = cus_info.typ_str.clone();
-            layouts.push(format!("custom attr {i}({format}): {:?}", attr_id));
-            attr_id += 1;
+               .set_attr_layout(info, *location, stride, local_offset);
+            attrs.push((*location, info.clone(), local_offset));
+            locations.push(*location);
+            layouts.push(format!("{label} ({}): {:?}", info.typ_str, location));
+            local_offset += size;
          }
-      }
-      if buffer.len() > 0 {
-         self.core.fill_buffer(vao_id, bfo_id, &buffer);
+         if buffer.len() > 0 {
+            self.core.fill_buffer(vao_id, b_id, &buffer);
+         }
+         layouts.push(format!("stream {}: {stride} bytes", streams.len()));
+         streams.push(NEVertexStream {
+            buf_id: b_id,
+            stride,
+            locations,
+            cpu: keep_cpu.then_some(buffer),
+            shared: false,
+            guard,
+            verts: Rc::new(Cell::new(own_count as u32)),
+         });
       }
       self.core.unbind_buffer();
 
       let mut index_buffer: Vec<u32> = Vec::new();
+      let mut index_format = IndexFormat::U8;
       if asset.indices.has_data() {
          ind_info = asset.indices.info();
          ind_data = asset.indices.data();
@@ -415,52 +1266,300 @@ impl NERenderer {
             ind_count += 1;
             index_buffer.push(*index);
          }
+         index_format = IndexFormat::fitting(index_buffer.iter().copied().max().unwrap_or(0));
          self.core.bind_index_buffer(i_id);
-         self.core.fill_index_buffer(i_id, &index_buffer);
+         self
+            .core
+            .fill_index_buffer(i_id, &index_format.pack(&index_buffer));
          self.core.unbind_index_buffer();
       }
+
+      //the first own stream lives in the buffer create_buffer made, it goes with the mesh
+      let guard = Rc::new(self.guard(
+         NEGpuRes::Mesh {
+            vao: vao_id,
+            vbo: bfo_id,
+            ibo: i_id,
+         },
+         &format!("mesh ({vert_count} verts)"),
+      ));
+      if let Some(stream) = streams.get_mut(own_first) {
+         stream.guard = Some(guard.clone());
+      }
+      let first_buf = streams.first().map_or(bfo_id, |s| s.buf_id);
       asset.transform.calc_matrix();
       NEMesh {
          alive: true,
          visible: true,
          shader: self.fallback_shader(),
+         uniforms: HashMap::new(),
+         textures: Vec::new(),
+         material: None,
          has_indices: ind_info.exists,
          vert_count,
          ind_count,
+         index_format,
          is_empty: false,
          layouts,
-         buf_id: (vao_id, bfo_id),
+         attrs,
+         streams,
+         cpu_indices: keep_cpu.then_some(index_buffer),
+         guard: Some(guard),
+         buf_id: (vao_id, first_buf),
          index_buf_id: i_id,
          transform: asset.transform.clone(),
-         draw_mode: DrawMode::Triangles,
+         draw_mode: asset.draw_mode,
+      }
+   }
+   //writes data for one attribute from vertex `first` on, the element type must match the mesh's
+   pub fn update_attr<T: DataType + 'static>(
+      &self,
+      mesh: &mut NEMesh,
+      location: u32,
+      first: usize,
+      data: &[T],
+   ) -> NEResult<()> {
+      let (info, offset) = match mesh.attrs.iter().find(|(id, _, _)| *id == location) {
+         None => {
+            let msg = format!("the mesh has no attribute at location {location}");
+            return NEResult::ER(NEError::buffer_range(msg));
+         }
+         Some((_, info, offset)) => (info.clone(), *offset),
+      };
+      let vao = mesh.buf_id.0;
+      let vert_count = mesh.vert_count as usize;
+      let stream = match mesh.stream_of(location) {
+         None => {
+            let msg = format!("no vertex stream holds location {location}");
+            return NEResult::ER(NEError::buffer_range(msg));
+         }
+         Some(s) => &mut mesh.streams[s],
+      };
+      if data.is_empty() {
+         return NEResult::OK(());
+      }
+      let (_, typ_str, _, _) = get_format(&data[0]);
+      if typ_str != info.typ_str {
+         let msg = format!(
+            "location {location} holds {} but the update is {typ_str}",
+            info.typ_str
+         );
+         return NEResult::ER(NEError::layout_mismatch("", vec![msg]));
+      }
+      if first + data.len() > vert_count {
+         return NEResult::ER(NEError::buffer_range(format!(
+            "vertices {first}..{} of {vert_count}",
+            first + data.len()
+         )));
+      }
+      let stride = stream.stride;
+      match &mut stream.cpu {
+         //patch the copy and send the touched span in one go
+         Some(cpu) => {
+            for (i, elem) in data.iter().enumerate() {
+               let at = (first + i) * stride + offset;
+               let bytes = elem.u8ify();
+               cpu[at..at + bytes.len()].copy_from_slice(&bytes);
+            }
+            let start = first * stride;
+            let end = (first + data.len()) * stride;
+            self
+               .core
+               .update_buffer(vao, stream.buf_id, start, &cpu[start..end]);
+         }
+         //without a copy the neighbouring attributes are unknown, so write element by element
+         None => {
+            for (i, elem) in data.iter().enumerate() {
+               let at = (first + i) * stride + offset;
+               self
+                  .core
+                  .update_buffer(vao, stream.buf_id, at, &elem.u8ify());
+            }
+         }
+      }
+      self.core.unbind_buffer();
+      self.reset_binds();
+      NEResult::OK(())
+   }
+   //overwrites whole interleaved vertices of the first stream from vertex `first` on
+   pub fn update_vertices(&self, mesh: &mut NEMesh, first: usize, bytes: &[u8]) -> NEResult<()> {
+      self.update_stream(mesh, 0, first, bytes)
+   }
+   pub fn update_stream(
+      &self,
+      mesh: &mut NEMesh,
+      stream: usize,
+      first: usize,
+      bytes: &[u8],
+   ) -> NEResult<()> {
+      let vao = mesh.buf_id.0;
+      let vert_count = mesh.vert_count as usize;
+      let stream = match mesh.streams.get_mut(stream) {
+         None => {
+            let msg = format!("the mesh has no vertex stream {stream}");
+            return NEResult::ER(NEError::buffer_range(msg));
+         }
+         Some(s) => s,
+      };
+      let stride = stream.stride;
+      if stride == 0 || bytes.len() % stride != 0 {
+         return NEResult::ER(NEError::buffer_range(format!(
+            "{} bytes is not a whole number of {stride} byte vertices",
+            bytes.len()
+         )));
+      }
+      let count = bytes.len() / stride;
+      if first + count > vert_count {
+         return NEResult::ER(NEError::buffer_range(format!(
+            "vertices {first}..{} of {vert_count}",
+            first + count
+         )));
       }
+      let start = first * stride;
+      if let Some(cpu) = &mut stream.cpu {
+         cpu[start..start + bytes.len()].copy_from_slice(bytes);
+      }
+      self.core.update_buffer(vao, stream.buf_id, start, bytes);
+      self.core.unbind_buffer();
+      self.reset_binds();
+      NEResult::OK(())
+   }
+   pub fn update_indices(&self, mesh: &mut NEMesh, first: usize, indices: &[u32]) -> NEResult<()> {
+      if first + indices.len() > mesh.ind_count as usize {
+         return NEResult::ER(NEError::buffer_range(format!(
+            "indices {first}..{} of {}",
+            first + indices.len(),
+            mesh.ind_count
+         )));
+      }
+      let widest = indices.iter().copied().max().unwrap_or(0);
+      if widest > mesh.index_format.max() && mesh.cpu_indices.is_none() {
+         return NEResult::ER(NEError::buffer_range(format!(
+            "index {widest} does not fit {:?} without a cpu copy",
+            mesh.index_format
+         )));
+      }
+      if let Some(cpu) = &mut mesh.cpu_indices {
+         cpu[first..first + indices.len()].copy_from_slice(indices);
+      }
+      //a wider index means repacking the whole buffer from the cpu copy
+      if widest > mesh.index_format.max() {
+         mesh.index_format = IndexFormat::fitting(widest);
+         let cpu = mesh.cpu_indices.as_ref().unwrap();
+         self
+            .core
+            .fill_index_buffer(mesh.index_buf_id, &mesh.index_format.pack(cpu));
+      } else {
+         let size = mesh.index_format.size();
+         self.core.update_index_buffer(
+            mesh.index_buf_id,
+            first * size,
+            &mesh.index_format.pack(indices),
+         );
+      }
+      self.core.unbind_index_buffer();
+      self.reset_binds();
+      NEResult::OK(())
+   }
+   //reallocates in place, contents survive only when the mesh keeps a cpu copy, new space is zeroed
+   //shared streams belong to the mesh they came from and are left alone,
+   //the count is clamped to the smallest of them
+   pub fn resize_vertices(&self, mesh: &mut NEMesh, count: u32) {
+      let shared = mesh
+         .streams
+         .iter()
+         .filter(|s| s.shared)
+         .map(|s| s.verts.get())
+         .min();
+      let count = match shared {
+         Some(limit) if count > limit => {
+            log_warn!("resize to {count} vertices clamped to the {limit} of a shared stream");
+            limit
+         }
+         _ => count,
+      };
+      for stream in mesh.streams.iter_mut().filter(|s| !s.shared) {
+         let size = count as usize * stream.stride;
+         let buffer = match &mut stream.cpu {
+            Some(cpu) => {
+               cpu.resize(size, 0);
+               cpu.clone()
+            }
+            None => vec![0; size],
+         };
+         self.core.fill_buffer(mesh.buf_id.0, stream.buf_id, &buffer);
+         stream.verts.set(count);
+      }
+      self.core.unbind_buffer();
+      self.reset_binds();
+      mesh.vert_count = count;
+      mesh.is_empty = count == 0;
+   }
+   pub fn resize_indices(&self, mesh: &mut NEMesh, count: u32) {
+      let buffer = match &mut mesh.cpu_indices {
+         Some(cpu) => {
+            cpu.resize(count as usize, 0);
+            cpu.clone()
+         }
+         None => vec![0; count as usize],
+      };
+      self
+         .core
+         .fill_index_buffer(mesh.index_buf_id, &mesh.index_format.pack(&buffer));
+      self.core.unbind_index_buffer();
+      self.reset_binds();
+      mesh.ind_count = count;
+      mesh.has_indices = count > 0;
    }
    pub fn render(&self, mesh: &mut NEMesh) {
       if !mesh.visible || !mesh.alive {
          return;
       }
-      mesh.update();
-      let s = match mesh.shader.exists_on_gpu {
-         false => self.fallback_shader.id,
-         true => mesh.shader.id,
-      };
-      self.core.bind_program(s);
-      self.core.set_uni_m4f32(s, "uCamView", self.cam_view);
-      self.core.set_uni_m4f32(s, "uCamProj", self.cam_proj);
-      self.core.set_uni_m4f32(s, "uMeshTfm", mesh.matrix());
-
-      for (i, t) in mesh.shader.image_ids.iter().enumerate() {
-         self.core.bind_texture_at(*t, i as u32);
+      self.submit(mesh, None);
+   }
+   //draws every instance of the mesh in one call, see NEInstances for the shader inputs
+   pub fn render_instanced(&self, mesh: &mut NEMesh, instances: &mut NEInstances) {
+      if !mesh.visible || !mesh.alive || instances.count() == 0 {
+         return;
       }
-
-      self.core.bind_buffer(mesh.buf_id.0, mesh.buf_id.1);
-      match mesh.has_indices {
-         false => self.core.draw_array(&mesh.draw_mode, mesh.vert_count),
-         true => {
-            self.core.bind_index_buffer(mesh.index_buf_id);
-            self.core.draw_indexed(&mesh.draw_mode, mesh.ind_count);
-         }
+      let clash: Vec<String> = mesh
+         .attrs
+         .iter()
+         .filter(|(id, _, _)| *id >= INSTANCE_LOCATION)
+         .map(|(id, _, _)| format!("mesh attribute at location {id} overlaps the instance data"))
+         .collect();
+      //the draw is skipped, the rest of the frame still goes out
+      if !clash.is_empty() {
+         NEError::layout_mismatch("", clash).log_warn();
+         return;
+      }
+      if instances.dirty {
+         self.upload_instances(mesh, instances);
+      }
+      self.submit(mesh, instances.layout.as_ref());
+   }
+   //draws the queued meshes, opaque ones grouped by state then transparent ones farthest first
+   pub fn flush(&self) {
+      let mut opaque = self.opaque.take();
+      let mut transparent = self.transparent.take();
+      sort_by_state(&mut opaque);
+      sort_back_to_front(&mut transparent);
+      self.reset_binds();
+      for cmd in opaque.iter().chain(transparent.iter()) {
+         self.draw(cmd);
       }
+      self.reset_binds();
+   }
+   pub fn frame_stats(&self) -> NEFrameStats {
+      *self.stats.borrow()
+   }
+}
+
+fn post_source(pass: &NEShader) -> String {
+   let own = |name: &str| pass.textures.iter().any(|b| b.sampler == name);
+   match pass.info.samplers.iter().find(|s| !own(&s.name)) {
+      Some(sampler) => sampler.name.clone(),
+      None => "tDif1".to_string(),
    }
 }
 
@@ -0,0 +1,132 @@
+use std::cell::RefCell;
+use std::rc::Weak;
+
+//generation-checked id of a gpu resource, it goes stale once the resource is released
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NEHandle {
+   pub(crate) index: u32,
+   pub(crate) generation: u32,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum NEGpuRes {
+   Mesh {
+      vao: u32,
+      vbo: u32,
+      ibo: u32,
+   },
+   Program(u32),
+   Texture(u32),
+   Instances(u32),
+   Buffer(u32),
+   Target {
+      id: u32,
+      colors: Vec<u32>,
+      depth: u32,
+   },
+}
+
+struct NESlot {
+   generation: u32,
+   res: Option<(NEGpuRes, String)>,
+}
+
+//every live gpu resource the renderer created, slots are reused with a bumped generation
+pub(crate) struct NEResources {
+   slots: Vec<NESlot>,
+   free: Vec<u32>,
+   //released by dropped owners, freed at the next post_update
+   pending: Vec<NEHandle>,
+   //deleted explicitly, dead for new draws but freed after the queued ones at post_update
+   retired: Vec<NEGpuRes>,
+}
+
+impl NEResources {
+   pub(crate) fn new() -> NEResources {
+      NEResources {
+         slots: Vec::new(),
+         free: Vec::new(),
+         pending: Vec::new(),
+         retired: Vec::new(),
+      }
+   }
+   pub(crate) fn insert(&mut self, res: NEGpuRes, label: &str) -> NEHandle {
+      let entry = Some((res, label.to_string()));
+      match self.free.pop() {
+         Some(index) => {
+            let slot = &mut self.slots[index as usize];
+            slot.res = entry;
+            NEHandle {
+               index,
+               generation: slot.generation,
+            }
+         }
+         None => {
+            self.slots.push(NESlot {
+               generation: 0,
+               res: entry,
+            });
+            NEHandle {
+               index: self.slots.len() as u32 - 1,
+               generation: 0,
+            }
+         }
+      }
+   }
+   pub(crate) fn is_alive(&self, handle: NEHandle) -> bool {
+      match self.slots.get(handle.index as usize) {
+         None => false,
+         Some(slot) => slot.generation == handle.generation && slot.res.is_some(),
+      }
+   }
+   //None for stale handles, so releasing twice is harmless
+   pub(crate) fn remove(&mut self, handle: NEHandle) -> Option<NEGpuRes> {
+      if !self.is_alive(handle) {
+         return None;
+      }
+      let slot = &mut self.slots[handle.index as usize];
+      slot.generation = slot.generation.wrapping_add(1);
+      self.free.push(handle.index);
+      slot.res.take().map(|(res, _)| res)
+   }
+   pub(crate) fn retire(&mut self, handle: NEHandle) {
+      if let Some(res) = self.remove(handle) {
+         self.retired.push(res)
+      }
+   }
+   pub(crate) fn take_pending(&mut self) -> Vec<NEHandle> {
+      std::mem::take(&mut self.pending)
+   }
+   pub(crate) fn take_retired(&mut self) -> Vec<NEGpuRes> {
+      std::mem::take(&mut self.retired)
+   }
+   pub(crate) fn live(&self) -> Vec<(NEHandle, String)> {
+      let mut live = Vec::new();
+      for (i, slot) in self.slots.iter().enumerate() {
+         if let Some((_, label)) = &slot.res {
+            let handle = NEHandle {
+               index: i as u32,
+               generation: slot.generation,
+            };
+            live.push((handle, label.clone()));
+         }
+      }
+      live
+   }
+}
+
+//owning side of a handle, dropping it queues the resource for release
+#[derive(Debug)]
+pub(crate) struct NEResGuard {
+   pub(crate) handle: NEHandle,
+   //weak so owners outliving the renderer don't keep its registry around
+   pub(crate) registry: Weak<RefCell<NEResources>>,
+}
+
+impl Drop for NEResGuard {
+   fn drop(&mut self) {
+      if let Some(registry) = self.registry.upgrade() {
+         registry.borrow_mut().pending.push(self.handle)
+      }
+   }
+}
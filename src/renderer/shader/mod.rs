@@ -1,2 +1,4 @@
 mod shader;
+mod texture;
 pub use shader::*;
+pub(crate) use texture::*;
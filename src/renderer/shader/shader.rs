@@ -1,23 +1,164 @@
-use cgmath::Matrix4;
+use crate::renderer::resource::NEResGuard;
+use crate::renderer::shader::{set_binding, NETexBinding};
+use crate::{NEHandle, NEShaderInfo, NETexture};
+use cgmath::{Matrix2, Matrix3, Matrix4};
+use std::collections::HashMap;
+use std::rc::Rc;
 
 #[derive(Clone, Debug)]
 pub struct NEShader {
    pub(crate) id: u32,
-   pub(crate) image_ids: Vec<u32>,
+   pub(crate) textures: Vec<NETexBinding>,
+   pub(crate) uniforms: HashMap<String, Uniform>,
+   //resolved once at link time
+   pub(crate) locations: HashMap<String, i32>,
+   pub(crate) info: NEShaderInfo,
    pub(crate) exists_on_gpu: bool,
+   //shared by clones, the program is released when the last one drops
+   pub(crate) guard: Option<Rc<NEResGuard>>,
 }
 
 impl NEShader {
    pub(crate) fn temporary() -> NEShader {
       NEShader {
          id: 0,
-         image_ids: Vec::new(),
+         textures: Vec::new(),
+         uniforms: HashMap::new(),
+         locations: HashMap::new(),
+         info: NEShaderInfo::empty(),
          exists_on_gpu: false,
+         guard: None,
       }
    }
+
+   //applied every time a mesh is rendered with this shader, meshes can override
+   pub fn set_uniform(&mut self, name: &str, uniform: Uniform) {
+      self.uniforms.insert(name.to_string(), uniform);
+   }
+   pub fn remove_uniform(&mut self, name: &str) -> Option<Uniform> {
+      self.uniforms.remove(name)
+   }
+   pub fn uniform(&self, name: &str) -> Option<&Uniform> {
+      self.uniforms.get(name)
+   }
+   pub fn info(&self) -> &NEShaderInfo {
+      &self.info
+   }
+   pub fn handle(&self) -> Option<NEHandle> {
+      self.guard.as_ref().map(|g| g.handle)
+   }
+   pub fn has_uniform(&self, name: &str) -> bool {
+      self.locations.contains_key(name)
+   }
+
+   //fills the shader's samplers in declaration order, tDif1, tDif2.. without reflection
+   pub fn attach_texture(&mut self, tex: &NETexture) -> bool {
+      let n = self.textures.len();
+      let sampler = match self.info.samplers.get(n) {
+         Some(s) => s.name.clone(),
+         None => format!("tDif{}", n + 1),
+      };
+      self.set_texture(&sampler, tex)
+   }
+   //textures are bound to units in attach order, the texture must be uploaded first
+   pub fn set_texture(&mut self, sampler: &str, tex: &NETexture) -> bool {
+      match NETexBinding::from(sampler, tex) {
+         None => false,
+         Some(binding) => {
+            set_binding(&mut self.textures, binding);
+            true
+         }
+      }
+   }
+   pub fn remove_texture(&mut self, sampler: &str) {
+      self.textures.retain(|b| b.sampler != sampler)
+   }
 }
 
+#[derive(Clone, Debug)]
 pub enum Uniform {
-   Matrix4(Matrix4<f32>),
    Int(i32),
+   IVec2([i32; 2]),
+   IVec3([i32; 3]),
+   IVec4([i32; 4]),
+
+   UInt(u32),
+   UVec2([u32; 2]),
+   UVec3([u32; 3]),
+   UVec4([u32; 4]),
+
+   Float(f32),
+   Vec2([f32; 2]),
+   Vec3([f32; 3]),
+   Vec4([f32; 4]),
+
+   Bool(bool),
+   //texture unit the sampler reads from
+   Sampler(u32),
+
+   Matrix2(Matrix2<f32>),
+   Matrix3(Matrix3<f32>),
+   Matrix4(Matrix4<f32>),
+
+   IntArray(Vec<i32>),
+   IVec2Array(Vec<[i32; 2]>),
+   IVec3Array(Vec<[i32; 3]>),
+   IVec4Array(Vec<[i32; 4]>),
+   UIntArray(Vec<u32>),
+   UVec2Array(Vec<[u32; 2]>),
+   UVec3Array(Vec<[u32; 3]>),
+   UVec4Array(Vec<[u32; 4]>),
+   FloatArray(Vec<f32>),
+   Vec2Array(Vec<[f32; 2]>),
+   Vec3Array(Vec<[f32; 3]>),
+   Vec4Array(Vec<[f32; 4]>),
+   BoolArray(Vec<bool>),
+   Matrix2Array(Vec<Matrix2<f32>>),
+   Matrix3Array(Vec<Matrix3<f32>>),
+   Matrix4Array(Vec<Matrix4<f32>>),
+}
+
+impl Uniform {
+   pub(crate) fn is_empty_array(&self) -> bool {
+      match self {
+         Uniform::IntArray(a) => a.is_empty(),
+         Uniform::IVec2Array(a) => a.is_empty(),
+         Uniform::IVec3Array(a) => a.is_empty(),
+         Uniform::IVec4Array(a) => a.is_empty(),
+         Uniform::UIntArray(a) => a.is_empty(),
+         Uniform::UVec2Array(a) => a.is_empty(),
+         Uniform::UVec3Array(a) => a.is_empty(),
+         Uniform::UVec4Array(a) => a.is_empty(),
+         Uniform::FloatArray(a) => a.is_empty(),
+         Uniform::Vec2Array(a) => a.is_empty(),
+         Uniform::Vec3Array(a) => a.is_empty(),
+         Uniform::Vec4Array(a) => a.is_empty(),
+         Uniform::BoolArray(a) => a.is_empty(),
+         Uniform::Matrix2Array(a) => a.is_empty(),
+         Uniform::Matrix3Array(a) => a.is_empty(),
+         Uniform::Matrix4Array(a) => a.is_empty(),
+         _ => false,
+      }
+   }
+   pub fn glsl_type(&self) -> &'static str {
+      match self {
+         Uniform::Int(_) | Uniform::IntArray(_) => "int",
+         Uniform::IVec2(_) | Uniform::IVec2Array(_) => "ivec2",
+         Uniform::IVec3(_) | Uniform::IVec3Array(_) => "ivec3",
+         Uniform::IVec4(_) | Uniform::IVec4Array(_) => "ivec4",
+         Uniform::UInt(_) | Uniform::UIntArray(_) => "uint",
+         Uniform::UVec2(_) | Uniform::UVec2Array(_) => "uvec2",
+         Uniform::UVec3(_) | Uniform::UVec3Array(_) => "uvec3",
+         Uniform::UVec4(_) | Uniform::UVec4Array(_) => "uvec4",
+         Uniform::Float(_) | Uniform::FloatArray(_) => "float",
+         Uniform::Vec2(_) | Uniform::Vec2Array(_) => "vec2",
+         Uniform::Vec3(_) | Uniform::Vec3Array(_) => "vec3",
+         Uniform::Vec4(_) | Uniform::Vec4Array(_) => "vec4",
+         Uniform::Bool(_) | Uniform::BoolArray(_) => "bool",
+         Uniform::Sampler(_) => "sampler2D",
+         Uniform::Matrix2(_) | Uniform::Matrix2Array(_) => "mat2",
+         Uniform::Matrix3(_) | Uniform::Matrix3Array(_) => "mat3",
+         Uniform::Matrix4(_) | Uniform::Matrix4Array(_) => "mat4",
+      }
+   }
 }
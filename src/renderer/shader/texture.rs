@@ -0,0 +1,31 @@
+use crate::renderer::resource::NEResGuard;
+use crate::NETexture;
+use std::rc::Rc;
+
+#[derive(Clone, Debug)]
+pub(crate) struct NETexBinding {
+   pub(crate) sampler: String,
+   pub(crate) id: u32,
+   //empty for textures owned elsewhere, like render target attachments
+   pub(crate) gpu: Option<Rc<NEResGuard>>,
+}
+
+impl NETexBinding {
+   pub(crate) fn from(sampler: &str, tex: &NETexture) -> Option<NETexBinding> {
+      match tex.exists_on_gpu {
+         false => None,
+         true => Some(NETexBinding {
+            sampler: sampler.to_string(),
+            id: tex.id,
+            gpu: tex.gpu.clone(),
+         }),
+      }
+   }
+}
+
+pub(crate) fn set_binding(bindings: &mut Vec<NETexBinding>, binding: NETexBinding) {
+   match bindings.iter_mut().find(|b| b.sampler == binding.sampler) {
+      Some(b) => *b = binding,
+      None => bindings.push(binding),
+   }
+}
@@ -1,6 +1,8 @@
 pub mod rgba;
+mod snapshot;
 mod transform;
 
 pub use rgba::RGB;
 pub use rgba::RGBA;
+pub use snapshot::*;
 pub use transform::*;
@@ -0,0 +1,147 @@
+use crate::asset::file;
+use crate::asset::TexFormat;
+use crate::{ansi, log_warn, NEOption, NEResult, NETexture};
+use std::path::PathBuf;
+
+pub struct NEImageDiff {
+   pub mismatched: usize,
+   pub max_delta: u8,
+   pub size_matches: bool,
+   //red where pixels differ, dimmed grey of the frame elsewhere
+   pub diff: NETexture,
+}
+
+impl NEImageDiff {
+   pub fn is_match(&self) -> bool {
+      self.size_matches && self.mismatched == 0
+   }
+}
+
+impl NETexture {
+   pub fn diff(&self, other: &NETexture, tolerance: u8) -> NEImageDiff {
+      let size = self.size;
+      let size_matches = size.w == other.size.w && size.h == other.size.h;
+      let (a, b) = (self.rgba8(), other.rgba8());
+
+      let mut mismatched = 0;
+      let mut max_delta = 0;
+      let mut diff = Vec::with_capacity(a.len());
+      for (i, pa) in a.chunks(4).enumerate() {
+         let pb = match size_matches {
+            true => &b[i * 4..i * 4 + 4],
+            false => &[0, 0, 0, 0],
+         };
+         let mut delta = 0;
+         for c in 0..4 {
+            delta = delta.max(pa[c].abs_diff(pb[c]));
+         }
+         max_delta = max_delta.max(delta);
+         if delta > tolerance || !size_matches {
+            mismatched += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+         } else {
+            let grey = ((pa[0] as u16 + pa[1] as u16 + pa[2] as u16) / 12) as u8;
+            diff.extend_from_slice(&[grey, grey, grey, 255]);
+         }
+      }
+      NEImageDiff {
+         mismatched,
+         max_delta,
+         size_matches,
+         diff: NETexture::from_raw(diff, size, TexFormat::RGBA(8)),
+      }
+   }
+
+   //compares against a golden png, a missing golden is written from this frame.
+   //on mismatch <golden>.diff.png and <golden>.actual.png are written next to it
+   pub fn match_golden(&self, golden_path: &str, tolerance: u8) -> NEResult<NEImageDiff> {
+      if !file::exists_on_disk(golden_path) {
+         log_warn!("golden [{golden_path}] does not exist, writing current frame");
+         return match self.write_png(golden_path) {
+            NEResult::ER(e) => NEResult::ER(e),
+            NEResult::OK(_) => NEResult::OK(self.diff(self, tolerance)),
+         };
+      }
+      let golden = match NETexture::read_png(golden_path) {
+         NEResult::ER(e) => return NEResult::ER(e),
+         NEResult::OK(g) => g,
+      };
+      let diff = self.diff(&golden, tolerance);
+      if diff.is_match() {
+         return NEResult::OK(diff);
+      }
+
+      let name = match file::name(golden_path) {
+         NEOption::Empty => "golden".to_string(),
+         NEOption::Exists(n) => n,
+      };
+      let dir = match PathBuf::from(golden_path).parent() {
+         None => PathBuf::new(),
+         Some(p) => p.to_path_buf(),
+      };
+      let diff_path = dir.join(format!("{name}.diff.png"));
+      let actual_path = dir.join(format!("{name}.actual.png"));
+      if let NEResult::ER(e) = diff.diff.write_png(&diff_path.to_string_lossy()) {
+         return NEResult::ER(e);
+      }
+      if let NEResult::ER(e) = self.write_png(&actual_path.to_string_lossy()) {
+         return NEResult::ER(e);
+      }
+      NEResult::OK(diff)
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use crate::*;
+
+   fn solid(size: u32, rgba: [u8; 4]) -> NETexture {
+      let bytes = rgba.repeat((size * size) as usize);
+      NETexture::from_raw(bytes, Size2D::from(size, size), TexFormat::RGBA(8))
+   }
+
+   #[test]
+   fn diff_respects_tolerance() {
+      let a = solid(4, [100, 100, 100, 255]);
+      let b = solid(4, [103, 100, 100, 255]);
+      assert!(a.diff(&b, 3).is_match());
+      let d = a.diff(&b, 2);
+      assert_eq!((d.mismatched, d.max_delta), (16, 3));
+      assert!(!a.diff(&solid(2, [100, 100, 100, 255]), 255).size_matches);
+   }
+
+   #[test]
+   fn golden_is_written_then_compared() {
+      let dir = std::env::temp_dir().join("nerve-snapshot");
+      let _ = std::fs::remove_dir_all(&dir);
+      let golden = dir.join("frame.png").to_string_lossy().to_string();
+      let frame = solid(4, [10, 20, 30, 255]);
+      assert!(frame.match_golden(&golden, 0).unpack().is_match());
+      assert!(frame.match_golden(&golden, 0).unpack().is_match());
+
+      let other = solid(4, [10, 200, 30, 255]);
+      assert!(!other.match_golden(&golden, 0).unpack().is_match());
+      let actual = NETexture::read_png(&dir.join("frame.actual.png").to_string_lossy()).unpack();
+      assert_eq!(actual.bytes, other.bytes);
+      assert!(dir.join("frame.diff.png").exists());
+   }
+
+   #[test]
+   fn readback_matches_clear_color() {
+      let size = Size2D::from(4, 2);
+      let cam = NECamera::from(size, CamProj::Persp);
+      let core = crate::renderer::core::sw_renderer_init(size);
+      let mut r = NERenderer::from(
+         Box::new(core),
+         RenderAPI::Software,
+         size,
+         cam.view_matrix,
+         cam.proj_matrix,
+      );
+      r.set_bg_color(RGB(1.0, 0.0, 1.0));
+      r.pre_update(&cam);
+      let frame = r.read_color();
+      assert_eq!(frame.bytes, [255, 0, 255, 255].repeat(8));
+      assert_eq!(r.read_depth().bytes.len(), 8 * 2);
+   }
+}
@@ -1,4 +1,4 @@
-use cgmath::{vec3, Deg, Matrix4, Rad, SquareMatrix, Vector3};
+use cgmath::{vec3, Deg, InnerSpace, Matrix4, Rad, SquareMatrix, Vector3};
 use std::ops::Add;
 
 #[derive(Clone)]
@@ -51,6 +51,41 @@ impl Transform {
          ..Default::default()
       }
    }
+   //splits an affine matrix into translation, xyz euler degrees and scale, shear is lost
+   pub(crate) fn from_matrix(matrix: Matrix4<f32>) -> Self {
+      let mut scale = vec3(
+         matrix.x.truncate().magnitude(),
+         matrix.y.truncate().magnitude(),
+         matrix.z.truncate().magnitude(),
+      );
+      if matrix.determinant() < 0.0 {
+         scale.x = -scale.x;
+      }
+      let unscale = |v: Vector3<f32>, s: f32| if s != 0.0 { v / s } else { v };
+      let (c0, c1, c2) = (
+         unscale(matrix.x.truncate(), scale.x),
+         unscale(matrix.y.truncate(), scale.y),
+         unscale(matrix.z.truncate(), scale.z),
+      );
+      //rot = rx * ry * rz, see calc_matrix
+      let y = c2.x.clamp(-1.0, 1.0).asin();
+      let (x, z) = match c2.x.abs() < 0.9999 {
+         true => ((-c2.y).atan2(c2.z), (-c1.x).atan2(c0.x)),
+         false => (c1.z.atan2(c1.y), 0.0),
+      };
+      let mut transform = Self {
+         pos: matrix.w.truncate(),
+         rot: vec3(
+            Deg::from(Rad(x)).0,
+            Deg::from(Rad(y)).0,
+            Deg::from(Rad(z)).0,
+         ),
+         scale,
+         ..Default::default()
+      };
+      transform.calc_matrix();
+      transform
+   }
 
    pub(crate) fn calc_matrix(&mut self) {
       let pos_matrix = Matrix4::<f32>::from_translation(self.pos);
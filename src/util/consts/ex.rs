@@ -3,6 +3,10 @@ pub(crate) const VERT: &str = "vert";
 pub(crate) const FRAG: &str = "frag";
 pub(crate) const GLSL: &str = "glsl";
 pub(crate) const OBJ: &str = "obj";
+pub(crate) const GLTF: &str = "gltf";
+pub(crate) const GLB: &str = "glb";
+pub(crate) const PLY: &str = "ply";
+pub(crate) const STL: &str = "stl";
 pub(crate) const PNG: &str = "png";
 
 //NERVE FILE FORMATS
@@ -3,7 +3,7 @@ use crate::asset::NEAssetErrKind;
 use crate::engine::NEInitErrKind;
 use crate::util::consts::ansi;
 use crate::util::misc;
-use crate::{env, log_fatal, log_warn, proc, NEOpenGLErrKind, NERendererErrKing};
+use crate::{env, log_fatal, log_warn, proc, ATTRKind, NEOpenGLErrKind, NERendererErrKing};
 
 #[derive(Copy, Clone)]
 pub enum NEErrorSeverity {
@@ -75,6 +75,13 @@ impl NEError {
       }
    }
 
+   pub(crate) fn file_couldnt_write(path: &str) -> NEError {
+      NEError::File {
+         kind: NEFileErrKind::CouldNotWrite,
+         path: path.to_string(),
+      }
+   }
+
    //ASSET
    pub(crate) fn vert_missing(path: &str) -> NEError {
       NEError::Asset {
@@ -90,13 +97,27 @@ impl NEError {
       }
    }
 
-   pub(crate) fn non_triangulated(path: &str, line: String) -> NEError {
+   pub(crate) fn mesh_invalid(path: &str, msg: &str) -> NEError {
       NEError::Asset {
-         kind: NEAssetErrKind::NonTriangle(line),
+         kind: NEAssetErrKind::MeshInvalid(msg.to_string()),
          path: path.to_string(),
       }
    }
 
+   pub(crate) fn attr_kind_invalid(typ_str: &str, kind: ATTRKind) -> NEError {
+      NEError::Asset {
+         kind: NEAssetErrKind::AttrKind(format!("{kind:?} does not fit {typ_str}")),
+         path: "".to_string(),
+      }
+   }
+
+   pub(crate) fn attr_missing(msg: &str) -> NEError {
+      NEError::Asset {
+         kind: NEAssetErrKind::AttrMissing(msg.to_string()),
+         path: "".to_string(),
+      }
+   }
+
    //RENDERER
    pub(crate) fn no_glsl_validator(path: &str) -> NEError {
       NEError::Renderer {
@@ -122,6 +143,43 @@ impl NEError {
       }
    }
 
+   pub(crate) fn target_incomplete(msg: String) -> NEError {
+      NEError::Renderer {
+         kind: NERendererErrKing::TargetIncomplete,
+         path: "".to_string(),
+         msg,
+      }
+   }
+
+   pub(crate) fn spirv_invalid(path: &str, msg: &str) -> NEError {
+      NEError::Renderer {
+         kind: NERendererErrKing::SpirvInvalid,
+         path: path.to_string(),
+         msg: msg.to_string(),
+      }
+   }
+   pub(crate) fn layout_mismatch(path: &str, problems: Vec<String>) -> NEError {
+      NEError::Renderer {
+         kind: NERendererErrKing::LayoutMismatch,
+         path: path.to_string(),
+         msg: problems.join("; "),
+      }
+   }
+   pub(crate) fn uniform_missing(name: &str, program: u32) -> NEError {
+      NEError::Renderer {
+         kind: NERendererErrKing::UniformMissing,
+         path: format!("program {program}"),
+         msg: format!("'{name}' is not active in the shader"),
+      }
+   }
+   pub(crate) fn buffer_range(msg: String) -> NEError {
+      NEError::Renderer {
+         kind: NERendererErrKing::BufferRange,
+         path: "".to_string(),
+         msg,
+      }
+   }
+
    //UTIL
    pub(crate) fn cstring_failed(msg: &str) -> NEError {
       NEError::Util {
@@ -189,7 +247,9 @@ impl NEError {
             let kind_msg = match kind {
                NEAssetErrKind::VertEmpty => "has no vertex src",
                NEAssetErrKind::FragEmpty => "has no fragment src",
-               NEAssetErrKind::NonTriangle(line) => &format!("mesh not triangulated at ({line})"),
+               NEAssetErrKind::MeshInvalid(msg) => &format!("invalid mesh, {msg}"),
+               NEAssetErrKind::AttrKind(msg) => &format!("invalid attribute kind, {msg}"),
+               NEAssetErrKind::AttrMissing(msg) => &format!("missing attribute, {msg}"),
             };
             severe = NEErrorSeverity::Fatal;
             format!("(asset) -> {kind_msg}! [{path}]")
@@ -204,8 +264,17 @@ impl NEError {
                NERendererErrKing::GLSLCompileFailed => "compilation failed",
                NERendererErrKing::CreateProgramFailed => "program creation failed",
                NERendererErrKing::CreateShaderFailed => "shader creation failed",
+               NERendererErrKing::TargetIncomplete => "render target incomplete",
+               NERendererErrKing::UniformMissing => "uniform missing",
+               NERendererErrKing::SpirvInvalid => "invalid spirv module",
+               NERendererErrKing::LayoutMismatch => "layout mismatch",
+               NERendererErrKing::BufferRange => "buffer update out of range",
+            };
+            severe = match kind {
+               NERendererErrKing::UniformMissing => NEErrorSeverity::Warn,
+               NERendererErrKing::BufferRange => NEErrorSeverity::Warn,
+               _ => NEErrorSeverity::Fatal,
             };
-            severe = NEErrorSeverity::Fatal;
             if msg.len() == 0 {
                format!("(spirv) -> {kind_msg}! [{path}]")
             } else {
@@ -226,6 +295,11 @@ impl NEError {
       };
       (severe, format!("NERVE ERROR: {msg}"))
    }
+   //for errors the caller recovers from, whatever their severity
+   pub(crate) fn log_warn(&self) {
+      let (_, msg) = self.msg();
+      log_warn!("{msg}");
+   }
    pub fn log(&self) {
       let (severe, msg) = self.msg();
       match severe {